@@ -0,0 +1,103 @@
+use std::{env, fs, io::Read, process};
+use rand::rngs::OsRng;
+use serde_json::json;
+
+
+
+// NOTE - the voter and trustee side of encrypted proposals, it uses the same elgamal file as the server
+// NOTE - `deal <threshold> <trustees>` makes a key with a trusted dealer for trying things out, put its `encryption` into the /add request and give every trustee its secret
+// NOTE - `encrypt <public key> <proposal id> <wallet> <upvote|downvote|abstain> <score>` prints the body of /cast-encrypted-vote
+// NOTE - `share <proposal id> <trustee> <secret> [file | -]` reads the response of /proposal/{id}/encryption once it's closed and prints the body of /proposal/{id}/decryption-share
+
+
+
+#[path = "../src/utils/elgamal.rs"]
+#[allow(dead_code)]
+mod elgamal;
+
+
+
+const CHOICES: [&str; 3] = ["upvote", "downvote", "abstain"]; //-- same order as the server
+
+
+
+fn fail(message: &str) -> !{
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+
+
+fn main(){
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<&str>>();
+    let body = match args.as_slice(){
+        ["deal", threshold, trustees] => deal(threshold.parse().unwrap_or_else(|_| fail("bad threshold")), trustees.parse().unwrap_or_else(|_| fail("bad number of trustees"))),
+        ["encrypt", public_key, proposal_id, wallet, choice, score] => encrypt(public_key, proposal_id, wallet, choice, score.parse().unwrap_or_else(|_| fail("bad score"))),
+        ["share", proposal_id, trustee, secret, rest @ ..] => share(proposal_id, trustee.parse().unwrap_or_else(|_| fail("bad trustee")), secret, rest.first().copied().unwrap_or("-")),
+        _ => fail("usage: elgamal_ballot deal <threshold> <trustees> | encrypt <public key> <proposal id> <wallet> <upvote|downvote|abstain> <score> | share <proposal id> <trustee> <secret> [file | -]"),
+    };
+    println!("{}", serde_json::to_string_pretty(&body).unwrap());
+}
+
+
+
+fn deal(threshold: usize, trustees: u64) -> serde_json::Value{
+    if threshold == 0 || threshold as u64 > trustees{
+        fail("the threshold must be between 1 and the number of trustees");
+    }
+    let (commitments, secrets) = elgamal::deal(threshold, trustees, &mut OsRng);
+    json!({
+        "encryption": {
+            "threshold": threshold,
+            "trustees": trustees,
+            "commitments": commitments.into_iter().map(elgamal::Point).collect::<Vec<_>>(),
+        },
+        "secrets": secrets.into_iter().enumerate().map(|(i, secret)| json!({"trustee": i + 1, "secret": elgamal::Exponent(secret)})).collect::<Vec<_>>(),
+    })
+}
+
+
+
+fn encrypt(public_key: &str, proposal_id: &str, wallet: &str, choice: &str, score: u32) -> serde_json::Value{
+    let public_key: elgamal::Point = serde_json::from_value(json!(public_key)).unwrap_or_else(|e| fail(&format!("bad public key: {}", e)));
+    let choice = CHOICES.iter().position(|c| *c == choice).unwrap_or_else(|| fail("the choice is upvote, downvote or abstain"));
+    let context = elgamal::ballot_context(proposal_id, wallet);
+    let (ciphertexts, proof) = elgamal::encrypt_ballot(public_key.0, CHOICES.len(), choice, &context, &mut OsRng);
+    json!({
+        "_id": proposal_id,
+        "wallet_address": wallet,
+        "score": score,
+        "ciphertexts": ciphertexts,
+        "proof": proof,
+    })
+}
+
+
+
+fn share(proposal_id: &str, trustee: u64, secret: &str, path: &str) -> serde_json::Value{
+    let secret: elgamal::Exponent = serde_json::from_value(json!(secret)).unwrap_or_else(|e| fail(&format!("bad secret: {}", e)));
+    let input = match path{
+        "-" => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input).expect("can't read stdin");
+            input
+        },
+        path => fs::read_to_string(path).unwrap_or_else(|e| fail(&format!("can't read {}: {}", path, e))),
+    };
+    let json: serde_json::Value = serde_json::from_str(&input).unwrap_or_else(|e| fail(&format!("invalid json: {}", e)));
+    let status = json.get("data").cloned().unwrap_or(json);
+    let choices = status["encrypted_tally"]["choices"].as_array().cloned().unwrap_or_else(|| fail("no encrypted tally yet, the proposal must be closed"));
+    let context = elgamal::share_context(proposal_id, trustee);
+    let mut shares = vec![];
+    for choice in choices{
+        for field in ["headcount", "weight"]{ //-- same order as EncryptedTally::ciphertexts() on the server
+            let ciphertext: elgamal::Ciphertext = serde_json::from_value(choice[field].clone()).unwrap_or_else(|e| fail(&format!("bad ciphertext: {}", e)));
+            shares.push(elgamal::decryption_share(&secret.0, &ciphertext, &context, &mut OsRng));
+        }
+    }
+    json!({
+        "trustee": trustee,
+        "shares": shares,
+    })
+}
@@ -0,0 +1,56 @@
+use std::{env, fs, io::Read, process};
+use ed25519_dalek::{PublicKey, Signature, Verifier};
+
+
+
+// NOTE - checks the signature of a result attestation without trusting the server, the public key should come from somewhere you trust (GET /auth/attestation/keys once, a partner, the docs)
+// NOTE - `cargo run --example verify_attestation -- attestation.json <public key hex>`, the file can be the bare attestation or the whole response of /proposal/{id}/attestation, `-` reads stdin
+
+
+
+fn main(){
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let input = match args.first().map(String::as_str){
+        Some("-") | None => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input).expect("can't read stdin");
+            input
+        },
+        Some(path) => fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("can't read {}: {}", path, e);
+            process::exit(2);
+        }),
+    };
+    let public_key = match args.get(1).and_then(|k| hex::decode(k).ok()).and_then(|k| PublicKey::from_bytes(&k).ok()){
+        Some(public_key) => public_key,
+        None => {
+            eprintln!("usage: verify_attestation <attestation.json | -> <public key hex>");
+            process::exit(2);
+        },
+    };
+    let json: serde_json::Value = serde_json::from_str(&input).unwrap_or_else(|e| {
+        eprintln!("invalid json: {}", e);
+        process::exit(2);
+    });
+    let attestation = json.get("data").cloned().unwrap_or(json);
+    let (document, signature) = match (attestation["document"].as_str(), attestation["signature"].as_str()){
+        (Some(document), Some(signature)) => (document, signature),
+        _ => {
+            eprintln!("not an attestation, expected the document and signature fields");
+            process::exit(2);
+        },
+    };
+    let signature = match hex::decode(signature).ok().and_then(|s| Signature::from_bytes(&s).ok()){
+        Some(signature) => signature,
+        None => {
+            println!("INVALID - malformed signature");
+            process::exit(1);
+        },
+    };
+    if public_key.verify(document.as_bytes(), &signature).is_err(){ //-- over the document string exactly as it was signed
+        println!("INVALID - the document wasn't signed by this key");
+        process::exit(1);
+    }
+    let result: serde_json::Value = serde_json::from_str(document).unwrap_or_default();
+    println!("ok - proposal {} signed by key {}: outcome {} with {} total weight, merkle root {}", result["proposal_id"], attestation["key_id"], result["outcome"], result["total_weight"], result["merkle_root"]);
+}
@@ -0,0 +1,52 @@
+
+
+use std::{env, fs, io::Read, process};
+
+
+
+// NOTE - checks a ballot inclusion proof without talking to the server, the merkle code is the same file the server uses
+// NOTE - `cargo run --example verify_ballot_proof -- proof.json [expected root]`, the file can be the bare proof or the whole response of /proposal/{id}/proof/{wallet}, `-` reads stdin
+// NOTE - pass the root you got from the outcome (or a partner) as the second argument, otherwise the proof is only checked against the root it carries
+
+
+
+#[path = "../src/utils/merkle.rs"]
+#[allow(dead_code)]
+mod merkle;
+
+
+
+fn main(){
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let input = match args.first().map(String::as_str){
+        Some("-") | None => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input).expect("can't read stdin");
+            input
+        },
+        Some(path) => fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("can't read {}: {}", path, e);
+            process::exit(2);
+        }),
+    };
+    let json: serde_json::Value = serde_json::from_str(&input).unwrap_or_else(|e| {
+        eprintln!("invalid json: {}", e);
+        process::exit(2);
+    });
+    let proof: merkle::InclusionProof = serde_json::from_value(json.get("data").cloned().unwrap_or(json)).unwrap_or_else(|e| {
+        eprintln!("not an inclusion proof: {}", e);
+        process::exit(2);
+    });
+    if let Some(expected) = args.get(1){
+        if expected.to_lowercase() != proof.root.to_lowercase(){
+            println!("INVALID - the proof is for root {} but {} was expected", proof.root, expected);
+            process::exit(1);
+        }
+    }
+    if merkle::verify(&proof){
+        println!("ok - the {} ballot of {} with score {} is leaf {} of {} under root {}", if proof.abstain { "abstain" } else if proof.is_upvote { "up" } else { "down" }, proof.wallet_address, proof.score, proof.index, proof.leaves, proof.root);
+    } else{
+        println!("INVALID - the ballot doesn't hash up to root {}", proof.root);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,73 @@
+
+
+
+use std::{env, sync::atomic::{AtomicU64, Ordering}};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+
+
+
+// NOTE - a local receiver to try the webhooks out, it checks the X-Biter-Signature header and prints every delivery
+// NOTE - run it with `WEBHOOK_SECRET=whsec_... cargo run --example webhook_receiver`, then register http://localhost:9000/ as a webhook and ping it
+// NOTE - RECEIVER_FAIL_FIRST=3 answers 500 to the first 3 deliveries to see the retries and their backoff
+
+
+
+static RECEIVED: AtomicU64 = AtomicU64::new(0);
+
+
+
+async fn receive(req: HttpRequest, body: web::Bytes) -> HttpResponse{
+    let count = RECEIVED.fetch_add(1, Ordering::SeqCst) + 1;
+    let header = |name: &str| req.headers().get(name).and_then(|h| h.to_str().ok()).unwrap_or_default().to_string();
+    let verified = match env::var("WEBHOOK_SECRET"){
+        Ok(secret) => verify(&secret, &header("X-Biter-Signature"), &body),
+        Err(_) => false,
+    };
+    println!("#{} {} delivery {} signature {}", count, header("X-Biter-Event"), header("X-Biter-Delivery"), if verified { "ok" } else { "INVALID" });
+    println!("{}", String::from_utf8_lossy(&body));
+    let fail_first = env::var("RECEIVER_FAIL_FIRST").ok().and_then(|n| n.parse::<u64>().ok()).unwrap_or(0);
+    if !verified{
+        HttpResponse::Unauthorized().finish()
+    } else if count <= fail_first{
+        HttpResponse::InternalServerError().finish()
+    } else{
+        HttpResponse::Ok().finish()
+    }
+}
+
+
+
+fn verify(secret: &str, signature: &str, body: &[u8]) -> bool{ //-- `t=<timestamp>,v1=<hex hmac-sha256 of "<timestamp>.<body>">`
+    let mut timestamp = None;
+    let mut expected = None;
+    for part in signature.split(','){
+        match part.split_once('='){
+            Some(("t", t)) => timestamp = Some(t),
+            Some(("v1", v)) => expected = hex::decode(v).ok(),
+            _ => {},
+        }
+    }
+    let (timestamp, expected) = match (timestamp, expected){
+        (Some(t), Some(e)) => (t, e),
+        _ => return false,
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify(&expected).is_ok() //-- constant time comparison
+}
+
+
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()>{
+    let port = env::var("RECEIVER_PORT").unwrap_or_else(|_| "9000".to_string());
+    println!("listening for webhooks on 0.0.0.0:{}", port);
+    HttpServer::new(|| App::new().default_service(web::route().to(receive)))
+        .bind(format!("0.0.0.0:{}", port))?
+        .run()
+        .await
+}
@@ -3,4 +3,17 @@
 
 
 
-pub mod event;
\ No newline at end of file
+pub mod event;
+pub mod wallet;
+pub mod admin;
+pub mod attestation;
+pub mod encryption;
+pub mod delegation;
+pub mod conviction;
+pub mod revision;
+pub mod auth;
+pub mod comment;
+pub mod taxonomy;
+pub mod ws;
+pub mod sse;
+pub mod webhook;
\ No newline at end of file
@@ -0,0 +1,199 @@
+
+
+use std::sync::Arc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use mongodb::bson::{doc, oid::ObjectId};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, post, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - maintenance operations on proposals, every route here is for admins only, see utils::admin::authorized()
+
+
+
+#[post("/admin/proposal/{id}/recompute-tally")]
+async fn recompute_tally(req: HttpRequest, id: web::Path<String>, query: web::Query<schemas::vote_log::RecomputeQuery>) -> Result<HttpResponse, Error>{ //-- rebuilds the tally from the vote log and reports how far the stored counters are from it, `?repair=true` fixes them
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    if let Ok(Some(schemas::event::EventInfo{ voting_mode: Some(schemas::event::VotingMode::Encrypted), .. })) = db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None){
+        return Ok(conflict(ENCRYPTED_TALLY)); //-- its counters come from the trustees, a repair would zero them
+    }
+    match utils::votes::recompute(&db, event_id, query.repair.unwrap_or(false)){
+        Ok(Some(drift)) => {
+            if drift.drifted{
+                log::warn!("tally of event {} drifted from its vote log by {:?}, repaired: {} - {}", drift.event_id, drift.drift, drift.repaired, chrono::Local::now().naive_local());
+            }
+            let response_body = ctx::app::Response::<schemas::vote_log::TallyDrift>{
+                data: Some(drift),
+                message: if query.repair.unwrap_or(false) { UPDATED } else { FETCHED },
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(not_found()),
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[post("/admin/proposal/{id}/attest")]
+async fn attest(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{ //-- signs the result of a closed proposal again with the active key, for proposals closed before a key was set or after a key rotation
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.is_expired.unwrap_or(false) => event,
+        Some(_) => return Ok(conflict(NOT_CLOSED_YET)), //-- the result isn't final yet
+        None => return Ok(not_found()),
+    };
+    let tally = schemas::event::Tally::new(&event, config.event.quorum);
+    match utils::attestation::attest(&db, config, &event, &tally){
+        Ok(Some(attestation)) => {
+            let response_body = ctx::app::Response::<schemas::attestation::AttestationInfo>{
+                data: Some(attestation.info()),
+                message: INSERTED,
+                status: 201,
+            };
+            Ok(
+                HttpResponse::Created().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(conflict(NO_ATTESTATION_KEY)),
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+
+
+
+
+
+
+fn unauthorized() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: WRONG_ADMIN_KEY,
+        status: 401,
+    };
+    HttpResponse::Unauthorized().json(
+        response_body
+    ).into_body()
+}
+
+
+fn conflict(message: &'static str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 409,
+    };
+    HttpResponse::Conflict().json(
+        response_body
+    ).into_body()
+}
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(recompute_tally);
+    config.service(attest);
+}
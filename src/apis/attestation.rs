@@ -0,0 +1,129 @@
+use std::sync::Arc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use mongodb::bson::{doc, oid::ObjectId};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, get, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - results of closed proposals signed by the server, anyone can check them against the public keys without trusting the db or the api
+// NOTE - the keys are listed with their id so attestations signed by a rotated key stay verifiable, see ATTESTATION_KEYS in the .env
+
+
+
+#[get("/proposal/{id}/attestation")]
+async fn get_attestation(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{ //-- the signed result document of a closed proposal
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.as_str()){ //-- generating mongodb object id from the id string
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let attestations = db.collection::<schemas::attestation::Attestation>("attestations");
+    match attestations.find_one(doc!{"_id": event_id}, None){
+        Ok(Some(attestation)) => {
+            let response_body = ctx::app::Response::<schemas::attestation::AttestationInfo>{
+                data: Some(attestation.info()),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(not_found()), //-- still open, or it closed while no key was configured
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[get("/attestation/keys")]
+async fn get_public_keys(req: HttpRequest) -> Result<HttpResponse, Error>{ //-- every public key the server has signed with, the active one signs the new attestations
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let response_body = ctx::app::Response::<Vec<schemas::attestation::PublicKeyInfo>>{
+        data: Some(utils::attestation::public_keys(config)),
+        message: FETCHED,
+        status: 200,
+    };
+    Ok(
+        HttpResponse::Ok().json(
+            response_body
+        ).into_body()
+    )
+
+}
+
+
+
+
+
+
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(get_attestation);
+    config.service(get_public_keys);
+}
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+use chrono::Utc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use mongodb::bson::{self, doc, Document};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, post, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - a challenge is good for one login within AUTH_CHALLENGE_TTL seconds, it's deleted by the login that uses it whether the signature verifies or not and by mongodb once it expired
+
+
+
+#[post("/wallet/challenge")]
+async fn get_challenge(req: HttpRequest, challenge_info: web::Json<schemas::auth::ChallengeRequest>) -> Result<HttpResponse, Error>{ //-- the message the wallet has to sign to log in
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if config.auth.secret.is_none(){
+        return Ok(forbidden(LOGIN_DISABLED));
+    }
+
+    let wallet_address = challenge_info.into_inner().wallet_address.trim().to_string();
+    if utils::auth::base58_decode(&wallet_address).map(|key| key.len()) != Some(32){
+        return Ok(bad_request(INVALID_WALLET));
+    }
+    let nonce = uuid::Uuid::new_v4().to_simple().to_string();
+    let expires_at = Utc::now().timestamp() + config.auth.challenge_ttl;
+    let challenge = schemas::auth::Challenge{
+        _id: None, //-- mongodb fills it on insert
+        message: schemas::auth::Challenge::message(&wallet_address, &nonce, expires_at),
+        wallet_address,
+        nonce,
+        expires_at,
+    };
+    let mut stored = bson::to_document(&challenge).unwrap();
+    stored.insert("expires", bson::DateTime::from_millis(expires_at * 1000)); //-- the ttl index sweeps the challenges nobody logged in with on this date
+    match app_storage.unwrap().database(&config.db.name).collection::<Document>("challenges").insert_one(stored, None){
+        Ok(_) => {
+            let response_body = ctx::app::Response::<schemas::auth::Challenge>{
+                data: Some(challenge),
+                message: INSERTED,
+                status: 201,
+            };
+            Ok(
+                HttpResponse::Created().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[post("/wallet/login")]
+async fn login_wallet(req: HttpRequest, login_info: web::Json<schemas::auth::LoginRequest>) -> Result<HttpResponse, Error>{ //-- trades a signed challenge for a session token
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if config.auth.secret.is_none(){
+        return Ok(forbidden(LOGIN_DISABLED));
+    }
+
+    let login_info = login_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let challenges = app_storage.unwrap().database(&config.db.name).collection::<schemas::auth::Challenge>("challenges");
+    let challenge = match challenges.find_one_and_delete(doc!{"wallet_address": login_info.wallet_address.trim(), "nonce": &login_info.nonce}, None){ //-- deleting it makes sure it's only used once
+        Ok(Some(challenge)) if challenge.expires_at >= Utc::now().timestamp() => challenge,
+        Ok(_) => return Ok(forbidden(CHALLENGE_EXPIRED)),
+        Err(e) => return Ok(server_error(e)),
+    };
+    if !utils::auth::verify(&challenge.wallet_address, &challenge.message, &login_info.signature){
+        return Ok(forbidden(INVALID_SIGNATURE));
+    }
+    let response_body = ctx::app::Response::<schemas::auth::Session>{
+        data: utils::auth::issue(config, &challenge.wallet_address),
+        message: FETCHED,
+        status: 200,
+    };
+    Ok(
+        HttpResponse::Ok().json(
+            response_body
+        ).into_body()
+    )
+
+}
+
+
+
+
+
+
+
+
+fn bad_request(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn forbidden(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 403,
+    };
+    HttpResponse::Forbidden().json(
+        response_body
+    ).into_body()
+}
+
+
+fn server_error(e: mongodb::error::Error) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: &e.to_string(), //-- take a reference to the string error
+        status: 500,
+    };
+    HttpResponse::InternalServerError().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(get_challenge);
+    config.service(login_wallet);
+}
@@ -0,0 +1,575 @@
+use std::sync::Arc;
+use chrono::Utc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use mongodb::{bson::{self, doc, oid::ObjectId}, options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument}};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, delete, get, post, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - writing needs the session token of a wallet inside the Authorization header, see apis/auth.rs, reading is open to everyone
+// NOTE - moderators hide and unhide comments with the admin key or as a logged in wallet listed in COMMENT_MODERATORS
+
+
+
+#[post("/proposal/{id}/comments")]
+async fn post_comment(req: HttpRequest, id: web::Path<String>, comment_info: web::Json<schemas::comment::PostCommentRequest>) -> Result<HttpResponse, Error>{ //-- a top level comment or a reply to one of the comments of the proposal
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let author = match utils::auth::wallet(&req, config){
+        Some(author) => author,
+        None => return Ok(unauthorized()),
+    };
+
+    let comment_info = comment_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let body = match check_body(&comment_info.body, config){
+        Ok(body) => body,
+        Err(reason) => return Ok(bad_request(reason)),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let events = db.collection::<schemas::event::EventInfo>("events");
+    if events.find_one(doc!{"_id": event_id}, None).unwrap().is_none(){
+        return Ok(not_found());
+    }
+    let comments = db.collection::<schemas::comment::Comment>("comments");
+    let (parent_id, depth) = match comment_info.parent_id.as_ref().map(ObjectId::parse_str){
+        Some(Ok(parent_id)) => match comments.find_one(doc!{"_id": parent_id, "event_id": event_id}, None).unwrap(){
+            Some(parent) if parent.depth + 1 > config.comment.max_depth => return Ok(bad_request(THREAD_TOO_DEEP)),
+            Some(parent) => (Some(parent_id), parent.depth + 1),
+            None => return Ok(not_found()), //-- no such comment on this proposal
+        },
+        Some(Err(_)) => return Ok(invalid_id()),
+        None => (None, 0),
+    };
+    let mut comment = schemas::comment::Comment{
+        _id: None, //-- mongodb fills it on insert
+        event_id,
+        parent_id,
+        depth,
+        author,
+        body,
+        created_at: Utc::now().timestamp(),
+        edited_at: None,
+        edits: 0,
+        reactions: Default::default(),
+        score: 0,
+        replies: 0,
+        hidden: None,
+    };
+    let posted = comments.insert_one(&comment, None)
+                        .and_then(|inserted| {
+                            comment._id = inserted.inserted_id.as_object_id();
+                            if let Some(parent_id) = parent_id{
+                                comments.update_one(doc!{"_id": parent_id}, doc!{"$inc": {"replies": 1}}, None)?;
+                            }
+                            events.update_one(doc!{"_id": event_id}, doc!{"$inc": {"comments": 1}}, None)
+                        });
+    match posted{
+        Ok(_) => {
+            utils::events::emit(&db, hub, ctx::hub::Activity::CommentPosted{
+                proposal_id: event_id.to_hex(),
+                comment_id: comment._id.unwrap().to_hex(),
+                parent_id: parent_id.map(|p| p.to_hex()),
+                author: comment.author.clone(),
+            });
+            let response_body = ctx::app::Response::<schemas::comment::Comment>{
+                data: Some(comment),
+                message: INSERTED,
+                status: 201,
+            };
+            Ok(
+                HttpResponse::Created().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[get("/proposal/{id}/comments")]
+async fn get_comments(req: HttpRequest, id: web::Path<String>, query: web::Query<schemas::comment::ListCommentsQuery>) -> Result<HttpResponse, Error>{ //-- one page of the top level comments of the proposal or of the replies to `parent`
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let query = query.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let parent_id = match query.parent.as_ref().map(ObjectId::parse_str){
+        Some(Ok(parent_id)) => Some(parent_id),
+        Some(Err(_)) => return Ok(invalid_id()),
+        None => None,
+    };
+    let cursor = match query.cursor.as_ref().map(|c| schemas::event::EventCursor::decode(c)){
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => return Ok(bad_request(INVALID_CURSOR)),
+        None => None,
+    };
+    let comments = app_storage.unwrap().database(&config.db.name).collection::<schemas::comment::Comment>("comments");
+    let field = query.sort().field();
+    let limit = query.limit();
+    let options = FindOptions::builder().sort(doc!{field: -1, "_id": -1}).limit(limit + 1).build(); //-- one more than the page to know if there is a next page
+    let page = comments.count_documents(query.filter(event_id, parent_id, None), None)
+                        .and_then(|total| {
+                            let page = comments.find(query.filter(event_id, parent_id, cursor.as_ref()), options)?.collect::<mongodb::error::Result<Vec<schemas::comment::Comment>>>()?;
+                            Ok((total, page))
+                        });
+    match page{
+        Ok((total, mut page)) => {
+            let next_cursor = if page.len() > limit as usize{
+                page.truncate(limit as usize);
+                page.last().and_then(|comment| query.cursor_of(comment)).map(|c| c.encode())
+            } else{
+                None
+            };
+            let response_body = ctx::app::Response::<schemas::comment::CommentPage>{
+                data: Some(schemas::comment::CommentPage{ comments: page.into_iter().map(|c| c.redacted()).collect(), total, next_cursor }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[post("/comment/{id}/edit")]
+async fn edit_comment(req: HttpRequest, id: web::Path<String>, edit_info: web::Json<schemas::comment::EditCommentRequest>) -> Result<HttpResponse, Error>{ //-- keeps the previous body inside the history of the comment
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let wallet = match utils::auth::wallet(&req, config){
+        Some(wallet) => wallet,
+        None => return Ok(unauthorized()),
+    };
+
+    let comment_id = match ObjectId::parse_str(id.as_str()){
+        Ok(comment_id) => comment_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let body = match check_body(&edit_info.body, config){
+        Ok(body) => body,
+        Err(reason) => return Ok(bad_request(reason)),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let comments = db.collection::<schemas::comment::Comment>("comments");
+    let comment = match comments.find_one(doc!{"_id": comment_id}, None).unwrap(){
+        Some(comment) if comment.author != wallet => return Ok(forbidden(NOT_THE_AUTHOR)),
+        Some(comment) if comment.hidden.is_some() => return Ok(forbidden(COMMENT_HIDDEN)),
+        Some(comment) if comment.body == body => return Ok(bad_request(NOTHING_TO_EDIT)),
+        Some(comment) => comment,
+        None => return Ok(not_found()),
+    };
+    let previous = schemas::comment::CommentEdit{
+        _id: None, //-- mongodb fills it on insert
+        comment_id,
+        number: comment.edits + 1,
+        body: comment.body.clone(),
+        written_at: comment.edited_at.unwrap_or(comment.created_at),
+    };
+    match db.collection::<schemas::comment::CommentEdit>("comment_edits").insert_one(&previous, None){ //-- the unique (comment_id, number) index rejects a concurrent edit of the same body
+        Ok(_) => {},
+        Err(e) if utils::db::is_duplicate_key(&e) => return Ok(conflict(EDIT_RACE)),
+        Err(e) => return Ok(server_error(e)),
+    }
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+    match comments.find_one_and_update(doc!{"_id": comment_id, "edits": comment.edits}, doc!{"$set": {"body": &body, "edited_at": Utc::now().timestamp()}, "$inc": {"edits": 1}}, options){
+        Ok(Some(comment)) => {
+            let response_body = ctx::app::Response::<schemas::comment::Comment>{
+                data: Some(comment),
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(conflict(EDIT_RACE)),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[get("/comment/{id}/history")]
+async fn get_comment_history(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{ //-- every earlier body of the comment
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let comment_id = match ObjectId::parse_str(id.as_str()){
+        Ok(comment_id) => comment_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let comment = match db.collection::<schemas::comment::Comment>("comments").find_one(doc!{"_id": comment_id}, None).unwrap(){
+        Some(comment) if comment.hidden.is_some() && utils::auth::moderator(&req, config).is_none() => return Ok(forbidden(COMMENT_HIDDEN)), //-- the history would show what was hidden
+        Some(comment) => comment,
+        None => return Ok(not_found()),
+    };
+    let options = FindOptions::builder().sort(doc!{"number": 1}).build();
+    match db.collection::<schemas::comment::CommentEdit>("comment_edits").find(doc!{"comment_id": comment_id}, options)
+            .and_then(|cursor| cursor.collect::<mongodb::error::Result<Vec<schemas::comment::CommentEdit>>>()){
+        Ok(edits) => {
+            let response_body = ctx::app::Response::<schemas::comment::CommentHistory>{
+                data: Some(schemas::comment::CommentHistory{ comment_id: comment_id.to_hex(), edits, current: comment.body }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[post("/comment/{id}/reactions")]
+async fn react(req: HttpRequest, id: web::Path<String>, reaction_info: web::Json<schemas::comment::ReactRequest>) -> Result<HttpResponse, Error>{ //-- a wallet gives each reaction at most once to a comment
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let wallet = match utils::auth::wallet(&req, config){
+        Some(wallet) => wallet,
+        None => return Ok(unauthorized()),
+    };
+
+    let comment_id = match ObjectId::parse_str(id.as_str()){
+        Ok(comment_id) => comment_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let reaction = reaction_info.into_inner().reaction;
+    if !schemas::comment::REACTIONS.contains(&reaction.as_str()){
+        return Ok(bad_request(UNKNOWN_REACTION));
+    }
+    let db = app_storage.unwrap().database(&config.db.name);
+    let comments = db.collection::<schemas::comment::Comment>("comments");
+    match comments.find_one(doc!{"_id": comment_id}, None).unwrap(){
+        Some(comment) if comment.hidden.is_some() => return Ok(forbidden(COMMENT_HIDDEN)),
+        Some(_) => {},
+        None => return Ok(not_found()),
+    }
+    let record = schemas::comment::Reaction{
+        _id: None, //-- mongodb fills it on insert
+        comment_id,
+        wallet_address: wallet,
+        reaction: reaction.clone(),
+        at: Utc::now().timestamp(),
+    };
+    match db.collection::<schemas::comment::Reaction>("comment_reactions").insert_one(&record, None){ //-- the unique (comment_id, wallet_address, reaction) index rejects the same reaction twice
+        Ok(_) => {},
+        Err(e) if utils::db::is_duplicate_key(&e) => return Ok(conflict(ALREADY_REACTED)),
+        Err(e) => return Ok(server_error(e)),
+    }
+    Ok(count_reaction(&comments, comment_id, &reaction, 1))
+
+}
+
+
+#[delete("/comment/{id}/reactions/{reaction}")]
+async fn unreact(req: HttpRequest, path: web::Path<(String, String)>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let wallet = match utils::auth::wallet(&req, config){
+        Some(wallet) => wallet,
+        None => return Ok(unauthorized()),
+    };
+
+    let (id, reaction) = path.into_inner();
+    let comment_id = match ObjectId::parse_str(id.as_str()){
+        Ok(comment_id) => comment_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    match db.collection::<schemas::comment::Reaction>("comment_reactions").delete_one(doc!{"comment_id": comment_id, "wallet_address": &wallet, "reaction": &reaction}, None){
+        Ok(deleted) if deleted.deleted_count == 0 => Ok(not_found()),
+        Ok(_) => Ok(count_reaction(&db.collection::<schemas::comment::Comment>("comments"), comment_id, &reaction, -1)),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[post("/comment/{id}/hide")]
+async fn hide_comment(req: HttpRequest, id: web::Path<String>, hide_info: web::Json<schemas::comment::HideCommentRequest>) -> Result<HttpResponse, Error>{ //-- the comment keeps its place in the thread without its body
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let moderator = match utils::auth::moderator(&req, config){
+        Some(moderator) => moderator,
+        None => return Ok(forbidden(NOT_A_MODERATOR)),
+    };
+
+    let comment_id = match ObjectId::parse_str(id.as_str()){
+        Ok(comment_id) => comment_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let moderation = schemas::comment::Moderation{
+        by: moderator,
+        reason: hide_info.into_inner().reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty()),
+        at: Utc::now().timestamp(),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    Ok(moderate(&db, comment_id, Some(moderation)))
+
+}
+
+
+#[post("/comment/{id}/unhide")]
+async fn unhide_comment(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if utils::auth::moderator(&req, config).is_none(){
+        return Ok(forbidden(NOT_A_MODERATOR));
+    }
+
+    let comment_id = match ObjectId::parse_str(id.as_str()){
+        Ok(comment_id) => comment_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    Ok(moderate(&db, comment_id, None))
+
+}
+
+
+
+
+
+
+
+
+fn check_body(body: &str, config: &Config) -> Result<String, &'static str>{ //-- the trimmed markdown of a comment
+    let body = body.trim();
+    if body.is_empty(){
+        Err(COMMENT_EMPTY)
+    } else if body.chars().count() > config.comment.max_length{
+        Err(COMMENT_TOO_LONG)
+    } else{
+        Ok(body.to_string())
+    }
+}
+
+
+fn count_reaction(comments: &mongodb::sync::Collection<schemas::comment::Comment>, comment_id: ObjectId, reaction: &str, by: i64) -> HttpResponse{ //-- moves the counters of the comment after a reaction was stored or deleted
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+    let inc = doc!{format!("reactions.{}", reaction): by, "score": by * schemas::comment::Reaction::score_of(reaction)};
+    match comments.find_one_and_update(doc!{"_id": comment_id}, doc!{"$inc": inc}, options){
+        Ok(Some(comment)) => {
+            let response_body = ctx::app::Response::<schemas::comment::Comment>{
+                data: Some(comment.redacted()),
+                message: UPDATED,
+                status: 200,
+            };
+            HttpResponse::Ok().json(
+                response_body
+            ).into_body()
+        },
+        Ok(None) => not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+
+fn moderate(db: &mongodb::sync::Database, comment_id: ObjectId, moderation: Option<schemas::comment::Moderation>) -> HttpResponse{ //-- hides the comment with Some and shows it again with None, the comment count of the proposal follows
+    let comments = db.collection::<schemas::comment::Comment>("comments");
+    let filter = match moderation{
+        Some(_) => doc!{"_id": comment_id, "hidden": null},
+        None => doc!{"_id": comment_id, "hidden": {"$ne": null}},
+    };
+    let by = if moderation.is_some() { -1 } else { 1 };
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+    let moderated = comments.find_one_and_update(filter, doc!{"$set": {"hidden": bson::to_bson(&moderation).unwrap()}}, options)
+                            .and_then(|comment| match comment{
+                                Some(comment) => {
+                                    db.collection::<schemas::event::EventInfo>("events").update_one(doc!{"_id": comment.event_id}, doc!{"$inc": {"comments": by}}, None)?;
+                                    Ok(Some(comment))
+                                },
+                                None => comments.find_one(doc!{"_id": comment_id}, None), //-- already in that state or missing
+                            });
+    match moderated{
+        Ok(Some(comment)) => {
+            let response_body = ctx::app::Response::<schemas::comment::Comment>{
+                data: Some(comment),
+                message: UPDATED,
+                status: 200,
+            };
+            HttpResponse::Ok().json(
+                response_body
+            ).into_body()
+        },
+        Ok(None) => not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn bad_request(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn unauthorized() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: LOGIN_REQUIRED,
+        status: 401,
+    };
+    HttpResponse::Unauthorized().json(
+        response_body
+    ).into_body()
+}
+
+
+fn forbidden(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 403,
+    };
+    HttpResponse::Forbidden().json(
+        response_body
+    ).into_body()
+}
+
+
+fn conflict(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 409,
+    };
+    HttpResponse::Conflict().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn server_error(e: mongodb::error::Error) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: &e.to_string(), //-- take a reference to the string error
+        status: 500,
+    };
+    HttpResponse::InternalServerError().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(post_comment);
+    config.service(get_comments);
+    config.service(edit_comment);
+    config.service(get_comment_history);
+    config.service(react);
+    config.service(unreact);
+    config.service(hide_comment);
+    config.service(unhide_comment);
+}
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+use chrono::Utc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use mongodb::{bson::{doc, oid::ObjectId}, options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument}};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, get, post, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - stakes of conviction proposals and their conviction curves, see schemas/conviction.rs for how the conviction builds up
+// NOTE - a stake replaces the previous stake of the wallet on the proposal, every change goes into the stake_log which is what the conviction is folded from
+
+
+
+#[post("/stake")]
+async fn stake(req: HttpRequest, stake_info: web::Json<schemas::conviction::StakeRequest>) -> Result<HttpResponse, Error>{ //-- sets the stake of a wallet on a conviction proposal, 0 withdraws it
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let stake_info = stake_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    if stake_info.score > config.event.max_score{
+        return Ok(bad_request(SCORE_TOO_HIGH));
+    }
+    let event_id = match ObjectId::parse_str(stake_info._id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.is_over(Utc::now().timestamp()) => return Ok(forbidden(VOTING_CLOSED)),
+        Some(event) if event.voting_mode() != schemas::event::VotingMode::Conviction => return Ok(bad_request(NOT_CONVICTION)),
+        Some(event) if !event.has_started(Utc::now().timestamp()) => return Ok(forbidden(VOTING_NOT_STARTED)),
+        Some(_) => {},
+        None => return Ok(not_found()),
+    }
+    match utils::conviction::committed(&db, &stake_info.wallet_address, event_id){ // NOTE - two stakes of the same wallet on different proposals at the same time can both pass this check
+        Ok(committed) if committed.saturating_add(stake_info.amount) > stake_info.score as u64 => return Ok(bad_request(STAKE_OVER_SCORE)),
+        Ok(_) => {},
+        Err(e) => return Ok(server_error(e)),
+    }
+    let now = Utc::now().timestamp();
+    let options = FindOneAndUpdateOptions::builder().upsert(true).return_document(ReturnDocument::Before).build(); //-- the stake before the change is what goes into the log as the previous amount
+    let staked = db.collection::<schemas::conviction::Stake>("stakes").find_one_and_update(
+                        doc!{"event_id": event_id, "wallet_address": &stake_info.wallet_address},
+                        doc!{"$set": {"amount": stake_info.amount as i64, "updated_at": now, "released": false}},
+                        options,
+                    )
+                    .and_then(|previous| {
+                        let previous = previous.map(|stake| stake.amount).unwrap_or(0);
+                        if previous != stake_info.amount{
+                            utils::conviction::append(&db, event_id, &stake_info.wallet_address, stake_info.amount, previous, now)?;
+                        }
+                        utils::conviction::refresh(&db, hub, config, event_id)
+                    });
+    match staked{
+        Ok(state) => {
+            if let Some(state) = state.as_ref(){
+                utils::events::emit(&db, hub, ctx::hub::Activity::ConvictionUpdated{ proposal_id: event_id.to_hex(), conviction: state.clone() });
+            }
+            let response_body = ctx::app::Response::<Option<schemas::conviction::ConvictionState>>{
+                data: Some(state), //-- None if it got closed in the meantime, passed_at is set if this stake made it pass
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[get("/proposal/{id}/conviction")]
+async fn get_conviction_curve(req: HttpRequest, id: web::Path<String>, query: web::Query<schemas::vote_log::TallySeriesQuery>) -> Result<HttpResponse, Error>{ //-- the conviction and the total staked per bucket, for charting
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.voting_mode() == schemas::event::VotingMode::Conviction => event,
+        Some(_) => return Ok(bad_request(NOT_CONVICTION)),
+        None => return Ok(not_found()),
+    };
+    let now = Utc::now().timestamp();
+    let from = query.from.or(event.created_at).unwrap_or(now);
+    let to = query.to.unwrap_or_else(|| event.closed_at.unwrap_or(now).min(now)).max(from);
+    let bucket = query.bucket(from, to);
+    let requested_amount = event.requested_amount.unwrap_or(0);
+    let threshold = config.conviction.threshold(requested_amount);
+    match utils::conviction::curve(&db, config, &event, from, to, bucket){
+        Ok((points, fold)) => {
+            let response_body = ctx::app::Response::<schemas::conviction::ConvictionCurve>{
+                data: Some(schemas::conviction::ConvictionCurve{
+                    proposal_id: event_id.to_hex(),
+                    requested_amount,
+                    half_life: config.conviction.half_life,
+                    threshold,
+                    from,
+                    to,
+                    bucket,
+                    points,
+                    state: fold.state(&config.conviction, threshold),
+                }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[get("/proposal/{id}/stakes")]
+async fn get_stakes(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{ //-- who backs a conviction proposal and with how much
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let options = FindOptions::builder().sort(doc!{"amount": -1, "_id": 1}).build();
+    match app_storage.unwrap().database(&config.db.name).collection::<schemas::conviction::Stake>("stakes").find(doc!{"event_id": event_id, "amount": {"$gt": 0_i64}}, options)
+            .and_then(|cursor| cursor.collect::<mongodb::error::Result<Vec<schemas::conviction::Stake>>>()){
+        Ok(stakes) => {
+            let response_body = ctx::app::Response::<schemas::conviction::StakeList>{
+                data: Some(schemas::conviction::StakeList{ staked: stakes.iter().map(|s| s.amount).sum(), stakes }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+
+
+
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn bad_request(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn forbidden(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 403,
+    };
+    HttpResponse::Forbidden().json(
+        response_body
+    ).into_body()
+}
+
+
+fn server_error(e: mongodb::error::Error) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: &e.to_string(), //-- take a reference to the string error
+        status: 500,
+    };
+    HttpResponse::InternalServerError().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(stake);
+    config.service(get_conviction_curve);
+    config.service(get_stakes);
+}
@@ -0,0 +1,265 @@
+use std::sync::Arc;
+use chrono::Utc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use mongodb::bson::doc;
+use actix_web::{Error, HttpRequest, HttpResponse, Result, get, post, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - a wallet hands its voting power to another wallet globally or for the proposals of a tag, there's at most one active delegation per delegator and scope
+// NOTE - delegating again in the same scope replaces the previous delegation, the history is kept with active set to false, see schemas/delegation.rs for how the power is resolved
+
+
+
+#[post("/delegate")]
+async fn delegate(req: HttpRequest, delegation_info: web::Json<schemas::delegation::DelegateRequest>) -> Result<HttpResponse, Error>{ //-- replaces the active delegation of the same scope unless the new one closes a loop
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let wallet = match utils::auth::wallet(&req, config){ //-- only the delegator hands over or takes back its power
+        Some(wallet) => wallet,
+        None => return Ok(unauthorized()),
+    };
+
+    let delegation_info = delegation_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let delegator = delegation_info.delegator.trim().to_string();
+    let delegate = delegation_info.delegate.trim().to_string();
+    if delegator.is_empty() || delegate.is_empty(){
+        return Ok(bad_request(WALLET_REQUIRED));
+    }
+    if delegator == delegate{
+        return Ok(bad_request(SELF_DELEGATION));
+    }
+    if delegator != wallet{
+        return Ok(forbidden(NOT_THE_DELEGATOR));
+    }
+    if delegation_info.score > config.event.max_score{
+        return Ok(bad_request(SCORE_TOO_HIGH));
+    }
+    let mut delegation = schemas::delegation::Delegation{
+        _id: None, //-- mongodb fills it on insert
+        delegator,
+        delegate,
+        tag: schemas::delegation::normalize_tag(delegation_info.tag.as_ref()),
+        score: delegation_info.score,
+        active: true,
+        created_at: Utc::now().timestamp(),
+        revoked_at: None,
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    if let Some(tag) = delegation.tag.as_ref(){ //-- a tag scope has to be a tag the proposals can carry
+        match utils::taxonomy::check(&db, None, std::slice::from_ref(tag)){
+            Ok(Some(reason)) => return Ok(bad_request(reason)),
+            Ok(None) => {},
+            Err(e) => return Ok(server_error(e)),
+        }
+    }
+    match utils::delegation::would_loop(&db, &delegation){
+        Ok(true) => return Ok(conflict(DELEGATION_CYCLE)),
+        Ok(false) => {},
+        Err(e) => return Ok(server_error(e)),
+    }
+    let delegations = db.collection::<schemas::delegation::Delegation>("delegations");
+    if let Err(e) = delegations.update_many(doc!{"delegator": &delegation.delegator, "tag": &delegation.tag, "active": true}, doc!{"$set": {"active": false, "revoked_at": delegation.created_at}}, None){
+        return Ok(server_error(e));
+    }
+    match delegations.insert_one(&delegation, None){ //-- the unique partial index on the active (delegator, tag) pairs rejects a concurrent one
+        Ok(inserted) => {
+            delegation._id = inserted.inserted_id.as_object_id();
+            let response_body = ctx::app::Response::<schemas::delegation::Delegation>{
+                data: Some(delegation),
+                message: INSERTED,
+                status: 201,
+            };
+            Ok(
+                HttpResponse::Created().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) if utils::db::is_duplicate_key(&e) => Ok(conflict(DELEGATION_RACE)),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[post("/revoke-delegation")]
+async fn revoke_delegation(req: HttpRequest, revoke_info: web::Json<schemas::delegation::RevokeDelegationRequest>) -> Result<HttpResponse, Error>{ //-- the power goes back to the delegator on every proposal that isn't closed yet
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let wallet = match utils::auth::wallet(&req, config){ //-- only the delegator hands over or takes back its power
+        Some(wallet) => wallet,
+        None => return Ok(unauthorized()),
+    };
+
+    let revoke_info = revoke_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    if revoke_info.delegator.trim() != wallet{
+        return Ok(forbidden(NOT_THE_DELEGATOR));
+    }
+    let tag = schemas::delegation::normalize_tag(revoke_info.tag.as_ref());
+    let delegations = app_storage.unwrap().database(&config.db.name).collection::<schemas::delegation::Delegation>("delegations");
+    match delegations.update_one(doc!{"delegator": revoke_info.delegator.trim(), "tag": tag, "active": true}, doc!{"$set": {"active": false, "revoked_at": Utc::now().timestamp()}}, None){
+        Ok(updated) if updated.matched_count == 0 => Ok(not_found()),
+        Ok(_) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[get("/wallet/{address}/delegations")]
+async fn get_wallet_delegations(req: HttpRequest, address: web::Path<String>) -> Result<HttpResponse, Error>{ //-- the active delegations made by the wallet and made to it
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let address = address.into_inner();
+    let delegations = app_storage.unwrap().database(&config.db.name).collection::<schemas::delegation::Delegation>("delegations");
+    let given = delegations.find(doc!{"delegator": &address, "active": true}, None).and_then(|cursor| cursor.collect::<mongodb::error::Result<Vec<_>>>());
+    let received = delegations.find(doc!{"delegate": &address, "active": true}, None).and_then(|cursor| cursor.collect::<mongodb::error::Result<Vec<_>>>());
+    match (given, received){
+        (Ok(given), Ok(received)) => {
+            let response_body = ctx::app::Response::<schemas::delegation::WalletDelegations>{
+                data: Some(schemas::delegation::WalletDelegations{ given, received }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        (Err(e), _) | (_, Err(e)) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+
+
+
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn unauthorized() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: LOGIN_REQUIRED,
+        status: 401,
+    };
+    HttpResponse::Unauthorized().json(
+        response_body
+    ).into_body()
+}
+
+
+fn forbidden(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 403,
+    };
+    HttpResponse::Forbidden().json(
+        response_body
+    ).into_body()
+}
+
+
+fn bad_request(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn conflict(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 409,
+    };
+    HttpResponse::Conflict().json(
+        response_body
+    ).into_body()
+}
+
+
+fn server_error(e: mongodb::error::Error) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: &e.to_string(), //-- take a reference to the string error
+        status: 500,
+    };
+    HttpResponse::InternalServerError().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(delegate);
+    config.service(revoke_delegation);
+    config.service(get_wallet_delegations);
+}
@@ -0,0 +1,315 @@
+use std::sync::Arc;
+use chrono::Utc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils::{self, elgamal};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, get, post, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - ballots of encrypted proposals and the decryption of their tally by the trustees, nothing here ever sees a choice in the clear
+// NOTE - every proof is checked before anything is stored so a bad ballot or a bad share can't spoil the sum, see utils/elgamal.rs for what the proofs are bound to
+
+
+
+#[post("/cast-encrypted-vote")]
+async fn cast_encrypted_vote(req: HttpRequest, vote_info: web::Json<schemas::encryption::CastEncryptedVoteRequest>) -> Result<HttpResponse, Error>{ //-- stores a ballot encrypted to the key of the trustees once its validity proof checks out
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let vote_info = vote_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let event_id = match ObjectId::parse_str(vote_info._id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let (setup, revision) = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.is_over(Utc::now().timestamp()) => return Ok(forbidden(VOTING_CLOSED)),
+        Some(event) if !event.has_started(Utc::now().timestamp()) => return Ok(forbidden(VOTING_NOT_STARTED)),
+        Some(event @ schemas::event::EventInfo{ voting_mode: Some(schemas::event::VotingMode::Encrypted), encryption: Some(_), .. }) => (event.encryption.clone().unwrap(), event.revision()),
+        Some(_) => return Ok(bad_request(NOT_ENCRYPTED)),
+        None => return Ok(not_found()),
+    };
+    if vote_info.score > config.event.max_score{
+        return Ok(bad_request(SCORE_TOO_HIGH));
+    }
+    let context = elgamal::ballot_context(&event_id.to_hex(), &vote_info.wallet_address);
+    if !elgamal::verify_ballot(setup.public_key().0, schemas::encryption::CHOICES.len(), &vote_info.ciphertexts, &vote_info.proof, &context){
+        return Ok(bad_request(INVALID_BALLOT_PROOF));
+    }
+    let ballot = schemas::encryption::EncryptedBallot{
+        _id: None, //-- mongodb fills it on insert
+        event_id,
+        wallet_address: vote_info.wallet_address,
+        score: vote_info.score,
+        ciphertexts: vote_info.ciphertexts,
+        proof: vote_info.proof,
+        voted_at: Utc::now().timestamp(),
+        revision: Some(revision),
+    };
+    match db.collection::<schemas::encryption::EncryptedBallot>("encrypted_ballots").insert_one(&ballot, None){ //-- the unique (event_id, wallet_address) index rejects a second ballot from the same wallet
+        Ok(_) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: INSERTED,
+                status: 201,
+            };
+            Ok(
+                HttpResponse::Created().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) if utils::db::is_duplicate_key(&e) => Ok(conflict(ALREADY_VOTED)),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[get("/proposal/{id}/encryption")]
+async fn get_encryption_status(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{ //-- the key to encrypt to, the trustees and once closed the encrypted tally they have to decrypt
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.encryption.is_some() => event,
+        Some(_) => return Ok(bad_request(NOT_ENCRYPTED)),
+        None => return Ok(not_found()),
+    };
+    let setup = event.encryption.clone().unwrap();
+    let ballots = db.collection::<Document>("encrypted_ballots").count_documents(doc!{"event_id": event_id}, None);
+    let submitted = db.collection::<schemas::encryption::StoredDecryptionShare>("decryption_shares").find(doc!{"event_id": event_id}, None)
+                        .and_then(|cursor| cursor.map(|share| share.map(|s| s.trustee)).collect::<mongodb::error::Result<Vec<u32>>>());
+    match (ballots, submitted){
+        (Ok(ballots), Ok(submitted)) => {
+            let status = schemas::encryption::EncryptionStatus{
+                proposal_id: event_id.to_hex(),
+                choices: schemas::encryption::CHOICES.iter().map(|c| c.to_string()).collect(),
+                public_key: setup.public_key(),
+                threshold: setup.threshold,
+                trustees: (1..=setup.trustees).map(|trustee| schemas::encryption::TrusteeKey{
+                    trustee,
+                    verification_key: setup.verification_key(trustee),
+                    submitted: submitted.contains(&trustee),
+                }).collect(),
+                ballots,
+                encrypted_tally: event.encrypted_tally,
+                decrypted_at: event.decrypted_at,
+            };
+            let response_body = ctx::app::Response::<schemas::encryption::EncryptionStatus>{
+                data: Some(status),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        (Err(e), _) | (_, Err(e)) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[post("/proposal/{id}/decryption-share")]
+async fn submit_decryption_share(req: HttpRequest, id: web::Path<String>, share_info: web::Json<schemas::encryption::DecryptionShareRequest>) -> Result<HttpResponse, Error>{ //-- a trustee's share of the encrypted tally, the counters are filled as soon as there are enough of them
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let share_info = share_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let db = app_storage.unwrap().database(&config.db.name);
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.encryption.is_none() => return Ok(bad_request(NOT_ENCRYPTED)),
+        Some(event) if !event.is_expired.unwrap_or(false) => return Ok(conflict(NOT_CLOSED_YET)), //-- the tally to decrypt is only frozen at the close
+        Some(event) if event.decrypted_at.is_some() => return Ok(conflict(ALREADY_DECRYPTED)),
+        Some(event) => event,
+        None => return Ok(not_found()),
+    };
+    let setup = event.encryption.unwrap();
+    let tally = match event.encrypted_tally{
+        Some(tally) => tally,
+        None => match utils::encryption::freeze(&db, event_id){ //-- adding the ballots up failed when it closed
+            Ok(tally) => tally,
+            Err(e) => return Ok(server_error(e)),
+        },
+    };
+    if share_info.trustee == 0 || share_info.trustee > setup.trustees{
+        return Ok(bad_request(UNKNOWN_TRUSTEE));
+    }
+    let ciphertexts = tally.ciphertexts();
+    let verification_key = setup.verification_key(share_info.trustee).0;
+    let context = elgamal::share_context(&event_id.to_hex(), share_info.trustee as u64);
+    if share_info.shares.len() != ciphertexts.len() || !ciphertexts.iter().zip(&share_info.shares).all(|((ciphertext, _), share)| elgamal::verify_share(verification_key, ciphertext, share, &context)){
+        return Ok(bad_request(INVALID_DECRYPTION_SHARE));
+    }
+    let share = schemas::encryption::StoredDecryptionShare{
+        _id: None, //-- mongodb fills it on insert
+        event_id,
+        trustee: share_info.trustee,
+        shares: share_info.shares,
+        submitted_at: Utc::now().timestamp(),
+    };
+    match db.collection::<schemas::encryption::StoredDecryptionShare>("decryption_shares").insert_one(&share, None){ //-- the unique (event_id, trustee) index keeps one share per trustee
+        Ok(_) => {},
+        Err(e) if utils::db::is_duplicate_key(&e) => return Ok(conflict(SHARE_ALREADY_SUBMITTED)),
+        Err(e) => return Ok(server_error(e)),
+    }
+    match utils::encryption::finalize(&db, hub, config, event_id){
+        Ok(Some(event)) => {
+            let response_body = ctx::app::Response::<schemas::event::Tally>{
+                data: Some(schemas::event::Tally::new(&event, config.event.quorum)),
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => { //-- waiting for more trustees
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: INSERTED,
+                status: 201,
+            };
+            Ok(
+                HttpResponse::Created().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+
+
+
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn bad_request(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn forbidden(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 403,
+    };
+    HttpResponse::Forbidden().json(
+        response_body
+    ).into_body()
+}
+
+
+fn conflict(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 409,
+    };
+    HttpResponse::Conflict().json(
+        response_body
+    ).into_body()
+}
+
+
+fn server_error(e: mongodb::error::Error) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: &e.to_string(), //-- take a reference to the string error
+        status: 500,
+    };
+    HttpResponse::InternalServerError().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(cast_encrypted_vote);
+    config.service(get_encryption_status);
+    config.service(submit_decryption_share);
+}
@@ -1,13 +1,12 @@
 
-use std::env;
+use std::sync::Arc;
 use crate::contexts as ctx;
+use crate::contexts::config::Config;
 use crate::schemas;
 use crate::constants::*;
 use crate::utils;
 use chrono::Utc;
-use futures::{executor::block_on, TryFutureExt, TryStreamExt}; //-- based on orphan rule TryStreamExt trait is required to use try_next() method on the future object which is solved by .await - try_next() is used on futures stream or chunks to get the next future IO stream
-use bytes::Buf; //-- based on orphan rule it'll be needed to call the reader() method on the whole_body buffer
-use mongodb::{bson, bson::{doc, oid::ObjectId}};
+use mongodb::{sync::Database, bson::{doc, oid::ObjectId}, options::{FindOptions, FindOneAndUpdateOptions, ReturnDocument}};
 use actix_web::{Error, HttpRequest, HttpResponse, Result, get, post, web};
 
 
@@ -23,15 +22,51 @@ use actix_web::{Error, HttpRequest, HttpResponse, Result, get, post, web};
 #[post("/add")]
 async fn add_event(req: HttpRequest, event_info: web::Json<schemas::event::EventAddRequest>) -> Result<HttpResponse, Error>{
     
-    let conn = utils::db::connection().await;
-    let app_storage = match conn.as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
-        ctx::app::Mode::On => conn.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
         ctx::app::Mode::Off => None, //-- no db is available cause it's off
     };
 
     let event_info = event_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T    
-    let events = app_storage.unwrap().database("bitrader").collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
-    match events.find_one(doc!{"title": event_info.clone().title}, None).unwrap(){ //-- finding event based on event title
+    let encryption = match (event_info.voting_mode, event_info.encryption.clone()){
+        (Some(schemas::event::VotingMode::Encrypted), Some(setup)) => match setup.validate(){
+            Ok(()) => Some(setup),
+            Err(reason) => return Ok(bad_request(reason)),
+        },
+        (Some(schemas::event::VotingMode::Encrypted), None) => return Ok(bad_request(ENCRYPTION_SETUP_REQUIRED)),
+        _ => None, //-- the other modes have no use for a key
+    };
+    let requested_amount = match (event_info.voting_mode, event_info.requested_amount){
+        (Some(schemas::event::VotingMode::Conviction), Some(amount)) if config.conviction.threshold(amount).is_some() => Some(amount),
+        (Some(schemas::event::VotingMode::Conviction), Some(_)) => return Ok(bad_request(REQUESTED_AMOUNT_TOO_HIGH)),
+        (Some(schemas::event::VotingMode::Conviction), None) => return Ok(bad_request(REQUESTED_AMOUNT_REQUIRED)),
+        _ => None,
+    };
+    let now = Utc::now().timestamp();
+    let (start_at, exp_time) = match event_info.window(now, &config.event){
+        Ok(window) => window,
+        Err(reason) => return Ok(bad_request(reason)),
+    };
+    if let Some(rule) = event_info.anti_sniping{
+        if event_info.voting_mode.unwrap_or(schemas::event::VotingMode::Open) != schemas::event::VotingMode::Open{ //-- the other modes have no live lead to flip
+            return Ok(bad_request(ANTI_SNIPING_OPEN_ONLY));
+        }
+        if let Err(reason) = rule.check(start_at, exp_time, &config.event){
+            return Ok(bad_request(reason));
+        }
+    }
+    let db = app_storage.unwrap().database(&config.db.name);
+    let category = event_info.category.as_ref().map(|c| c.trim().to_lowercase()).filter(|c| !c.is_empty());
+    let tags = schemas::event::normalize_tags(&event_info.tags.clone().unwrap_or_default());
+    if let Some(reason) = utils::taxonomy::check(&db, category.as_deref(), &tags).unwrap(){ //-- only what the admins put inside the taxonomy
+        return Ok(bad_request(reason));
+    }
+    let events = db.collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
+    let title_normalized = schemas::event::normalize_title(&event_info.title);
+    match events.find_one(doc!{"title_normalized": title_normalized.clone()}, None).unwrap(){ //-- finding event based on event normalized title
         Some(event_doc) => { //-- deserializing BSON into the EventInfo struct
             let response_body = ctx::app::Response::<schemas::event::EventInfo>{ //-- we have to specify a generic type for data field in Response struct which in our case is EventInfo struct
                 data: Some(event_doc), //-- data is an empty &[u8] array
@@ -46,22 +81,62 @@ async fn add_event(req: HttpRequest, event_info: web::Json<schemas::event::Event
             )
         }, 
         None => { //-- means we didn't find any document related to this title and we have to create a new event
-            let events = app_storage.unwrap().database("bitrader").collection::<schemas::event::EventAddRequest>("events");
-            let now = Utc::now().timestamp_nanos() / 1_000_000_000; // nano to sec
-            let exp_time = now + env::var("EVENT_EXPIRATION").expect("⚠️ found no event expiration time").parse::<i64>().unwrap();
+            let events = db.collection::<schemas::event::EventAddRequest>("events");
+            let voting_mode = event_info.voting_mode.unwrap_or(schemas::event::VotingMode::Open);
             let new_event = schemas::event::EventAddRequest{
                 title: event_info.clone().title,
                 content: event_info.clone().content,
                 creator_wallet_address: event_info.clone().creator_wallet_address,
                 upvotes: Some(0),
                 downvotes: Some(0),
-                voters: Some(vec![]), //-- initializing empty voters
+                upvotes_weight: Some(0),
+                downvotes_weight: Some(0),
+                abstains: Some(0),
+                abstains_weight: Some(0),
                 is_expired: Some(false), //-- a event is not expired yet or at initialization
-                expire_at: Some(exp_time), //-- a event will be expired at
+                expire_at: if voting_mode == schemas::event::VotingMode::Conviction { None } else { Some(exp_time) }, //-- a event will be expired at, conviction events run until they pass or get expired by hand
+                start_at: if start_at > now { Some(start_at) } else { None },
+                voting_start: None,
+                voting_end: None,
                 created_at: Some(now),
+                category,
+                tags: Some(tags),
+                title_normalized: Some(title_normalized.clone()),
+                schema_version: Some(schemas::event::EVENT_SCHEMA_VERSION),
+                voting_mode: Some(voting_mode),
+                reveal_at: match voting_mode{ //-- the commit phase takes whatever is left before the reveal period
+                    schemas::event::VotingMode::CommitReveal => Some(exp_time - config.event.reveal_period),
+                    _ => None,
+                },
+                encryption,
+                requested_amount,
+                anti_sniping: event_info.anti_sniping,
+                revision: Some(1),
+                comments: Some(0),
             };
-            match events.insert_one(new_event, None){
+            match events.insert_one(new_event.clone(), None){
                 Ok(insert_result) => {
+                    let original = schemas::revision::Revision{
+                        _id: None, //-- mongodb fills it on insert
+                        event_id: insert_result.inserted_id.as_object_id().unwrap(),
+                        number: 1,
+                        title: new_event.title.clone(),
+                        content: new_event.content.clone(),
+                        tags: new_event.tags.clone().unwrap_or_default(),
+                        editor: new_event.creator_wallet_address.clone(),
+                        note: None,
+                        created_at: now,
+                    };
+                    if let Err(e) = db.collection::<schemas::revision::Revision>("revisions").insert_one(&original, None){ //-- the first edit stores it again if it's missing
+                        log::error!("can't store the first revision of event {} {} - {}", original.event_id, e, chrono::Local::now().naive_local());
+                    }
+                    utils::events::emit(&db, hub, ctx::hub::Activity::ProposalCreated{
+                        proposal_id: insert_result.inserted_id.as_object_id().unwrap().to_hex(),
+                        title: new_event.title,
+                        creator_wallet_address: new_event.creator_wallet_address,
+                        expire_at: new_event.expire_at,
+                        start_at: new_event.start_at,
+                    });
                     let response_body = ctx::app::Response::<ObjectId>{ //-- we have to specify a generic type for data field in Response struct which in our case is ObjectId struct
                         data: Some(insert_result.inserted_id.as_object_id().unwrap()),
                         message: INSERTED,
@@ -73,6 +148,19 @@ async fn add_event(req: HttpRequest, event_info: web::Json<schemas::event::Event
                         ).into_body()
                     )
                 },
+                Err(e) if utils::db::is_duplicate_key(&e) => { //-- another request inserted the same title between our find_one() and insert_one() and the unique index rejected this one
+                    let events = db.collection::<schemas::event::EventInfo>("events");
+                    let response_body = ctx::app::Response::<schemas::event::EventInfo>{
+                        data: events.find_one(doc!{"title_normalized": title_normalized}, None).unwrap_or(None),
+                        message: FOUND_DOCUMENT,
+                        status: 302,
+                    };
+                    Ok(
+                        HttpResponse::Found().json(
+                            response_body
+                        ).into_body()
+                    )
+                },
                 Err(e) => {
                     let response_body = ctx::app::Response::<ctx::app::Nill>{
                         data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
@@ -93,36 +181,39 @@ async fn add_event(req: HttpRequest, event_info: web::Json<schemas::event::Event
 
 
 #[get("/get/availables")]
-async fn get_all_events(req: HttpRequest) -> Result<HttpResponse, Error>{
+async fn get_all_events(req: HttpRequest, query: web::Query<schemas::event::ListEventsQuery>) -> Result<HttpResponse, Error>{
 
-    let conn = utils::db::connection().await;
-    let app_storage = match conn.as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
-        ctx::app::Mode::On => conn.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
         ctx::app::Mode::Off => None, //-- no db is available cause it's off
     };
 
-    let filter = doc! { "is_expired": false }; //-- filtering all none expired events
-    let events = app_storage.unwrap().database("bitrader").collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch and deserialize all event infos or documents from BSON into the EventInfo struct
-    let mut available_events = schemas::event::AvailableEvents{
-        events: vec![],
+    let query = query.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let cursor = match query.cursor.as_ref().map(|c| schemas::event::EventCursor::decode(c)){
+        Some(None) => { //-- the client sent a cursor that we didn't give it
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: INVALID_CURSOR,
+                status: 400,
+            };
+            return Ok(
+                HttpResponse::BadRequest().json(
+                    response_body
+                ).into_body()
+            );
+        },
+        Some(cursor) => cursor,
+        None => None,
     };
+    let db = app_storage.unwrap().database(&config.db.name);
 
-    match events.find(filter, None){
-        Ok(cursor) => {
-
-            // ---------------------------------------
-            // NOTE - uncomment this for async mongodb
-            // ---------------------------------------
-            // while let Some(event) = cursor.try_next().await.unwrap(){ //-- calling try_next() method on cursor needs the cursor to be mutable - reading while awaiting on try_next() method doesn't return None
-            //     available_events.events.push(event);
-            // }
-            
-            for event in cursor {
-                available_events.events.push(event.unwrap());
-            }
+    match utils::db::list_events(&db, &query, cursor.as_ref()){
+        Ok(available_events) => {
             let response_body = ctx::app::Response::<schemas::event::AvailableEvents>{
                 message: FETCHED,
-                data: Some(available_events), //-- data is an empty &[u8] array
+                data: Some(available_events),
                 status: 200,
             };
             Ok(
@@ -148,41 +239,455 @@ async fn get_all_events(req: HttpRequest) -> Result<HttpResponse, Error>{
 }
 
 
+#[get("/search")]
+async fn search_events(req: HttpRequest, query: web::Query<schemas::event::SearchQuery>) -> Result<HttpResponse, Error>{ //-- full-text search over the titles and contents ranked by relevance
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let query = query.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let text = match query.text(){
+        Some(text) => text,
+        None => return Ok(bad_request(SEARCH_TEXT_REQUIRED)),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    match utils::db::search_events(&db, &query, text){
+        Ok(results) => {
+            let response_body = ctx::app::Response::<schemas::event::SearchResults>{
+                data: Some(results),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[get("/proposal/{id}")]
+async fn get_proposal(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.into_inner().as_str()){ //-- generating mongodb object id from the id string
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let events = db.collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
+    match events.find_one(doc!{"_id": event_id}, None).unwrap(){ //-- finding event based on event id
+        Some(mut event_doc) => { //-- deserializing BSON into the EventInfo struct
+            if let Err(e) = utils::delegation::refresh(&db, &mut event_doc){ //-- the tally goes out without the delegated weight
+                log::error!("can't resolve the delegations of event {} {} - {}", event_id, e, chrono::Local::now().naive_local());
+            }
+            let now = Utc::now().timestamp();
+            let detail = schemas::event::ProposalDetail{
+                tally: schemas::event::Tally::new(&event_doc, config.event.quorum),
+                time_remaining: (event_doc.expire_at.unwrap_or(now) - now).max(0),
+                time_until_start: (event_doc.start_at.unwrap_or(now) - now).max(0),
+                proposal: event_doc,
+            };
+            let response_body = ctx::app::Response::<schemas::event::ProposalDetail>{
+                data: Some(detail),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        None => Ok(not_found()),
+    }
+
+}
+
+
+#[get("/proposal/{id}/tally")]
+async fn get_proposal_tally_at(req: HttpRequest, id: web::Path<String>, query: web::Query<schemas::vote_log::TallyAtQuery>) -> Result<HttpResponse, Error>{ //-- the tally as it was at `?at=<unix seconds>`, rebuilt from the vote log
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.into_inner().as_str()){ //-- generating mongodb object id from the id string
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) => event,
+        None => return Ok(not_found()),
+    };
+    let at = query.at.unwrap_or_else(|| Utc::now().timestamp());
+    let rebuilt = utils::votes::project(&db, event_id, Some(at))
+                            .and_then(|projection| Ok((utils::delegation::resolve_at(&db, &event, at)?, projection)));
+    match rebuilt{
+        Ok((resolution, projection)) => {
+            let tally = schemas::event::Tally::with_delegated(&projection.counters, &resolution.counters, config.event.quorum); //-- the delegations as they were then, like the live tally does with the current ones
+            let was_over = event.is_expired.unwrap_or(false) && event.expire_at.map(|e| e <= at).unwrap_or(false);
+            let response_body = ctx::app::Response::<schemas::vote_log::TallyAt>{
+                data: Some(schemas::vote_log::TallyAt{
+                    proposal_id: event_id.to_hex(),
+                    at,
+                    outcome: tally.outcome(was_over),
+                    tally,
+                    casts: projection.casts,
+                    changes: projection.changes,
+                    retractions: projection.retractions,
+                    last_vote_at: projection.last_vote_at,
+                }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[get("/proposal/{id}/tally/series")]
+async fn get_proposal_tally_series(req: HttpRequest, id: web::Path<String>, query: web::Query<schemas::vote_log::TallySeriesQuery>) -> Result<HttpResponse, Error>{ //-- cumulative counters per bucket, for charting
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.into_inner().as_str()){ //-- generating mongodb object id from the id string
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) => event,
+        None => return Ok(not_found()),
+    };
+    let now = Utc::now().timestamp();
+    let from = query.from.or(event.start_at).or(event.created_at).unwrap_or(now);
+    let to = query.to.unwrap_or_else(|| event.expire_at.unwrap_or(now).min(now)).max(from);
+    let bucket = query.bucket(from, to);
+    let series = utils::votes::series(&db, event_id, from, to, bucket)
+                            .and_then(|mut points| {
+                                utils::delegation::series(&db, &event, &mut points)?;
+                                Ok(points)
+                            });
+    match series{
+        Ok(points) => {
+            let response_body = ctx::app::Response::<schemas::vote_log::TallySeries>{
+                data: Some(schemas::vote_log::TallySeries{
+                    proposal_id: event_id.to_hex(),
+                    from,
+                    to,
+                    bucket,
+                    points,
+                }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[get("/proposal/{id}/voters")]
+async fn get_proposal_voters(req: HttpRequest, id: web::Path<String>, query: web::Query<schemas::ballot::ListVotersQuery>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.into_inner().as_str()){ //-- generating mongodb object id from the id string
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let events = db.collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
+    let ballots = db.collection::<schemas::ballot::Ballot>("ballots"); //-- selecting ballots collection to page through the ballots of this event
+    match events.find_one(doc!{"_id": event_id}, None).unwrap(){ //-- finding event based on event id
+        Some(mut event) => {
+            let limit = query.limit();
+            let offset = query.offset();
+            let options = FindOptions::builder().sort(query.sort()).skip(offset).limit(limit).build();
+            let resolution = match utils::delegation::refresh(&db, &mut event){ //-- the ballots of a closed event already carry their delegated power
+                Ok(resolution) => resolution,
+                Err(e) => {
+                    log::error!("can't resolve the delegations of event {} {} - {}", event_id, e, chrono::Local::now().naive_local());
+                    None
+                },
+            };
+            match (ballots.count_documents(doc!{"event_id": event_id}, None), ballots.find(doc!{"event_id": event_id}, options)){
+                (Ok(total), Ok(cursor)) => {
+                    let mut voters = cursor.map(|ballot| ballot.unwrap()).collect::<Vec<schemas::ballot::Ballot>>();
+                    if let Some(resolution) = resolution.as_ref(){
+                        for voter in voters.iter_mut(){
+                            voter.delegated = resolution.voters.get(&voter.wallet_address).copied();
+                        }
+                    }
+                    let end = offset + voters.len() as u64;
+                    let voter_list = schemas::ballot::VoterList{
+                        voters,
+                        total,
+                        next_offset: if end < total { Some(end) } else { None },
+                    };
+                    let response_body = ctx::app::Response::<schemas::ballot::VoterList>{
+                        data: Some(voter_list),
+                        message: FETCHED,
+                        status: 200,
+                    };
+                    Ok(
+                        HttpResponse::Ok().json(
+                            response_body
+                        ).into_body()
+                    )
+                },
+                (Err(e), _) | (_, Err(e)) => {
+                    let response_body = ctx::app::Response::<ctx::app::Nill>{
+                        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                        message: &e.to_string(), //-- take a reference to the string error
+                        status: 500,
+                    };
+                    Ok(
+                        HttpResponse::InternalServerError().json(
+                            response_body
+                        ).into_body()
+                    )
+                },
+            }
+        },
+        None => Ok(not_found()),
+    }
+
+}
+
+
+#[get("/proposal/{id}/proof/{wallet}")]
+async fn get_ballot_proof(req: HttpRequest, param: web::Path<(String, String)>) -> Result<HttpResponse, Error>{ //-- the merkle inclusion proof of a ballot once the proposal is closed
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let (id, wallet_address) = param.into_inner();
+    let event_id = match ObjectId::parse_str(id.as_str()){ //-- generating mongodb object id from the id string
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let published = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(schemas::event::EventInfo{ merkle_root: Some(root), .. }) => root,
+        Some(_) => { //-- the root is only built when the proposal closes
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: NOT_CLOSED_YET,
+                status: 409,
+            };
+            return Ok(
+                HttpResponse::Conflict().json(
+                    response_body
+                ).into_body()
+            );
+        },
+        None => return Ok(not_found()),
+    };
+    match utils::audit::prove(&db, event_id, &wallet_address){
+        Ok((root, _)) if root != published => { //-- a ballot has been added, changed or removed since the close
+            log::error!("ballots of event {} hash to {} instead of the published root {} - {}", event_id, root, published, chrono::Local::now().naive_local());
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: BALLOTS_TAMPERED,
+                status: 409,
+            };
+            Ok(
+                HttpResponse::Conflict().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok((_, Some(proof))) => {
+            let response_body = ctx::app::Response::<utils::merkle::InclusionProof>{
+                data: Some(proof),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok((_, None)) => Ok(not_found()), //-- this wallet didn't vote
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
 #[post("/cast-vote")]
 async fn cast_vote_event(req: HttpRequest, vote_info: web::Json<schemas::event::CastVoteRequest>) -> Result<HttpResponse, Error>{
     
-    let conn = utils::db::connection().await;
-    let app_storage = match conn.as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
-        ctx::app::Mode::On => conn.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
         ctx::app::Mode::Off => None, //-- no db is available cause it's off
     };
+    let wallet = match utils::auth::wallet(&req, config){ //-- a ballot spends the weight of its wallet and overrides its delegations
+        Some(wallet) => wallet,
+        None => return Ok(unauthorized()),
+    };
 
     let vote_info = vote_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
-    let event_id = ObjectId::parse_str(vote_info._id.as_str()).unwrap(); //-- generating mongodb object id from the id string 
-    let events = app_storage.unwrap().database("bitrader").collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
+    if vote_info.voter.event_owner_wallet_address.trim() != wallet{
+        return Ok(forbidden(NOT_YOUR_BALLOT));
+    }
+    if vote_info.voter.score > config.event.max_score{
+        return Ok(bad_request(SCORE_TOO_HIGH));
+    }
+    if let Err(reason) = vote_info.voter.check_rationale(config.event.rationale_max_length){
+        return Ok(bad_request(reason));
+    }
+    let event_id = match ObjectId::parse_str(vote_info._id.as_str()){ //-- generating mongodb object id from the id string
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let events = db.collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
+    let ballots = db.collection::<schemas::ballot::Ballot>("ballots"); //-- selecting ballots collection to store the ballot of this voter
+    let now = Utc::now().timestamp();
     match events.find_one(doc!{"_id": event_id}, None).unwrap(){ //-- finding event based on event title and id
-        Some(event_doc) => { //-- deserializing BSON into the EventInfo struct
-            let mut upvotes = event_doc.upvotes.unwrap(); //-- trait Copy is implemented for u16 thus we don't loose the ownership when we move them into a new scope
-            let mut downvotes = event_doc.downvotes.unwrap(); //-- trait Copy is implemented for u16 thus we don't loose the ownership when we move them into a new scope
-            if vote_info.voter.is_upvote{
-                upvotes+=1;
-            }
-            if !vote_info.voter.is_upvote{
-                downvotes+=1;
-            }
-            let updated_voters = event_doc.clone().add_voter(vote_info.clone().voter).await;
-            let serialized_voters = bson::to_bson(&updated_voters).unwrap(); //-- we have to serialize the updated_voters to BSON Document object in order to update voters field inside the collection
-            let serialized_upvotes = bson::to_bson(&upvotes).unwrap(); //-- we have to serialize the upvotes to BSON Document object in order to update voters field inside the collection
-            let serialized_downvotes = bson::to_bson(&downvotes).unwrap(); //-- we have to serialize the downvotes to BSON Document object in order to update voters field inside the collection
-            match events.update_one(doc!{"_id": event_id}, doc!{"$set": { "voters": serialized_voters, "upvotes": serialized_upvotes, "downvotes": serialized_downvotes }}, None){
-                Ok(updated_result) => {
-                    let response_body = ctx::app::Response::<ctx::app::Nill>{ //-- we have to specify a generic type for data field in Response struct which in our case is Nill struct
+        Some(event) if event.is_over(now) => Ok(voting_closed()), //-- the ballots are sealed under the merkle root once it's closed
+        Some(event) if event.voting_mode() != schemas::event::VotingMode::Open => Ok(forbidden(only_takes(event.voting_mode()))), //-- its ballots go through their own routes
+        Some(event) if !event.has_started(now) => Ok(forbidden(VOTING_NOT_STARTED)),
+        Some(event) => { //-- deserializing BSON into the EventInfo struct
+            let ballot = schemas::ballot::Ballot{ revision: Some(event.revision()), ..schemas::ballot::Ballot::new(event_id, &vote_info.voter, now) };
+            match ballots.insert_one(&ballot, None){ //-- the unique (event_id, wallet_address) index rejects a second ballot from the same wallet
+                Ok(_) => {
+                    let counted = utils::votes::append(&db, event_id, &ballot.wallet_address, schemas::vote_log::VoteAction::Cast, Some(ballot.choice()), None, ballot.voted_at)
+                                        .and_then(|record| Ok((utils::votes::apply(&db, &record)?, record)));
+                    match counted{
+                        Ok((updated_event, record)) => {
+                            if let Some(updated_event) = updated_event{ //-- pushing the new tally to the sockets watching this event
+                                publish_tally(&db, hub, config, &record, &updated_event);
+                            }
+                            let response_body = ctx::app::Response::<ctx::app::Nill>{ //-- we have to specify a generic type for data field in Response struct which in our case is Nill struct
+                                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                                message: UPDATED, //-- collection found in bitrader document (database)
+                                status: 200,
+                            };
+                            Ok(
+                                HttpResponse::Ok().json(
+                                    response_body
+                                ).into_body()
+                            )
+                        },
+                        Err(e) => {
+                            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                                message: &e.to_string(), //-- take a reference to the string error
+                                status: 500,
+                            };
+                            Ok(
+                                HttpResponse::InternalServerError().json(
+                                    response_body
+                                ).into_body()
+                            )
+                        },
+                    }
+                },
+                Err(e) if utils::db::is_duplicate_key(&e) => {
+                    let response_body = ctx::app::Response::<ctx::app::Nill>{
                         data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
-                        message: UPDATED, //-- collection found in bitrader document (database)
-                        status: 200,
+                        message: ALREADY_VOTED,
+                        status: 409,
                     };
                     Ok(
-                        HttpResponse::Ok().json(
+                        HttpResponse::Conflict().json(
                             response_body
                         ).into_body()
                     )
@@ -201,36 +706,423 @@ async fn cast_vote_event(req: HttpRequest, vote_info: web::Json<schemas::event::
                 },
             }
         }, 
-        None => { //-- means we didn't find any document related to this title and we have to tell the user to create a new event
-            let response_body = ctx::app::Response::<ctx::app::Nill>{ //-- we have to specify a generic type for data field in Response struct which in our case is Nill struct
+        None => Ok(not_found()), //-- means we didn't find any document related to this id and we have to tell the user to create a new event
+    }
+    
+}
+
+
+#[post("/change-vote")]
+async fn change_vote_event(req: HttpRequest, vote_info: web::Json<schemas::event::CastVoteRequest>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let wallet = match utils::auth::wallet(&req, config){ //-- only the wallet itself can touch its ballot
+        Some(wallet) => wallet,
+        None => return Ok(unauthorized()),
+    };
+
+    let vote_info = vote_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    if vote_info.voter.event_owner_wallet_address.trim() != wallet{
+        return Ok(forbidden(NOT_YOUR_BALLOT));
+    }
+    if vote_info.voter.score > config.event.max_score{
+        return Ok(bad_request(SCORE_TOO_HIGH));
+    }
+    if let Err(reason) = vote_info.voter.check_rationale(config.event.rationale_max_length){
+        return Ok(bad_request(reason));
+    }
+    let event_id = match ObjectId::parse_str(vote_info._id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let now = Utc::now().timestamp();
+    let revision = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.is_over(now) => return Ok(voting_closed()),
+        Some(event) if event.voting_mode() != schemas::event::VotingMode::Open => return Ok(forbidden(only_takes(event.voting_mode()))), //-- a revealed or encrypted ballot is final
+        Some(event) if !event.has_started(now) => return Ok(forbidden(VOTING_NOT_STARTED)),
+        Some(event) => event.revision(),
+        None => return Ok(not_found()),
+    };
+    let ballots = db.collection::<schemas::ballot::Ballot>("ballots");
+    let wallet_address = vote_info.voter.event_owner_wallet_address.clone();
+    let choice = schemas::vote_log::Choice::of(&vote_info.voter);
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::Before).build(); //-- the ballot before the change is what goes into the log as the previous choice
+    let changed = ballots.find_one_and_update(doc!{"event_id": event_id, "wallet_address": &wallet_address}, doc!{"$set": {"is_upvote": choice.is_upvote, "abstain": choice.abstain, "score": choice.score, "voted_at": now, "revision": revision, "rationale": vote_info.voter.rationale()}}, options)
+                            .and_then(|previous| match previous{
+                                Some(previous) if previous.choice() != choice => {
+                                    let record = utils::votes::append(&db, event_id, &wallet_address, schemas::vote_log::VoteAction::Change, Some(choice), Some(previous.choice()), now)?;
+                                    Ok(Some((utils::votes::apply(&db, &record)?, Some(record))))
+                                },
+                                Some(_) => Ok(Some((None, None))), //-- same choice as before so there's nothing to log
+                                None => Ok(None),
+                            });
+    match changed{
+        Ok(Some((updated_event, record))) => {
+            if let (Some(updated_event), Some(record)) = (updated_event, record){
+                publish_tally(&db, hub, config, &record, &updated_event);
+            }
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
                 data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
-                message: NOT_FOUND_DOCUMENT,
-                status: 404,
+                message: UPDATED,
+                status: 200,
             };
             Ok(
-                HttpResponse::NotFound().json(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(not_found()), //-- this wallet has no ballot to change
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
                     response_body
                 ).into_body()
             )
         },
     }
-    
+
+}
+
+
+#[post("/retract-vote")]
+async fn retract_vote_event(req: HttpRequest, vote_info: web::Json<schemas::ballot::RetractVoteRequest>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    let wallet = match utils::auth::wallet(&req, config){ //-- only the wallet itself can touch its ballot
+        Some(wallet) => wallet,
+        None => return Ok(unauthorized()),
+    };
+
+    let vote_info = vote_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    if vote_info.wallet_address.trim() != wallet{
+        return Ok(forbidden(NOT_YOUR_BALLOT));
+    }
+    let event_id = match ObjectId::parse_str(vote_info._id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let now = Utc::now().timestamp();
+    match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.is_over(now) => return Ok(voting_closed()),
+        Some(event) if event.voting_mode() != schemas::event::VotingMode::Open => return Ok(forbidden(only_takes(event.voting_mode()))), //-- a revealed or encrypted ballot is final
+        Some(event) if !event.has_started(now) => return Ok(forbidden(VOTING_NOT_STARTED)),
+        Some(_) => {},
+        None => return Ok(not_found()),
+    }
+    let ballots = db.collection::<schemas::ballot::Ballot>("ballots");
+    let retracted = ballots.find_one_and_delete(doc!{"event_id": event_id, "wallet_address": &vote_info.wallet_address}, None) //-- only one of two concurrent retractions gets the ballot back
+                            .and_then(|previous| match previous{
+                                Some(previous) => {
+                                    let record = utils::votes::append(&db, event_id, &vote_info.wallet_address, schemas::vote_log::VoteAction::Retract, None, Some(previous.choice()), now)?;
+                                    Ok(Some((utils::votes::apply(&db, &record)?, record)))
+                                },
+                                None => Ok(None),
+                            });
+    match retracted{
+        Ok(Some((updated_event, record))) => {
+            if let Some(updated_event) = updated_event{
+                publish_tally(&db, hub, config, &record, &updated_event);
+            }
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: DELETED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(not_found()), //-- this wallet has no ballot to retract
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[post("/commit-vote")]
+async fn commit_vote_event(req: HttpRequest, commit_info: web::Json<schemas::commitment::CommitVoteRequest>) -> Result<HttpResponse, Error>{ //-- stores the hidden ballot of a wallet during the commit phase
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let commit_info = commit_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let event_id = match ObjectId::parse_str(commit_info._id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let commitment = match commit_info.commitment(){
+        Some(commitment) => commitment,
+        None => return Ok(bad_request(INVALID_COMMITMENT)),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let now = Utc::now().timestamp();
+    match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.is_over(now) => return Ok(voting_closed()),
+        Some(event) if event.voting_mode() != schemas::event::VotingMode::CommitReveal => return Ok(bad_request(NOT_COMMIT_REVEAL)),
+        Some(event) if !event.has_started(now) => return Ok(forbidden(VOTING_NOT_STARTED)),
+        Some(event) if event.is_revealing(now) => return Ok(forbidden(COMMIT_PHASE_OVER)),
+        Some(_) => {},
+        None => return Ok(not_found()),
+    }
+    let commitments = db.collection::<schemas::commitment::Commitment>("commitments");
+    let commitment = schemas::commitment::Commitment{
+        _id: None, //-- mongodb fills it on insert
+        event_id,
+        wallet_address: commit_info.wallet_address,
+        commitment,
+        committed_at: now,
+        revealed_at: None,
+    };
+    match commitments.insert_one(&commitment, None){ //-- the unique (event_id, wallet_address) index rejects a second commitment from the same wallet
+        Ok(_) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: INSERTED,
+                status: 201,
+            };
+            Ok(
+                HttpResponse::Created().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) if utils::db::is_duplicate_key(&e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: ALREADY_VOTED,
+                status: 409,
+            };
+            Ok(
+                HttpResponse::Conflict().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[post("/reveal-vote")]
+async fn reveal_vote_event(req: HttpRequest, reveal_info: web::Json<schemas::commitment::RevealVoteRequest>) -> Result<HttpResponse, Error>{ //-- opens a commitment during the reveal phase, the ballot is counted if it matches
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let reveal_info = reveal_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    if reveal_info.voter.score > config.event.max_score{
+        return Ok(bad_request(SCORE_TOO_HIGH));
+    }
+    if let Err(reason) = reveal_info.voter.check_rationale(config.event.rationale_max_length){
+        return Ok(bad_request(reason));
+    }
+    let event_id = match ObjectId::parse_str(reveal_info._id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let now = Utc::now().timestamp();
+    let revision = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.is_over(now) => return Ok(voting_closed()), //-- unrevealed commitments are reported, not counted
+        Some(event) if event.voting_mode() != schemas::event::VotingMode::CommitReveal => return Ok(bad_request(NOT_COMMIT_REVEAL)),
+        Some(event) if !event.is_revealing(now) => return Ok(forbidden(REVEAL_NOT_STARTED)), //-- revealing early would leak the choice while others can still commit
+        Some(event) => event.revision(),
+        None => return Ok(not_found()),
+    };
+    let commitments = db.collection::<schemas::commitment::Commitment>("commitments");
+    let ballot = schemas::ballot::Ballot{ revision: Some(revision), ..schemas::ballot::Ballot::new(event_id, &reveal_info.voter, now) };
+    let commitment = match commitments.find_one(doc!{"event_id": event_id, "wallet_address": &ballot.wallet_address}, None).unwrap(){
+        Some(commitment) => commitment,
+        None => return Ok(not_found()), //-- this wallet didn't commit
+    };
+    if !commitment.opens_with(&ballot.choice(), &reveal_info.salt){
+        return Ok(bad_request(COMMITMENT_MISMATCH));
+    }
+    let ballots = db.collection::<schemas::ballot::Ballot>("ballots");
+    let revealed = ballots.insert_one(&ballot, None) //-- the unique (event_id, wallet_address) index rejects a second reveal of the same commitment
+                                .and_then(|_| {
+                                    commitments.update_one(doc!{"_id": commitment._id}, doc!{"$set": {"revealed_at": now}}, None)?;
+                                    let record = utils::votes::append(&db, event_id, &ballot.wallet_address, schemas::vote_log::VoteAction::Cast, Some(ballot.choice()), None, ballot.voted_at)?;
+                                    Ok((utils::votes::apply(&db, &record)?, record))
+                                });
+    match revealed{
+        Ok((updated_event, record)) => {
+            if let Some(updated_event) = updated_event{
+                publish_tally(&db, hub, config, &record, &updated_event);
+            }
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) if utils::db::is_duplicate_key(&e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: ALREADY_VOTED,
+                status: 409,
+            };
+            Ok(
+                HttpResponse::Conflict().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[get("/proposal/{id}/commitments")]
+async fn get_proposal_commitments(req: HttpRequest, id: web::Path<String>, query: web::Query<schemas::commitment::ListCommitmentsQuery>) -> Result<HttpResponse, Error>{ //-- `?revealed=false` lists the wallets that committed but never revealed
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.into_inner().as_str()){ //-- generating mongodb object id from the id string
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    if db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap().is_none(){
+        return Ok(not_found());
+    }
+    let commitments = db.collection::<schemas::commitment::Commitment>("commitments");
+    let limit = query.limit();
+    let offset = query.offset();
+    let filter = query.filter(event_id);
+    let options = FindOptions::builder().sort(doc!{"committed_at": 1, "_id": 1}).skip(offset).limit(limit).build();
+    match (commitments.count_documents(filter.clone(), None), commitments.find(filter, options)){
+        (Ok(total), Ok(cursor)) => {
+            let page = cursor.map(|commitment| commitment.unwrap()).collect::<Vec<schemas::commitment::Commitment>>();
+            let end = offset + page.len() as u64;
+            let response_body = ctx::app::Response::<schemas::commitment::CommitmentList>{
+                data: Some(schemas::commitment::CommitmentList{
+                    commitments: page,
+                    total,
+                    next_offset: if end < total { Some(end) } else { None },
+                }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        (Err(e), _) | (_, Err(e)) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
 }
 
 
 #[post("/set-expire")]
 async fn expire_event(req: HttpRequest, exp_info: web::Json<schemas::event::ExpireEventRequest>) -> Result<HttpResponse, Error>{
     
-    let conn = utils::db::connection().await;
-    let app_storage = match conn.as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
-        ctx::app::Mode::On => conn.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
         ctx::app::Mode::Off => None, //-- no db is available cause it's off
     };
 
     let exp_info = exp_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
     let event_id = ObjectId::parse_str(exp_info._id.as_str()).unwrap(); //-- generating mongodb object id from the id string
-    let events = app_storage.unwrap().database("bitrader").collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
-    match events.find_one_and_update(doc!{"_id": event_id}, doc!{"$set": {"is_expired": true}}, None).unwrap(){ //-- finding event based on event id
+    let db = app_storage.unwrap().database(&config.db.name);
+    let events = db.collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
+    let closed = match utils::events::close(&db, hub, config, event_id).unwrap(){ //-- closing it the same way the sweeper does so the sockets get the outcome
+        Some(event_doc) => Some(event_doc),
+        None => events.find_one(doc!{"_id": event_id}, None).unwrap(), //-- it was already closed
+    };
+    match closed{
         Some(event_doc) => { //-- deserializing BSON into the EventInfo struct
             let response_body = ctx::app::Response::<schemas::event::EventInfo>{ //-- we have to specify a generic type for data field in Response struct which in our case is EventInfo struct
                 data: Some(event_doc), //-- data is an empty &[u8] array
@@ -263,28 +1155,30 @@ async fn expire_event(req: HttpRequest, exp_info: web::Json<schemas::event::Expi
 #[post("/delete/{id}/{api_key}")]
 async fn delete_event(req: HttpRequest, param: web::Path<(String, String)>) -> Result<HttpResponse, Error>{
     
-    // let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the app_data() method
-    // let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
-    //     ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
-    //     ctx::app::Mode::Off => None, //-- no db is available cause it's off
-    // };
-
-    let delete_api_key = env::var("event_DELETE_KEY").expect("⚠️ no api key variable set");
-    let db_host = env::var("DB_HOST").expect("⚠️ no db host variable set");
-    let db_port = env::var("DB_PORT").expect("⚠️ no db port variable set");
-    let db_engine = env::var("DB_ENGINE").expect("⚠️ no db engine variable set");
-    let db_addr = format!("{}://{}:{}", db_engine, db_host, db_port);
-    let app_storage = Client::with_uri_str(db_addr).unwrap();
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let delete_api_key = &config.event.delete_key;
 
     let param = param.into_inner(); //-- into_inner() will convert the id and api_key into its actual type which is of type String - param.1 is the api key and param.0 is the id of the event
-    if param.1 == delete_api_key{
+    if &param.1 == delete_api_key{
         let event_id = ObjectId::parse_str(param.0.as_str()).unwrap(); //-- generating mongodb object id from the id string
-        let events = app_storage.database("fishuman").collection::<schemas::fishuman::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
+        let db = app_storage.unwrap().database(&config.db.name);
+        let events = db.collection::<schemas::event::EventInfo>("events"); //-- selecting events collection to fetch all event infos into the EventInfo struct
         match events.find_one_and_delete(doc!{"_id": event_id}, None).unwrap(){ //-- finding event based on event id
             Some(event_doc) => { //-- deserializing BSON into the eventInfo struct
-                let response_body = ctx::app::Response::<schemas::fishuman::EventInfo>{ //-- we have to specify a generic type for data field in Response struct which in our case is EventInfo struct
+                if let Err(e) = utils::events::purge(&db, event_id){ //-- the event itself is gone already so a failure here is only logged
+                    log::error!("can't purge the documents of deleted event {} {} - {}", event_id, e, chrono::Local::now().naive_local());
+                }
+                utils::events::emit(&db, hub, ctx::hub::Activity::ProposalDeleted{ proposal_id: event_id.to_hex() });
+                let response_body = ctx::app::Response::<schemas::event::EventInfo>{ //-- we have to specify a generic type for data field in Response struct which in our case is EventInfo struct
                     data: Some(event_doc), //-- data is an empty &[u8] array
-                    message: DELETED, //-- collection found in fishuman document (database)
+                    message: DELETED, //-- collection found in bitrader document (database)
                     status: 200,
                 };
                 Ok(
@@ -328,10 +1222,140 @@ async fn delete_event(req: HttpRequest, param: web::Path<(String, String)>) -> R
 
 
 
+fn publish_tally(db: &Database, hub: &ctx::hub::Hub, config: &Config, record: &schemas::vote_log::VoteRecord, event: &schemas::event::EventInfo){ //-- pushes the tally the record led to, plus the quorum if it's the one that reached it
+    let proposal_id = record.event_id.to_hex();
+    let (before, after) = match utils::delegation::around(db, event, record){ //-- the ballot can move the delegated power of others along with it
+        Ok(resolutions) => resolutions,
+        Err(e) => {
+            log::error!("can't resolve the delegations of event {} {} - {}", proposal_id, e, chrono::Local::now().naive_local());
+            Default::default()
+        },
+    };
+    let counters = schemas::event::Counters::of_event(event);
+    let tally = schemas::event::Tally::with_delegated(&counters, &after.counters, config.event.quorum);
+    let previous = schemas::event::Tally::with_delegated(&counters.sub(&record.delta()), &before.counters, config.event.quorum);
+    let reached_quorum = tally.quorum > 0 && tally.quorum_reached && !previous.quorum_reached;
+    let activity = match record.action{
+        schemas::vote_log::VoteAction::Cast => ctx::hub::Activity::VoteCast{ proposal_id: proposal_id.clone(), tally: tally.clone() },
+        schemas::vote_log::VoteAction::Change => ctx::hub::Activity::VoteChanged{ proposal_id: proposal_id.clone(), tally: tally.clone() },
+        schemas::vote_log::VoteAction::Retract => ctx::hub::Activity::VoteRetracted{ proposal_id: proposal_id.clone(), tally: tally.clone() },
+    };
+    utils::events::emit(db, hub, activity);
+    let (leader_before, leader_after) = (previous.leader(), tally.leader()); //-- only the sign of upvotes minus downvotes, reaching the quorum alone doesn't flip the lead
+    if reached_quorum{
+        utils::events::emit(db, hub, ctx::hub::Activity::QuorumReached{ proposal_id: proposal_id.clone(), tally });
+    }
+    if event.anti_sniping.is_some() && leader_before != leader_after{
+        if let Err(e) = utils::events::extend(db, hub, event, &record.wallet_address, record.at, leader_before, leader_after){
+            log::error!("can't extend event {} {} - {}", proposal_id, e, chrono::Local::now().naive_local());
+        }
+    }
+}
+
+
+fn only_takes(mode: schemas::event::VotingMode) -> &'static str{ //-- why a clear ballot is refused
+    match mode{
+        schemas::event::VotingMode::CommitReveal => COMMIT_REVEAL_ONLY,
+        schemas::event::VotingMode::Encrypted => ENCRYPTED_ONLY,
+        schemas::event::VotingMode::Conviction => CONVICTION_ONLY,
+        schemas::event::VotingMode::Open => VOTING_CLOSED,
+    }
+}
+
+
+fn unauthorized() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: LOGIN_REQUIRED,
+        status: 401,
+    };
+    HttpResponse::Unauthorized().json(
+        response_body
+    ).into_body()
+}
+
+
+fn forbidden(message: &'static str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 403,
+    };
+    HttpResponse::Forbidden().json(
+        response_body
+    ).into_body()
+}
+
+
+fn bad_request(message: &'static str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn voting_closed() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: VOTING_CLOSED,
+        status: 403,
+    };
+    HttpResponse::Forbidden().json(
+        response_body
+    ).into_body()
+}
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{ //-- we have to specify a generic type for data field in Response struct which in our case is Nill struct
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
 pub fn register(config: &mut web::ServiceConfig){
     config.service(add_event);
     config.service(cast_vote_event);
+    config.service(change_vote_event);
+    config.service(retract_vote_event);
+    config.service(commit_vote_event);
+    config.service(reveal_vote_event);
     config.service(expire_event);
     config.service(get_all_events);
+    config.service(search_events);
+    config.service(get_proposal);
+    config.service(get_proposal_voters);
+    config.service(get_proposal_tally_at);
+    config.service(get_proposal_tally_series);
+    config.service(get_ballot_proof);
+    config.service(get_proposal_commitments);
     config.service(delete_event);
 }
@@ -0,0 +1,295 @@
+use std::sync::Arc;
+use chrono::Utc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use mongodb::bson::{doc, oid::ObjectId};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, get, post, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - the creator edits the title, content and tags of a proposal as long as voting hasn't started, every edit is stored as the next numbered revision
+// NOTE - the revision goes in first so its unique (event_id, number) index settles two edits racing each other, it's dropped again if the event can't take it
+
+
+
+#[post("/edit-proposal")]
+async fn edit_proposal(req: HttpRequest, edit_info: web::Json<schemas::revision::EditEventRequest>) -> Result<HttpResponse, Error>{ //-- stores the next revision and puts it on the event
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let edit_info = edit_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let event_id = match ObjectId::parse_str(edit_info._id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let now = Utc::now().timestamp();
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) if event.creator_wallet_address != edit_info.creator_wallet_address.trim() => return Ok(forbidden(NOT_THE_CREATOR)),
+        Some(event) if !event.is_editable(now) => return Ok(forbidden(CONTENT_LOCKED)),
+        Some(event) => event,
+        None => return Ok(not_found()),
+    };
+    let title = edit_info.title.map(|t| t.trim().to_string()).unwrap_or_else(|| event.title.clone());
+    let content = edit_info.content.unwrap_or_else(|| event.content.clone());
+    let tags = edit_info.tags.map(|t| schemas::event::normalize_tags(&t)).unwrap_or_else(|| event.tags.clone().unwrap_or_default());
+    if title.is_empty(){
+        return Ok(bad_request(TITLE_REQUIRED));
+    }
+    if title == event.title && content == event.content && Some(&tags) == event.tags.as_ref(){
+        return Ok(bad_request(NOTHING_TO_EDIT));
+    }
+    match utils::taxonomy::check(&db, None, &tags){
+        Ok(Some(reason)) => return Ok(bad_request(reason)),
+        Ok(None) => {},
+        Err(e) => return Ok(server_error(e)),
+    }
+    if let Err(e) = utils::revisions::ensure_original(&db, &event){
+        return Ok(server_error(e));
+    }
+    let revision = schemas::revision::Revision{
+        _id: None, //-- mongodb fills it on insert
+        event_id,
+        number: event.revision() + 1,
+        title,
+        content,
+        tags,
+        editor: event.creator_wallet_address.clone(),
+        note: edit_info.note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()),
+        created_at: now,
+    };
+    match db.collection::<schemas::revision::Revision>("revisions").insert_one(&revision, None){ //-- the unique (event_id, number) index rejects a concurrent edit of the same revision
+        Ok(_) => {},
+        Err(e) if utils::db::is_duplicate_key(&e) => return Ok(conflict(EDIT_RACE)),
+        Err(e) => return Ok(server_error(e)),
+    }
+    let filter = doc!{ //-- the revision we read acts as a version and voting may have started in between
+        "_id": event_id,
+        "is_expired": false,
+        "start_at": {"$gt": now},
+        "revision": event.revision,
+    };
+    let update = doc!{"$set": {
+        "title": &revision.title,
+        "title_normalized": schemas::event::normalize_title(&revision.title),
+        "content": &revision.content,
+        "tags": &revision.tags,
+        "revision": revision.number,
+    }};
+    let edited = db.collection::<schemas::event::EventInfo>("events").update_one(filter, update, None);
+    if !matches!(edited, Ok(ref updated) if updated.matched_count == 1){
+        if let Err(e) = utils::revisions::discard(&db, event_id, revision.number){
+            log::error!("can't discard revision {} of event {} {} - {}", revision.number, event_id, e, chrono::Local::now().naive_local());
+        }
+    }
+    match edited{
+        Ok(updated) if updated.matched_count == 0 => Ok(conflict(EDIT_RACE)),
+        Ok(_) => {
+            utils::events::emit(&db, hub, ctx::hub::Activity::ProposalEdited{ proposal_id: event_id.to_hex(), title: revision.title.clone(), revision: revision.number });
+            let response_body = ctx::app::Response::<schemas::revision::Revision>{
+                data: Some(revision),
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) if utils::db::is_duplicate_key(&e) => Ok(conflict(TITLE_TAKEN)), //-- titles are unique regardless of case
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[get("/proposal/{id}/revisions")]
+async fn get_revisions(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{ //-- every version of the proposal, oldest first
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) => event,
+        None => return Ok(not_found()),
+    };
+    match utils::revisions::history(&db, &event){
+        Ok(revisions) => {
+            let response_body = ctx::app::Response::<schemas::revision::RevisionList>{
+                data: Some(schemas::revision::RevisionList{ current: event.revision(), revisions }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+#[get("/proposal/{id}/revisions/{number}/diff")]
+async fn get_revision_diff(req: HttpRequest, path: web::Path<(String, u32)>, query: web::Query<schemas::revision::DiffQuery>) -> Result<HttpResponse, Error>{ //-- what changed between a revision and the previous one or the `against` one
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let (id, number) = path.into_inner();
+    let event_id = match ObjectId::parse_str(id.as_str()){
+        Ok(event_id) => event_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let event = match db.collection::<schemas::event::EventInfo>("events").find_one(doc!{"_id": event_id}, None).unwrap(){
+        Some(event) => event,
+        None => return Ok(not_found()),
+    };
+    let revisions = match utils::revisions::history(&db, &event){
+        Ok(revisions) => revisions,
+        Err(e) => return Ok(server_error(e)),
+    };
+    let against = query.against.unwrap_or_else(|| number.saturating_sub(1).max(1)); //-- revision 1 is diffed against itself
+    match (revisions.iter().find(|r| r.number == against), revisions.iter().find(|r| r.number == number)){
+        (Some(from), Some(to)) => {
+            let response_body = ctx::app::Response::<schemas::revision::RevisionDiff>{
+                data: Some(schemas::revision::RevisionDiff::between(from, to)),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        _ => Ok(not_found()),
+    }
+
+}
+
+
+
+
+
+
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn bad_request(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn forbidden(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 403,
+    };
+    HttpResponse::Forbidden().json(
+        response_body
+    ).into_body()
+}
+
+
+fn conflict(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 409,
+    };
+    HttpResponse::Conflict().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn server_error(e: mongodb::error::Error) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: &e.to_string(), //-- take a reference to the string error
+        status: 500,
+    };
+    HttpResponse::InternalServerError().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(edit_proposal);
+    config.service(get_revisions);
+    config.service(get_revision_diff);
+}
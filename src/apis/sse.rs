@@ -0,0 +1,124 @@
+
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::utils;
+use futures::{channel::mpsc, select, FutureExt, SinkExt, StreamExt};
+use mongodb::bson::oid::ObjectId;
+use actix_web::{Error, HttpRequest, HttpResponse, Result, get, web, web::Bytes};
+
+
+
+
+
+
+
+
+
+
+// NOTE - the sse feed is the read only sibling of the websocket, every frame carries the activity sequence number as its id so the browser sends it back inside the Last-Event-ID header when it reconnects
+// NOTE - we subscribe to the hub before reading the backlog so the live activities published in between wait inside the subscription, then once the backlog is sent they're drained skipping the sequence numbers it already had
+// NOTE - the numbers are taken before the activities are persisted so the backlog can hold a later one without an earlier one, which is why they're skipped one by one and never up to the highest replayed
+// NOTE - a client that's further behind than the replay limit gets a resync event instead of a partial backlog and should refetch the proposals it cares about
+
+
+
+#[get("/sse")]
+async fn stream(req: HttpRequest, query: web::Query<schemas::sse::StreamQuery>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let proposals = query.proposals().iter().filter_map(|p| ObjectId::parse_str(p).ok()).map(|p| p.to_hex()).collect::<Vec<String>>(); //-- the hub matches on the lowercase hex form
+    let watched = if proposals.is_empty() { None } else { Some(proposals.iter().cloned().collect::<HashSet<String>>()) };
+    let last_event_id = req.headers().get("Last-Event-ID")
+                                .and_then(|h| h.to_str().ok())
+                                .and_then(|h| h.trim().parse::<i64>().ok())
+                                .or(query.last_event_id);
+
+    let (id, activities) = hub.subscribe(config.sse.buffer, watched.clone());
+    let mut frames = vec![];
+    let mut replayed = HashSet::new();
+    if let Some(after) = last_event_id{
+        let db = app_storage.unwrap().database(&config.db.name);
+        match utils::events::replay(&db, after, watched.as_ref().map(|_| proposals.as_slice()), config.sse.replay_limit + 1){ //-- one more than the limit to know if the client is too far behind
+            Ok(backlog) if backlog.len() as i64 > config.sse.replay_limit => frames.push(Bytes::from_static(b"event: resync\ndata: {}\n\n")),
+            Ok(backlog) => for envelope in backlog{
+                replayed.extend(envelope.seq);
+                frames.push(frame(&envelope));
+            },
+            Err(e) => {
+                hub.unsubscribe(id);
+                let response_body = ctx::app::Response::<ctx::app::Nill>{
+                    data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                    message: &e.to_string(), //-- take a reference to the string error
+                    status: 500,
+                };
+                return Ok(
+                    HttpResponse::InternalServerError().json(
+                        response_body
+                    ).into_body()
+                );
+            },
+        }
+    }
+
+    let (mut outgoing, body) = mpsc::channel::<Result<Bytes, Error>>(config.sse.buffer);
+    let hub = hub.get_ref().clone();
+    let keepalive_interval = Duration::from_secs(config.sse.keepalive_interval);
+    actix_web::rt::spawn(async move {
+        let mut activities = activities.fuse();
+        let mut keepalive = actix_web::rt::time::interval(keepalive_interval);
+        let mut alive = true;
+        for frame in frames{
+            alive = alive && outgoing.send(Ok(frame)).await.is_ok();
+        }
+        while alive{
+            alive = select!{
+                envelope = activities.next() => match envelope{
+                    Some(envelope) if envelope.seq.map(|seq| replayed.remove(&seq)).unwrap_or(false) => true, //-- already sent with the backlog, each one only comes by once
+                    Some(envelope) => outgoing.send(Ok(frame(&envelope))).await.is_ok(),
+                    None => false, //-- the hub dropped us cause we couldn't keep up, the client reconnects with its Last-Event-ID
+                },
+                _ = keepalive.tick().fuse() => outgoing.send(Ok(Bytes::from_static(b": keepalive\n\n"))).await.is_ok(),
+            };
+        }
+        hub.unsubscribe(id);
+    });
+    Ok(
+        HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .header("Cache-Control", "no-cache")
+            .header("X-Accel-Buffering", "no") //-- stops nginx from holding the frames back
+            .streaming(body)
+    )
+
+}
+
+
+
+fn frame(envelope: &ctx::hub::Envelope) -> Bytes{ //-- activities without a sequence number are sent without an id so the client keeps its last resumable one
+    let data = serde_json::to_string(envelope).unwrap();
+    match envelope.seq{
+        Some(seq) => Bytes::from(format!("id: {}\nevent: {}\ndata: {}\n\n", seq, envelope.activity.name(), data)),
+        None => Bytes::from(format!("event: {}\ndata: {}\n\n", envelope.activity.name(), data)),
+    }
+}
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(stream);
+}
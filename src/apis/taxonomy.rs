@@ -0,0 +1,310 @@
+
+
+
+use std::sync::Arc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use chrono::Utc;
+use mongodb::{bson::{doc, oid::ObjectId, Document}, options::{FindOptions, FindOneAndUpdateOptions, ReturnDocument}};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, delete, get, post, put, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - anyone can read the taxonomy, the routes under /admin need the admin key, see utils::admin::authorized()
+
+
+
+#[get("/taxonomy")]
+async fn get_taxonomy(req: HttpRequest) -> Result<HttpResponse, Error>{ //-- the categories and tags a proposal can pick from, sorted by slug
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let terms = app_storage.unwrap().database(&config.db.name).collection::<schemas::taxonomy::Term>("taxonomy");
+    let options = FindOptions::builder().sort(doc!{"slug": 1}).build();
+    match terms.find(None, options).and_then(|cursor| cursor.collect::<mongodb::error::Result<Vec<schemas::taxonomy::Term>>>()){
+        Ok(terms) => {
+            let (categories, tags) = terms.into_iter().partition(|t| t.kind == schemas::taxonomy::TermKind::Category);
+            let response_body = ctx::app::Response::<schemas::taxonomy::Taxonomy>{
+                data: Some(schemas::taxonomy::Taxonomy{ categories, tags }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[post("/admin/taxonomy")]
+async fn add_term(req: HttpRequest, term_info: web::Json<schemas::taxonomy::TermRequest>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let term_info = term_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let name = term_info.name.trim().to_string();
+    if name.is_empty(){
+        return Ok(bad_request(NAME_REQUIRED));
+    }
+    let slug = schemas::taxonomy::slugify(term_info.slug.as_deref().unwrap_or(&name));
+    if slug.is_empty(){
+        return Ok(bad_request(INVALID_SLUG));
+    }
+    let mut term = schemas::taxonomy::Term{
+        _id: None, //-- mongodb fills it on insert
+        kind: term_info.kind,
+        slug,
+        name,
+        description: term_info.description.map(|d| d.trim().to_string()).filter(|d| !d.is_empty()),
+        created_at: Utc::now().timestamp(),
+        updated_at: None,
+    };
+    let terms = app_storage.unwrap().database(&config.db.name).collection::<schemas::taxonomy::Term>("taxonomy");
+    match terms.insert_one(&term, None){ //-- the unique (kind, slug) index rejects a second term with the same slug
+        Ok(inserted) => {
+            term._id = inserted.inserted_id.as_object_id();
+            let response_body = ctx::app::Response::<schemas::taxonomy::Term>{
+                data: Some(term),
+                message: INSERTED,
+                status: 201,
+            };
+            Ok(
+                HttpResponse::Created().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) if utils::db::is_duplicate_key(&e) => Ok(conflict(TERM_EXISTS)),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[put("/admin/taxonomy/{id}")]
+async fn update_term(req: HttpRequest, id: web::Path<String>, term_info: web::Json<schemas::taxonomy::UpdateTermRequest>) -> Result<HttpResponse, Error>{ //-- the slug stays, it's what the proposals refer to
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let term_id = match ObjectId::parse_str(id.as_str()){
+        Ok(term_id) => term_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let term_info = term_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let mut update = Document::new();
+    if let Some(name) = term_info.name.map(|n| n.trim().to_string()){
+        if name.is_empty(){
+            return Ok(bad_request(NAME_REQUIRED));
+        }
+        update.insert("name", name);
+    }
+    if let Some(description) = term_info.description{
+        let description = description.trim().to_string();
+        update.insert("description", if description.is_empty() { None } else { Some(description) }); //-- an empty description clears it
+    }
+    let terms = app_storage.unwrap().database(&config.db.name).collection::<schemas::taxonomy::Term>("taxonomy");
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+    let updated = if update.is_empty(){
+        terms.find_one(doc!{"_id": term_id}, None)
+    } else{
+        update.insert("updated_at", Utc::now().timestamp());
+        terms.find_one_and_update(doc!{"_id": term_id}, doc!{"$set": update}, options)
+    };
+    match updated{
+        Ok(Some(term)) => {
+            let response_body = ctx::app::Response::<schemas::taxonomy::Term>{
+                data: Some(term),
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[delete("/admin/taxonomy/{id}")]
+async fn delete_term(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let term_id = match ObjectId::parse_str(id.as_str()){
+        Ok(term_id) => term_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let terms = db.collection::<schemas::taxonomy::Term>("taxonomy");
+    let term = match terms.find_one(doc!{"_id": term_id}, None){
+        Ok(Some(term)) => term,
+        Ok(None) => return Ok(not_found()),
+        Err(e) => return Ok(server_error(e)),
+    };
+    match utils::taxonomy::in_use(&db, &term){
+        Ok(true) => return Ok(conflict(TERM_IN_USE)),
+        Ok(false) => {},
+        Err(e) => return Ok(server_error(e)),
+    }
+    match terms.delete_one(doc!{"_id": term_id}, None){
+        Ok(_) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: DELETED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+
+
+
+
+
+fn unauthorized() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: WRONG_ADMIN_KEY,
+        status: 401,
+    };
+    HttpResponse::Unauthorized().json(
+        response_body
+    ).into_body()
+}
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn bad_request(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn conflict(message: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message,
+        status: 409,
+    };
+    HttpResponse::Conflict().json(
+        response_body
+    ).into_body()
+}
+
+
+fn server_error(e: mongodb::error::Error) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: &e.to_string(), //-- take a reference to the string error
+        status: 500,
+    };
+    HttpResponse::InternalServerError().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(get_taxonomy);
+    config.service(add_term);
+    config.service(update_term);
+    config.service(delete_term);
+}
@@ -0,0 +1,233 @@
+
+
+use std::sync::Arc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use mongodb::bson::{self, Document};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, get, web};
+
+
+
+
+
+
+
+
+
+
+
+#[get("/wallet/{address}/votes")]
+async fn get_wallet_votes(req: HttpRequest, address: web::Path<String>, query: web::Query<schemas::ballot::WalletVotesQuery>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let address = address.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let ballots = app_storage.unwrap().database(&config.db.name).collection::<Document>("ballots"); //-- selecting ballots collection as raw documents cause each ballot is joined with its event inside the pipeline
+    match ballots.aggregate(query.pipeline(&address), None){
+        Ok(mut cursor) => {
+            let facet = cursor.next().map(|f| f.unwrap()).unwrap_or_default(); //-- $facet always outputs a single document
+            let total = facet.get_array("total").ok()
+                                .and_then(|t| t.first())
+                                .and_then(|t| t.as_document())
+                                .and_then(|t| t.get_i32("count").ok())
+                                .unwrap_or(0) as u64;
+            let mut votes = vec![];
+            for entry in facet.get_array("page").cloned().unwrap_or_default(){
+                let mut entry = entry.as_document().cloned().unwrap_or_default();
+                let event = bson::from_document::<schemas::event::EventInfo>(entry.remove("event").and_then(|e| e.as_document().cloned()).unwrap_or_default()).unwrap();
+                let ballot = bson::from_document::<schemas::ballot::Ballot>(entry).unwrap();
+                let is_expired = event.is_expired.unwrap_or(false);
+                votes.push(schemas::ballot::WalletVote{
+                    event_id: ballot.event_id,
+                    title: event.title.clone(),
+                    is_upvote: ballot.is_upvote,
+                    abstain: ballot.abstain,
+                    score: ballot.score,
+                    voted_at: ballot.voted_at,
+                    is_expired,
+                    expire_at: event.expire_at,
+                    outcome: schemas::event::Tally::new(&event, config.event.quorum).outcome(is_expired),
+                });
+            }
+            let end = query.offset() + votes.len() as u64;
+            let wallet_votes = schemas::ballot::WalletVotes{
+                votes,
+                total,
+                next_offset: if end < total { Some(end) } else { None },
+            };
+            let response_body = ctx::app::Response::<schemas::ballot::WalletVotes>{
+                data: Some(wallet_votes),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+#[get("/rationales")]
+async fn get_rationales(req: HttpRequest, query: web::Query<schemas::ballot::RationaleFeedQuery>) -> Result<HttpResponse, Error>{ //-- the latest explained ballots across every proposal
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let ballots = app_storage.unwrap().database(&config.db.name).collection::<Document>("ballots"); //-- raw documents cause each ballot comes back with the title of its event
+    match ballots.aggregate(query.pipeline(), None){
+        Ok(mut cursor) => {
+            let facet = cursor.next().map(|f| f.unwrap()).unwrap_or_default(); //-- $facet always outputs a single document
+            let total = facet.get_array("total").ok()
+                                .and_then(|t| t.first())
+                                .and_then(|t| t.as_document())
+                                .and_then(|t| t.get_i32("count").ok())
+                                .unwrap_or(0) as u64;
+            let rationales = facet.get_array("page").cloned().unwrap_or_default()
+                                .into_iter()
+                                .filter_map(|entry| bson::from_bson::<schemas::ballot::RationaleEntry>(entry).ok())
+                                .collect::<Vec<_>>();
+            let end = query.offset() + rationales.len() as u64;
+            let feed = schemas::ballot::RationaleFeed{
+                rationales,
+                total,
+                next_offset: if end < total { Some(end) } else { None },
+            };
+            let response_body = ctx::app::Response::<schemas::ballot::RationaleFeed>{
+                data: Some(feed),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+
+
+
+
+
+
+#[get("/wallet/{address}/proposals")]
+async fn get_wallet_proposals(req: HttpRequest, address: web::Path<String>, query: web::Query<schemas::event::ListEventsQuery>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+
+    let address = address.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    let mut query = query.into_inner();
+    query.creator = Some(address.clone()); //-- the path wins over any creator query param
+    query.status.get_or_insert(schemas::event::EventStatus::All); //-- a profile shows every proposal of the creator unless asked otherwise
+    let cursor = match query.cursor.as_ref().map(|c| schemas::event::EventCursor::decode(c)){
+        Some(None) => { //-- the client sent a cursor that we didn't give it
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: INVALID_CURSOR,
+                status: 400,
+            };
+            return Ok(
+                HttpResponse::BadRequest().json(
+                    response_body
+                ).into_body()
+            );
+        },
+        Some(cursor) => cursor,
+        None => None,
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    let events = db.collection::<Document>("events");
+
+    let stats = events.aggregate(schemas::event::CreatorStats::pipeline(&address, config.event.quorum), None)
+                        .and_then(|mut groups| groups.next().transpose()) //-- there is no group at all if the wallet has never created a proposal
+                        .map(|group| group.as_ref().map(schemas::event::CreatorStats::from_group).unwrap_or_default());
+    match (stats, utils::db::list_events(&db, &query, cursor.as_ref())){
+        (Ok(stats), Ok(proposals)) => {
+            let response_body = ctx::app::Response::<schemas::event::CreatorDashboard>{
+                data: Some(schemas::event::CreatorDashboard{ stats, proposals }),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        (Err(e), _) | (_, Err(e)) => {
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: &e.to_string(), //-- take a reference to the string error
+                status: 500,
+            };
+            Ok(
+                HttpResponse::InternalServerError().json(
+                    response_body
+                ).into_body()
+            )
+        },
+    }
+
+}
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(get_wallet_votes);
+    config.service(get_rationales);
+    config.service(get_wallet_proposals);
+}
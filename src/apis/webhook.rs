@@ -0,0 +1,509 @@
+
+
+
+use std::sync::Arc;
+use crate::contexts as ctx;
+use crate::contexts::config::Config;
+use crate::schemas;
+use crate::constants::*;
+use crate::utils;
+use chrono::Utc;
+use mongodb::{sync::Database, bson::{doc, oid::ObjectId, Document}, options::{FindOptions, FindOneAndUpdateOptions, ReturnDocument}};
+use actix_web::{Error, HttpRequest, HttpResponse, Result, delete, get, post, put, web};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - every route here is for admins only, see utils::admin::authorized()
+// NOTE - the deliveries themselves are sent by utils::webhooks::deliver() which is spawned at startup
+
+
+
+#[post("/admin/webhooks")]
+async fn add_webhook(req: HttpRequest, webhook_info: web::Json<schemas::webhook::WebhookRequest>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let webhook_info = webhook_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    if let Err(reason) = webhook_info.validate(){
+        return Ok(bad_request(&reason));
+    }
+    let url = match webhook_info.url{
+        Some(url) => url,
+        None => return Ok(bad_request("the webhook url is required")),
+    };
+    let mut events = webhook_info.events.unwrap_or_default();
+    events.sort();
+    events.dedup();
+    let mut webhook = schemas::webhook::Webhook{
+        _id: None, //-- mongodb fills it on insert
+        url,
+        secret: schemas::webhook::Webhook::new_secret(),
+        events,
+        description: webhook_info.description,
+        active: webhook_info.active.unwrap_or(true),
+        created_at: Utc::now().timestamp(),
+    };
+    let webhooks = app_storage.unwrap().database(&config.db.name).collection::<schemas::webhook::Webhook>("webhooks");
+    match webhooks.insert_one(&webhook, None){
+        Ok(inserted) => {
+            webhook._id = inserted.inserted_id.as_object_id();
+            let response_body = ctx::app::Response::<schemas::webhook::WebhookSecret>{
+                data: Some(schemas::webhook::WebhookSecret{ webhook: webhook.info(), secret: Some(webhook.secret) }), //-- the only time the secret is shown besides a rotation
+                message: INSERTED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[get("/admin/webhooks")]
+async fn get_webhooks(req: HttpRequest) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let webhooks = app_storage.unwrap().database(&config.db.name).collection::<schemas::webhook::Webhook>("webhooks");
+    let options = FindOptions::builder().sort(doc!{"created_at": -1, "_id": -1}).build();
+    match webhooks.find(None, options).and_then(|cursor| cursor.collect::<mongodb::error::Result<Vec<schemas::webhook::Webhook>>>()){
+        Ok(webhooks) => {
+            let response_body = ctx::app::Response::<Vec<schemas::webhook::WebhookInfo>>{
+                data: Some(webhooks.iter().map(|w| w.info()).collect()),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[get("/admin/webhooks/{id}")]
+async fn get_webhook(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let webhook_id = match ObjectId::parse_str(id.as_str()){
+        Ok(webhook_id) => webhook_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let webhooks = app_storage.unwrap().database(&config.db.name).collection::<schemas::webhook::Webhook>("webhooks");
+    match webhooks.find_one(doc!{"_id": webhook_id}, None){
+        Ok(Some(webhook)) => {
+            let response_body = ctx::app::Response::<schemas::webhook::WebhookInfo>{
+                data: Some(webhook.info()),
+                message: FETCHED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[put("/admin/webhooks/{id}")]
+async fn update_webhook(req: HttpRequest, id: web::Path<String>, webhook_info: web::Json<schemas::webhook::WebhookRequest>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let webhook_id = match ObjectId::parse_str(id.as_str()){
+        Ok(webhook_id) => webhook_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let webhook_info = webhook_info.into_inner(); //-- into_inner() will deconstruct to an inner value and return T
+    if let Err(reason) = webhook_info.validate(){
+        return Ok(bad_request(&reason));
+    }
+    let mut update = Document::new();
+    if let Some(url) = webhook_info.url{
+        update.insert("url", url);
+    }
+    if let Some(mut events) = webhook_info.events{
+        events.sort();
+        events.dedup();
+        update.insert("events", events);
+    }
+    if let Some(description) = webhook_info.description{
+        update.insert("description", description);
+    }
+    if let Some(active) = webhook_info.active{
+        update.insert("active", active);
+    }
+    let secret = if webhook_info.rotate_secret.unwrap_or(false){
+        let secret = schemas::webhook::Webhook::new_secret();
+        update.insert("secret", secret.clone());
+        Some(secret) //-- deliveries already in the queue are signed with the new one when they're sent
+    } else{
+        None
+    };
+    let webhooks = app_storage.unwrap().database(&config.db.name).collection::<schemas::webhook::Webhook>("webhooks");
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+    let updated = if update.is_empty(){
+        webhooks.find_one(doc!{"_id": webhook_id}, None)
+    } else{
+        webhooks.find_one_and_update(doc!{"_id": webhook_id}, doc!{"$set": update}, options)
+    };
+    match updated{
+        Ok(Some(webhook)) => {
+            let response_body = ctx::app::Response::<schemas::webhook::WebhookSecret>{
+                data: Some(schemas::webhook::WebhookSecret{ webhook: webhook.info(), secret }),
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[delete("/admin/webhooks/{id}")]
+async fn delete_webhook(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let webhook_id = match ObjectId::parse_str(id.as_str()){
+        Ok(webhook_id) => webhook_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    match db.collection::<Document>("webhooks").delete_one(doc!{"_id": webhook_id}, None){
+        Ok(deleted) if deleted.deleted_count == 0 => Ok(not_found()),
+        Ok(_) => {
+            if let Err(e) = db.collection::<Document>("webhook_deliveries").delete_many(doc!{"webhook_id": webhook_id}, None){ //-- its queue and delivery log go with it
+                return Ok(server_error(e));
+            }
+            let response_body = ctx::app::Response::<ctx::app::Nill>{
+                data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+                message: DELETED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[post("/admin/webhooks/{id}/ping")]
+async fn ping_webhook(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let webhook_id = match ObjectId::parse_str(id.as_str()){
+        Ok(webhook_id) => webhook_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    match db.collection::<Document>("webhooks").find_one(doc!{"_id": webhook_id}, None){
+        Ok(Some(_)) => match utils::webhooks::ping(&db, webhook_id){
+            Ok(delivery) => {
+                let response_body = ctx::app::Response::<schemas::webhook::Delivery>{
+                    data: Some(delivery),
+                    message: INSERTED,
+                    status: 200,
+                };
+                Ok(
+                    HttpResponse::Ok().json(
+                        response_body
+                    ).into_body()
+                )
+            },
+            Err(e) => Ok(server_error(e)),
+        },
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+#[get("/admin/webhooks/{id}/deliveries")]
+async fn get_webhook_deliveries(req: HttpRequest, id: web::Path<String>, query: web::Query<schemas::webhook::ListDeliveriesQuery>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let webhook_id = match ObjectId::parse_str(id.as_str()){
+        Ok(webhook_id) => webhook_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let db = app_storage.unwrap().database(&config.db.name);
+    Ok(deliveries(&db, query.filter(Some(webhook_id)), &query))
+
+}
+
+
+
+#[get("/admin/deliveries")]
+async fn get_deliveries(req: HttpRequest, query: web::Query<schemas::webhook::ListDeliveriesQuery>) -> Result<HttpResponse, Error>{ //-- `?status=dead` is the dead letter list of every webhook
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let db = app_storage.unwrap().database(&config.db.name);
+    Ok(deliveries(&db, query.filter(None), &query))
+
+}
+
+
+
+#[post("/admin/deliveries/{id}/retry")]
+async fn retry_delivery(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let storage = req.app_data::<web::Data<Option<Arc<ctx::app::Storage>>>>().unwrap(); //-- unwrapping the db inside the web data structure which is passed inside the data() method
+    let app_storage = match storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().mode{ //-- here as_ref() method convert &Option<T> to Option<&T>
+        ctx::app::Mode::On => storage.as_ref().as_ref().unwrap().db.as_ref().unwrap().instance.as_ref(), //-- return the db if it wasn't detached - instance.as_ref() will return the Option<&Client>
+        ctx::app::Mode::Off => None, //-- no db is available cause it's off
+    };
+    if !utils::admin::authorized(&req, config){
+        return Ok(unauthorized());
+    }
+
+    let delivery_id = match ObjectId::parse_str(id.as_str()){
+        Ok(delivery_id) => delivery_id,
+        Err(_) => return Ok(invalid_id()),
+    };
+    let deliveries = app_storage.unwrap().database(&config.db.name).collection::<schemas::webhook::Delivery>("webhook_deliveries");
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+    let requeue = doc!{"$set": {"status": "pending", "attempts": 0, "next_attempt_at": Utc::now().timestamp()}}; //-- a fresh set of attempts, the log keeps the old ones
+    match deliveries.find_one_and_update(doc!{"_id": delivery_id}, requeue, options){
+        Ok(Some(delivery)) => {
+            let response_body = ctx::app::Response::<schemas::webhook::Delivery>{
+                data: Some(delivery),
+                message: UPDATED,
+                status: 200,
+            };
+            Ok(
+                HttpResponse::Ok().json(
+                    response_body
+                ).into_body()
+            )
+        },
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(server_error(e)),
+    }
+
+}
+
+
+
+
+
+
+
+
+fn deliveries(db: &Database, filter: Document, query: &schemas::webhook::ListDeliveriesQuery) -> HttpResponse{ //-- one page of deliveries, newest first
+    let collection = db.collection::<schemas::webhook::Delivery>("webhook_deliveries");
+    let options = FindOptions::builder()
+                    .sort(doc!{"created_at": -1, "_id": -1})
+                    .skip(query.offset())
+                    .limit(query.limit())
+                    .build();
+    let page = collection.count_documents(filter.clone(), None)
+                    .and_then(|total| Ok((total, collection.find(filter, options)?.collect::<mongodb::error::Result<Vec<_>>>()?)));
+    match page{
+        Ok((total, deliveries)) => {
+            let end = query.offset() + deliveries.len() as u64;
+            let response_body = ctx::app::Response::<schemas::webhook::DeliveryList>{
+                data: Some(schemas::webhook::DeliveryList{
+                    deliveries,
+                    total,
+                    next_offset: if end < total { Some(end) } else { None },
+                }),
+                message: FETCHED,
+                status: 200,
+            };
+            HttpResponse::Ok().json(
+                response_body
+            ).into_body()
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+
+fn unauthorized() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: WRONG_ADMIN_KEY,
+        status: 401,
+    };
+    HttpResponse::Unauthorized().json(
+        response_body
+    ).into_body()
+}
+
+
+fn not_found() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: NOT_FOUND_DOCUMENT,
+        status: 404,
+    };
+    HttpResponse::NotFound().json(
+        response_body
+    ).into_body()
+}
+
+
+fn invalid_id() -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: INVALID_ID,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn bad_request(reason: &str) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: reason,
+        status: 400,
+    };
+    HttpResponse::BadRequest().json(
+        response_body
+    ).into_body()
+}
+
+
+fn server_error(e: mongodb::error::Error) -> HttpResponse{
+    let response_body = ctx::app::Response::<ctx::app::Nill>{
+        data: Some(ctx::app::Nill(&[])), //-- data is an empty &[u8] array
+        message: &e.to_string(), //-- take a reference to the string error
+        status: 500,
+    };
+    HttpResponse::InternalServerError().json(
+        response_body
+    ).into_body()
+}
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(add_webhook);
+    config.service(get_webhooks);
+    config.service(get_webhook);
+    config.service(update_webhook);
+    config.service(delete_webhook);
+    config.service(ping_webhook);
+    config.service(get_webhook_deliveries);
+    config.service(get_deliveries);
+    config.service(retry_delivery);
+}
@@ -0,0 +1,184 @@
+
+
+use std::{collections::HashSet, time::{Duration, Instant}};
+use crate::contexts as ctx;
+use crate::contexts::config::{Config, WsConfig};
+use crate::schemas;
+use futures::{channel::mpsc, select, FutureExt, SinkExt, StreamExt};
+use mongodb::bson::oid::ObjectId;
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{self, CloseCode, CloseReason, Codec, Frame, Message};
+use actix_web::{Error, HttpRequest, HttpResponse, ResponseError, Result, get, web, web::{Bytes, BytesMut}};
+
+
+
+
+
+
+
+
+
+
+
+// NOTE - actix-web 3 has no actor based websocket support that works with our actix version, so we do the handshake and frame the messages ourselves with the actix-http codec
+// NOTE - every connection gets its own task which reads the client frames, pings the client and writes the hub activities into the response body stream
+
+
+
+#[get("/ws")]
+async fn subscribe(req: HttpRequest, payload: web::Payload, query: web::Query<schemas::ws::SubscribeQuery>) -> Result<HttpResponse, Error>{
+
+    let config = req.app_data::<web::Data<Config>>().unwrap(); //-- unwrapping the config inside the web data structure which is passed inside the data() method
+    let hub = req.app_data::<web::Data<ctx::hub::Hub>>().unwrap(); //-- unwrapping the hub inside the web data structure which is passed inside the data() method
+
+    let mut response = match ws::handshake(req.head()){ //-- checking the upgrade headers and building the 101 switching protocols response
+        Ok(response) => response,
+        Err(e) => return Ok(e.error_response()),
+    };
+    let (id, activities) = hub.subscribe(config.ws.buffer, Some(HashSet::new())); //-- a socket starts watching nothing until it subscribes
+    let (outgoing, body) = mpsc::channel::<Result<Bytes, Error>>(config.ws.buffer); //-- bounded so a slow socket makes the session wait instead of buffering forever
+    let mut session = Session{
+        id,
+        hub: hub.get_ref().clone(),
+        config: config.ws.clone(),
+        codec: Codec::new(),
+        outgoing,
+    };
+    let proposals = query.proposals();
+    actix_web::rt::spawn(async move {
+        if !proposals.is_empty(){
+            session.subscribe(proposals).await;
+        }
+        session.run(payload, activities).await;
+    });
+    Ok(response.streaming(body))
+
+}
+
+
+
+struct Session{
+    id: u64,
+    hub: ctx::hub::Hub,
+    config: WsConfig,
+    codec: Codec,
+    outgoing: mpsc::Sender<Result<Bytes, Error>>,
+}
+
+impl Session{
+
+    async fn run(&mut self, payload: web::Payload, activities: mpsc::Receiver<ctx::hub::Envelope>){
+        let mut payload = payload.fuse();
+        let mut activities = activities.fuse();
+        let mut heartbeat = actix_web::rt::time::interval(Duration::from_secs(self.config.heartbeat_interval));
+        let mut buffer = BytesMut::new();
+        let mut last_seen = Instant::now();
+        loop{
+            let alive = select!{
+                chunk = payload.next() => match chunk{
+                    Some(Ok(chunk)) => {
+                        last_seen = Instant::now();
+                        buffer.extend_from_slice(&chunk);
+                        self.read(&mut buffer).await
+                    },
+                    _ => false, //-- the client went away
+                },
+                activity = activities.next() => match activity{
+                    Some(activity) => self.send(Message::Text(serde_json::to_string(&activity).unwrap())).await,
+                    None => { //-- the hub dropped us cause we couldn't keep up with the activities
+                        self.close(CloseCode::Policy, "too slow to keep up with the updates").await;
+                        false
+                    },
+                },
+                _ = heartbeat.tick().fuse() => {
+                    if last_seen.elapsed() > Duration::from_secs(self.config.client_timeout){
+                        self.close(CloseCode::Away, "heartbeat timed out").await;
+                        false
+                    } else{
+                        self.send(Message::Ping(Bytes::new())).await
+                    }
+                },
+            };
+            if !alive{
+                break;
+            }
+        }
+        self.hub.unsubscribe(self.id);
+    }
+
+    async fn read(&mut self, buffer: &mut BytesMut) -> bool{ //-- handles every complete frame inside the buffer, returns false once the session must end
+        loop{
+            let frame = match self.codec.decode(buffer){
+                Ok(Some(frame)) => frame,
+                Ok(None) => return true, //-- waiting for the rest of the frame
+                Err(_) => {
+                    self.close(CloseCode::Protocol, "invalid frame").await;
+                    return false;
+                },
+            };
+            let alive = match frame{
+                Frame::Text(text) => self.handle(&text).await,
+                Frame::Ping(bytes) => self.send(Message::Pong(bytes)).await,
+                Frame::Pong(_) => true,
+                Frame::Close(reason) => {
+                    self.send(Message::Close(reason)).await;
+                    false
+                },
+                Frame::Binary(_) | Frame::Continuation(_) => self.reply(schemas::ws::ServerMessage::Error{ message: "only json text frames are supported".to_string() }).await,
+            };
+            if !alive{
+                return false;
+            }
+        }
+    }
+
+    async fn handle(&mut self, text: &[u8]) -> bool{
+        match serde_json::from_slice::<schemas::ws::ClientMessage>(text){
+            Ok(schemas::ws::ClientMessage::Subscribe{proposals}) => self.subscribe(proposals).await,
+            Ok(schemas::ws::ClientMessage::Unsubscribe{proposals}) => {
+                let proposals = proposals.into_iter().filter_map(|p| ObjectId::parse_str(&p).ok()).map(|p| p.to_hex()).collect::<Vec<String>>();
+                self.hub.unwatch(self.id, proposals.clone());
+                self.reply(schemas::ws::ServerMessage::Unsubscribed{ proposals }).await
+            },
+            Err(e) => self.reply(schemas::ws::ServerMessage::Error{ message: e.to_string() }).await,
+        }
+    }
+
+    async fn subscribe(&mut self, proposals: Vec<String>) -> bool{
+        let (valid, invalid): (Vec<String>, Vec<String>) = proposals.into_iter().partition(|p| ObjectId::parse_str(p).is_ok());
+        let valid = valid.iter().map(|p| ObjectId::parse_str(p).unwrap().to_hex()).collect::<Vec<String>>(); //-- the hub matches on the lowercase hex form
+        self.hub.watch(self.id, valid.clone());
+        if !invalid.is_empty() && !self.reply(schemas::ws::ServerMessage::Error{ message: format!("invalid proposal ids {:?}", invalid) }).await{
+            return false;
+        }
+        self.reply(schemas::ws::ServerMessage::Subscribed{ proposals: valid }).await
+    }
+
+    async fn reply(&mut self, message: schemas::ws::ServerMessage) -> bool{
+        self.send(Message::Text(serde_json::to_string(&message).unwrap())).await
+    }
+
+    async fn close(&mut self, code: CloseCode, description: &str){
+        self.send(Message::Close(Some(CloseReason{ code, description: Some(description.to_string()) }))).await;
+    }
+
+    async fn send(&mut self, message: Message) -> bool{ //-- waits while the outgoing buffer is full, returns false once the response body is gone or the client stopped reading for too long
+        let mut frame = BytesMut::new();
+        if self.codec.encode(message, &mut frame).is_err(){
+            return false;
+        }
+        let timeout = Duration::from_secs(self.config.client_timeout);
+        matches!(actix_web::rt::time::timeout(timeout, self.outgoing.send(Ok(frame.freeze()))).await, Ok(Ok(())))
+    }
+}
+
+
+
+
+
+
+
+
+pub fn register(config: &mut web::ServiceConfig){
+    config.service(subscribe);
+}
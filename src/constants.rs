@@ -9,4 +9,69 @@ pub static NOT_FOUND_DOCUMENT: &str = "Not Found Document";
 pub static FOUND_DOCUMENT: &str = "Found Document";
 pub static INSERTED: &str = "Inserted Successfully";
 pub static UPDATED: &str = "Updated Successfully";
-pub static FETCHED: &str = "Fetched Successfully";
\ No newline at end of file
+pub static FETCHED: &str = "Fetched Successfully";
+pub static DELETED: &str = "Deleted Successfully";
+pub static WRONG_API_KEY: &str = "Wrong API Key";
+pub static INVALID_CURSOR: &str = "Invalid Cursor";
+pub static INVALID_ID: &str = "Invalid Id";
+pub static ALREADY_VOTED: &str = "Already Voted";
+pub static WRONG_ADMIN_KEY: &str = "Wrong Admin Key";
+pub static VOTING_CLOSED: &str = "Voting Is Closed";
+pub static NOT_CLOSED_YET: &str = "Not Closed Yet";
+pub static BALLOTS_TAMPERED: &str = "Ballots Do Not Match The Published Merkle Root";
+pub static NO_ATTESTATION_KEY: &str = "No Attestation Key Is Configured";
+pub static COMMIT_REVEAL_ONLY: &str = "This Proposal Only Takes Committed Ballots";
+pub static NOT_COMMIT_REVEAL: &str = "This Proposal Is Not Commit-Reveal";
+pub static COMMIT_PHASE_OVER: &str = "Commit Phase Is Over";
+pub static REVEAL_NOT_STARTED: &str = "Reveal Phase Has Not Started Yet";
+pub static INVALID_COMMITMENT: &str = "Commitment Must Be A Hex Encoded Sha256";
+pub static COMMITMENT_MISMATCH: &str = "Ballot And Salt Do Not Match The Commitment";
+pub static ENCRYPTED_ONLY: &str = "This Proposal Only Takes Encrypted Ballots";
+pub static NOT_ENCRYPTED: &str = "This Proposal Is Not Encrypted";
+pub static ENCRYPTION_SETUP_REQUIRED: &str = "Encrypted Proposals Need The Encryption Setup Of Their Trustees";
+pub static INVALID_BALLOT_PROOF: &str = "The Ballot Proof Does Not Verify";
+pub static INVALID_DECRYPTION_SHARE: &str = "The Decryption Share Does Not Verify";
+pub static UNKNOWN_TRUSTEE: &str = "Unknown Trustee";
+pub static ALREADY_DECRYPTED: &str = "Tally Is Already Decrypted";
+pub static ENCRYPTED_TALLY: &str = "Encrypted Tallies Are Not In The Vote Log";
+pub static SHARE_ALREADY_SUBMITTED: &str = "Share Already Submitted";
+pub static WALLET_REQUIRED: &str = "Wallet Address Is Required";
+pub static SELF_DELEGATION: &str = "A Wallet Can Not Delegate To Itself";
+pub static DELEGATION_CYCLE: &str = "This Delegation Would Create A Cycle";
+pub static DELEGATION_RACE: &str = "Another Delegation Of This Scope Was Just Made";
+pub static CONVICTION_ONLY: &str = "This Proposal Only Takes Stakes";
+pub static NOT_CONVICTION: &str = "This Proposal Is Not A Conviction Proposal";
+pub static REQUESTED_AMOUNT_REQUIRED: &str = "Conviction Proposals Need A Requested Amount";
+pub static REQUESTED_AMOUNT_TOO_HIGH: &str = "Requested Amount Is Too High For The Pool";
+pub static STAKE_OVER_SCORE: &str = "Stakes Can Not Add Up To More Than The Score";
+pub static VOTING_NOT_STARTED: &str = "Voting Has Not Started Yet";
+pub static ANTI_SNIPING_OPEN_ONLY: &str = "Anti-Sniping Only Applies To Open Proposals";
+pub static NOT_THE_CREATOR: &str = "Only The Creator Can Edit This Proposal";
+pub static CONTENT_LOCKED: &str = "Content Is Locked Once Voting Starts";
+pub static TITLE_REQUIRED: &str = "Title Is Required";
+pub static NOTHING_TO_EDIT: &str = "Nothing To Edit";
+pub static TITLE_TAKEN: &str = "Another Proposal Has This Title";
+pub static EDIT_RACE: &str = "The Proposal Changed While Editing";
+pub static LOGIN_DISABLED: &str = "Wallet Login Is Not Configured";
+pub static INVALID_WALLET: &str = "Wallet Address Must Be A Base58 Ed25519 Public Key";
+pub static INVALID_SIGNATURE: &str = "The Signature Does Not Verify";
+pub static CHALLENGE_EXPIRED: &str = "Challenge Expired Or Already Used";
+pub static LOGIN_REQUIRED: &str = "Wallet Login Required";
+pub static COMMENT_EMPTY: &str = "Comment Is Empty";
+pub static COMMENT_TOO_LONG: &str = "Comment Is Too Long";
+pub static THREAD_TOO_DEEP: &str = "Replies Can Not Nest Any Deeper";
+pub static NOT_THE_AUTHOR: &str = "Only The Author Can Edit This Comment";
+pub static COMMENT_HIDDEN: &str = "Comment Is Hidden";
+pub static UNKNOWN_REACTION: &str = "Unknown Reaction";
+pub static ALREADY_REACTED: &str = "Already Reacted";
+pub static NOT_A_MODERATOR: &str = "Only Moderators Can Do This";
+pub static UNKNOWN_CATEGORY: &str = "Category Is Not In The Taxonomy";
+pub static UNKNOWN_TAG: &str = "Tag Is Not In The Taxonomy";
+pub static INVALID_SLUG: &str = "Slug Must Have At Least One Letter Or Digit";
+pub static TERM_EXISTS: &str = "A Term Of This Kind Already Has This Slug";
+pub static TERM_IN_USE: &str = "Term Is Still Used By Proposals Or Delegations";
+pub static NAME_REQUIRED: &str = "Name Is Required";
+pub static SEARCH_TEXT_REQUIRED: &str = "Search Text Is Required";
+pub static NOT_YOUR_BALLOT: &str = "Only The Wallet That Cast A Ballot Can Change It";
+pub static SCORE_TOO_HIGH: &str = "Score Is Higher Than The Maximum";
+pub static NOT_THE_DELEGATOR: &str = "Only The Delegator Can Change Its Delegations";
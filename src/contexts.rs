@@ -5,4 +5,6 @@
 
 
 
-pub mod app;
\ No newline at end of file
+pub mod app;
+pub mod config;
+pub mod hub;
\ No newline at end of file
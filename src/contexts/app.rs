@@ -21,6 +21,7 @@ use actix_web::{Error, HttpRequest, HttpResponse, Result};
 
 
 
+#[allow(dead_code)] // NOTE - not wired to any route yet, handlers are registered directly through actix macros
 #[derive(Debug)]
 pub struct Api{
     pub name: String,
@@ -29,6 +30,7 @@ pub struct Api{
 }
 
 
+#[allow(dead_code)]
 impl Api{
 
     pub async fn new(request: Option<HttpRequest>, response: Option<HttpResponse>) -> Self{
@@ -92,7 +94,7 @@ impl Db{
         )
     }
     
-    pub async fn GetMongoDbInstance(&self) -> Client{ //-- it'll return an instance of the mongodb client
+    pub async fn get_mongodb_instance(&self) -> Client{ //-- it'll return an instance of the mongodb client
         Client::with_uri_str(self.url.as_ref().unwrap()).unwrap() //-- building mongodb client instance
     }
 
@@ -103,12 +105,14 @@ impl Db{
 
 #[derive(Clone, Debug)]
 pub struct Storage{
+    #[allow(dead_code)]
     pub id: Uuid,
     pub db: Option<Db>, //-- we could have no db at all
 }
 
 
 
+#[allow(dead_code)]
 #[derive(Copy, Clone, Debug)]
 pub enum Mode{
     On,
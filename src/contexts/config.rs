@@ -0,0 +1,802 @@
+
+
+
+
+use std::{collections::BTreeMap, env, fmt, fs, net::{IpAddr, SocketAddr}, path::{Path, PathBuf}};
+use clap::Parser;
+
+
+
+
+
+
+
+// NOTE - every setting is known by its env var name, the toml file uses the same names in lowercase with the first underscore turned into a table, like `[db] host = ...` for DB_HOST
+// NOTE - the precedence is defaults < toml file < .env and env vars < cli flags, .env never overrides an already exported env var
+
+
+
+pub const KEYS: &[(&str, Option<&str>)] = &[ //-- (key, default value) - keys with no default are required
+    ("ENVIRONMENT", Some("dev")),
+    ("HOST", Some("0.0.0.0")),
+    ("PORT", Some("7435")),
+    ("COMPANY_NAME", Some("Bitdad")),
+    ("DB_ENGINE", Some("mongodb")),
+    ("DB_HOST", Some("localhost")),
+    ("DB_PORT", Some("27017")),
+    ("DB_USERNAME", Some("")),
+    ("DB_PASSWORD", Some("")),
+    ("DB_NAME", Some("bitrader")),
+    ("EVENT_EXPIRATION", Some("172800")),
+    ("EVENT_QUORUM", Some("0")),
+    ("EVENT_SWEEP_INTERVAL", Some("30")),
+    ("EVENT_REVEAL_PERIOD", Some("86400")),
+    ("EVENT_MIN_DURATION", Some("3600")),
+    ("EVENT_MAX_DURATION", Some("2592000")),
+    ("EVENT_MAX_START_DELAY", Some("2592000")),
+    ("EVENT_RATIONALE_MAX_LENGTH", Some("2000")),
+    ("EVENT_MAX_SCORE", Some("1000000")),
+    ("CONVICTION_HALF_LIFE", Some("259200")),
+    ("CONVICTION_FUNDS", Some("100000")),
+    ("CONVICTION_SUPPLY", Some("10000")),
+    ("CONVICTION_MAX_RATIO", Some("0.2")),
+    ("CONVICTION_WEIGHT", Some("0.0025")),
+    ("CONVICTION_INTERVAL", Some("300")),
+    ("WS_HEARTBEAT_INTERVAL", Some("5")),
+    ("WS_CLIENT_TIMEOUT", Some("30")),
+    ("WS_BUFFER", Some("64")),
+    ("SSE_KEEPALIVE_INTERVAL", Some("15")),
+    ("SSE_BUFFER", Some("256")),
+    ("SSE_REPLAY_LIMIT", Some("1000")),
+    ("WEBHOOK_POLL_INTERVAL", Some("5")),
+    ("WEBHOOK_TIMEOUT", Some("10")),
+    ("WEBHOOK_MAX_ATTEMPTS", Some("8")),
+    ("WEBHOOK_BACKOFF_BASE", Some("10")),
+    ("WEBHOOK_BACKOFF_MAX", Some("3600")),
+    ("WEBHOOK_BATCH", Some("50")),
+    ("ADMIN_KEY", Some("")),
+    ("AUTH_SECRET", Some("")),
+    ("AUTH_TOKEN_TTL", Some("86400")),
+    ("AUTH_CHALLENGE_TTL", Some("300")),
+    ("COMMENT_MAX_LENGTH", Some("5000")),
+    ("COMMENT_MAX_DEPTH", Some("8")),
+    ("COMMENT_MODERATORS", Some("")),
+    ("ATTESTATION_KEYS", Some("")),
+    ("ATTESTATION_ACTIVE_KEY", Some("")),
+    ("EVENT_DELETE_KEY", None),
+];
+
+
+
+const SECRET_KEYS: &[&str] = &["DB_PASSWORD", "ADMIN_KEY", "AUTH_SECRET", "ATTESTATION_KEYS", "EVENT_DELETE_KEY"]; //-- their values never show up inside an error
+
+
+
+#[derive(Parser, Debug, Default)]
+#[clap(name = "biter", about = "bitrader proposal and voting service")]
+pub struct Cli{
+    #[clap(long, value_name = "FILE", help = "path to a toml config file, defaults to CONFIG_FILE or ./biter.toml if it exists")]
+    pub config: Option<PathBuf>,
+    #[clap(long)]
+    pub environment: Option<String>,
+    #[clap(long)]
+    pub host: Option<String>,
+    #[clap(long)]
+    pub port: Option<String>,
+    #[clap(long)]
+    pub db_engine: Option<String>,
+    #[clap(long)]
+    pub db_host: Option<String>,
+    #[clap(long)]
+    pub db_port: Option<String>,
+    #[clap(long)]
+    pub db_name: Option<String>,
+    #[clap(long)]
+    pub event_expiration: Option<String>,
+}
+
+impl Cli{
+
+    fn overrides(&self) -> Vec<(&'static str, &String)>{
+        let flags = [
+            ("ENVIRONMENT", &self.environment),
+            ("HOST", &self.host),
+            ("PORT", &self.port),
+            ("DB_ENGINE", &self.db_engine),
+            ("DB_HOST", &self.db_host),
+            ("DB_PORT", &self.db_port),
+            ("DB_NAME", &self.db_name),
+            ("EVENT_EXPIRATION", &self.event_expiration),
+        ];
+        flags.into_iter().filter_map(|(key, value)| value.as_ref().map(|v| (key, v))).collect()
+    }
+}
+
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Source{
+    Default,
+    File,
+    Env,
+    Cli,
+}
+
+impl fmt::Display for Source{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        let name = match self{
+            Source::Default => "default",
+            Source::File => "config file",
+            Source::Env => "env",
+            Source::Cli => "cli flag",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+
+#[derive(Debug)]
+pub enum ConfigError{
+    Missing(&'static str),
+    Invalid{ key: &'static str, value: String, source: Source, reason: &'static str },
+    UnknownKey{ key: String, path: PathBuf },
+    File{ path: PathBuf, reason: String },
+}
+
+impl fmt::Display for ConfigError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            ConfigError::Missing(key) => write!(f, "no value set for {}, export it or put it inside .env, the config file or pass it as a cli flag", key),
+            ConfigError::Invalid{key, value, source, reason} => write!(f, "invalid value `{}` for {} (from {}): {}", value, key, source, reason),
+            ConfigError::UnknownKey{key, path} => write!(f, "unknown key `{}` in config file {}", key, path.display()),
+            ConfigError::File{path, reason} => write!(f, "can't load config file {}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError{}
+
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Environment{
+    Dev,
+    Prod,
+}
+
+
+
+#[derive(Clone)]
+pub struct DbConfig{
+    pub engine: String,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub name: String,
+}
+
+impl fmt::Debug for DbConfig{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{ //-- never print the password
+        f.debug_struct("DbConfig")
+            .field("engine", &self.engine)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl DbConfig{
+
+    pub fn url(&self, environment: Environment) -> String{
+        match (environment, self.username.as_ref(), self.password.as_ref()){
+            (Environment::Prod, Some(username), Some(password)) => format!("{}://{}:{}@{}:{}", self.engine, username, password, self.host, self.port),
+            _ => format!("{}://{}:{}", self.engine, self.host, self.port), //-- no credentials in dev
+        }
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct EventConfig{
+    pub expiration: i64, // NOTE - in seconds
+    pub quorum: u64, // NOTE - total voters' score needed, 0 means no quorum
+    pub sweep_interval: u64, // NOTE - seconds between two checks for events that passed their expire_at
+    pub reveal_period: i64, // NOTE - seconds at the end of a commit-reveal event during which the commitments are revealed
+    pub min_duration: i64, // NOTE - the shortest voting window a creator can ask for, in seconds
+    pub max_duration: i64, // NOTE - the longest one
+    pub max_start_delay: i64, // NOTE - how far in the future voting can be scheduled to start, in seconds
+    pub rationale_max_length: usize, // NOTE - characters in the rationale of a ballot
+    pub max_score: u32, // NOTE - the highest score a ballot, a stake or a delegation can carry
+    pub delete_key: String,
+}
+
+impl fmt::Debug for EventConfig{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{ //-- never print the delete key
+        f.debug_struct("EventConfig")
+            .field("expiration", &self.expiration)
+            .field("quorum", &self.quorum)
+            .field("sweep_interval", &self.sweep_interval)
+            .field("reveal_period", &self.reveal_period)
+            .field("min_duration", &self.min_duration)
+            .field("max_duration", &self.max_duration)
+            .field("max_start_delay", &self.max_start_delay)
+            .field("rationale_max_length", &self.rationale_max_length)
+            .field("max_score", &self.max_score)
+            .field("delete_key", &redacted(&Some(&self.delete_key)))
+            .finish()
+    }
+}
+
+
+
+#[derive(Clone, Debug)]
+pub struct ConvictionConfig{
+    pub half_life: i64, // NOTE - seconds for the conviction to get halfway to the staked amount
+    pub funds: u64, // NOTE - what the grants pool holds, in the unit of the requested amounts
+    pub supply: u64, // NOTE - the total score that could be staked
+    pub max_ratio: f64, // NOTE - a proposal can't request this share of the funds or more
+    pub weight: f64, // NOTE - scales every threshold, the lower the easier to pass
+    pub interval: u64, // NOTE - seconds between two recomputations of the open conviction proposals
+}
+
+impl ConvictionConfig{
+
+    pub fn threshold(&self, requested_amount: u64) -> Option<f64>{ //-- the conviction needed to pass, it grows with the share of the funds requested and there's none past max_ratio
+        let ratio = requested_amount as f64 / self.funds as f64;
+        if ratio >= self.max_ratio{
+            return None;
+        }
+        Some(self.weight * self.supply as f64 / (self.max_ratio - ratio).powi(2))
+    }
+
+    pub fn grow(&self, conviction: f64, staked: u64, elapsed: i64) -> f64{ //-- the conviction after `elapsed` seconds of a constant stake, it moves halfway to the stake every half life
+        let staked = staked as f64;
+        staked + (conviction - staked) * 0.5_f64.powf(elapsed.max(0) as f64 / self.half_life as f64)
+    }
+
+    pub fn time_to(&self, conviction: f64, staked: u64, target: f64) -> Option<i64>{ //-- seconds until a constant stake takes the conviction to the target, None if it never does
+        let staked = staked as f64;
+        if conviction >= target{
+            Some(0)
+        } else if staked <= target{
+            None
+        } else{
+            Some((self.half_life as f64 * ((staked - conviction) / (staked - target)).log2()).ceil() as i64)
+        }
+    }
+}
+
+
+
+#[derive(Clone, Debug)]
+pub struct WsConfig{
+    pub heartbeat_interval: u64, // NOTE - seconds between two pings
+    pub client_timeout: u64, // NOTE - seconds without hearing from the client before we close the socket
+    pub buffer: usize, // NOTE - messages queued per connection before it's dropped as too slow
+}
+
+
+
+#[derive(Clone, Debug)]
+pub struct SseConfig{
+    pub keepalive_interval: u64, // NOTE - seconds between two comment lines that keep proxies from closing an idle stream
+    pub buffer: usize, // NOTE - activities queued per stream before it's dropped as too slow
+    pub replay_limit: i64, // NOTE - most activities replayed after a Last-Event-ID, a client further behind is told to resync
+}
+
+
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig{
+    pub poll_interval: u64, // NOTE - seconds between two scans of the delivery queue
+    pub timeout: u64, // NOTE - seconds a receiver has to answer before the attempt counts as failed
+    pub max_attempts: u32, // NOTE - a delivery goes to the dead letters once it failed this many times
+    pub backoff_base: u64, // NOTE - seconds before the first retry, doubled after every failed attempt
+    pub backoff_max: u64, // NOTE - the longest wait between two attempts
+    pub batch: u32, // NOTE - deliveries sent per scan
+}
+
+
+
+#[derive(Clone)]
+pub struct AuthConfig{
+    pub secret: Option<String>, // NOTE - signs the session tokens of the wallets, nobody can log in if it's not set
+    pub token_ttl: i64, // NOTE - seconds a session token stays valid
+    pub challenge_ttl: i64, // NOTE - seconds a wallet has to sign its login challenge
+}
+
+impl fmt::Debug for AuthConfig{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{ //-- never print the secret
+        write!(f, "AuthConfig{{ secret: {}, token_ttl: {}, challenge_ttl: {} }}", redacted(&self.secret), self.token_ttl, self.challenge_ttl)
+    }
+}
+
+
+
+#[derive(Clone, Debug)]
+pub struct CommentConfig{
+    pub max_length: usize, // NOTE - characters in a comment
+    pub max_depth: u32, // NOTE - how deep replies can nest, top level comments are at 0
+    pub moderators: Vec<String>, // NOTE - wallets that can hide comments besides the admin key
+}
+
+
+
+#[derive(Clone)]
+pub struct AttestationKey{
+    pub id: String,
+    pub seed: [u8; 32], // NOTE - the ed25519 secret key
+}
+
+impl fmt::Debug for AttestationKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{ //-- never print the seed
+        write!(f, "AttestationKey({})", self.id)
+    }
+}
+
+
+
+#[derive(Clone, Debug)]
+pub struct AttestationConfig{
+    pub keys: Vec<AttestationKey>, // NOTE - every key that ever signed, their public keys stay published so old attestations can still be checked
+    pub active: Option<String>, // NOTE - id of the key new attestations are signed with, results aren't attested if there's no key
+}
+
+impl AttestationConfig{
+
+    pub fn active_key(&self) -> Option<&AttestationKey>{
+        self.active.as_ref().and_then(|id| self.keys.iter().find(|k| &k.id == id))
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct Config{
+    pub environment: Environment,
+    pub host: IpAddr,
+    pub port: u16,
+    pub company_name: String,
+    pub db: DbConfig,
+    pub event: EventConfig,
+    pub conviction: ConvictionConfig,
+    pub ws: WsConfig,
+    pub sse: SseConfig,
+    pub webhook: WebhookConfig,
+    pub admin_key: Option<String>, //-- the admin api is disabled if it's not set
+    pub auth: AuthConfig,
+    pub comment: CommentConfig,
+    pub attestation: AttestationConfig,
+}
+
+impl fmt::Debug for Config{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{ //-- the secrets inside are only said to be set or not, so the whole config can be logged
+        f.debug_struct("Config")
+            .field("environment", &self.environment)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("company_name", &self.company_name)
+            .field("db", &self.db)
+            .field("event", &self.event)
+            .field("conviction", &self.conviction)
+            .field("ws", &self.ws)
+            .field("sse", &self.sse)
+            .field("webhook", &self.webhook)
+            .field("admin_key", &redacted(&self.admin_key))
+            .field("auth", &self.auth)
+            .field("comment", &self.comment)
+            .field("attestation", &self.attestation)
+            .finish()
+    }
+}
+
+impl Config{
+
+    pub fn load() -> Result<Config, ConfigError>{
+        dotenv::dotenv().ok(); //-- .env is optional, it only fills the env vars that are not already exported
+        let cli = Cli::parse();
+        let file = cli.config.clone()
+                        .or_else(|| env::var("CONFIG_FILE").ok().map(PathBuf::from))
+                        .or_else(|| Some(PathBuf::from("biter.toml")).filter(|p| p.exists()));
+        Config::from_raw(&Raw::layered(file.as_deref(), &cli)?)
+    }
+
+    pub fn server_addr(&self) -> SocketAddr{
+        SocketAddr::new(self.host, self.port)
+    }
+
+    fn from_raw(raw: &Raw) -> Result<Config, ConfigError>{
+        let mut config = Config{
+            environment: raw.parse("ENVIRONMENT", "expected dev or prod", |v| match v{
+                "dev" => Some(Environment::Dev),
+                "prod" => Some(Environment::Prod),
+                _ => None,
+            })?,
+            host: raw.parse("HOST", "expected an ip address", |v| v.parse().ok())?,
+            port: raw.parse("PORT", "expected a port number between 1 and 65535", |v| v.parse().ok().filter(|p| *p != 0))?,
+            company_name: raw.get("COMPANY_NAME")?.0.clone(),
+            db: DbConfig{
+                engine: raw.parse("DB_ENGINE", "only mongodb is supported", |v| Some(v.to_string()).filter(|e| e == "mongodb"))?,
+                host: raw.non_empty("DB_HOST")?,
+                port: raw.parse("DB_PORT", "expected a port number between 1 and 65535", |v| v.parse().ok().filter(|p| *p != 0))?,
+                username: raw.optional("DB_USERNAME"),
+                password: raw.optional("DB_PASSWORD"),
+                name: raw.parse("DB_NAME", "expected a mongodb database name without spaces, dots or slashes", |v| Some(v.to_string()).filter(|n| !n.is_empty() && !n.contains(|c: char| c.is_whitespace() || "./\\\"$".contains(c))))?,
+            },
+            event: EventConfig{
+                expiration: raw.parse("EVENT_EXPIRATION", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                quorum: raw.parse("EVENT_QUORUM", "expected a non negative total score", |v| v.parse().ok())?,
+                sweep_interval: raw.parse("EVENT_SWEEP_INTERVAL", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                reveal_period: raw.parse("EVENT_REVEAL_PERIOD", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                min_duration: raw.parse("EVENT_MIN_DURATION", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                max_duration: raw.parse("EVENT_MAX_DURATION", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                max_start_delay: raw.parse("EVENT_MAX_START_DELAY", "expected a non negative number of seconds", |v| v.parse().ok().filter(|s| *s >= 0))?,
+                rationale_max_length: raw.parse("EVENT_RATIONALE_MAX_LENGTH", "expected a positive number of characters", |v| v.parse().ok().filter(|s| *s > 0))?,
+                max_score: raw.parse("EVENT_MAX_SCORE", "expected a positive score", |v| v.parse().ok().filter(|s| *s > 0))?,
+                delete_key: raw.non_empty("EVENT_DELETE_KEY")?,
+            },
+            conviction: ConvictionConfig{
+                half_life: raw.parse("CONVICTION_HALF_LIFE", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                funds: raw.parse("CONVICTION_FUNDS", "expected a positive amount", |v| v.parse().ok().filter(|f| *f > 0))?,
+                supply: raw.parse("CONVICTION_SUPPLY", "expected a positive total score", |v| v.parse().ok().filter(|s| *s > 0))?,
+                max_ratio: raw.parse("CONVICTION_MAX_RATIO", "expected a share of the funds above 0 and up to 1", |v| v.parse().ok().filter(|r: &f64| *r > 0.0 && *r <= 1.0))?,
+                weight: raw.parse("CONVICTION_WEIGHT", "expected a positive number", |v| v.parse().ok().filter(|w: &f64| *w > 0.0 && w.is_finite()))?,
+                interval: raw.parse("CONVICTION_INTERVAL", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+            },
+            ws: WsConfig{
+                heartbeat_interval: raw.parse("WS_HEARTBEAT_INTERVAL", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                client_timeout: raw.parse("WS_CLIENT_TIMEOUT", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                buffer: raw.parse("WS_BUFFER", "expected a positive number of messages", |v| v.parse().ok().filter(|s| *s > 0))?,
+            },
+            sse: SseConfig{
+                keepalive_interval: raw.parse("SSE_KEEPALIVE_INTERVAL", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                buffer: raw.parse("SSE_BUFFER", "expected a positive number of activities", |v| v.parse().ok().filter(|s| *s > 0))?,
+                replay_limit: raw.parse("SSE_REPLAY_LIMIT", "expected a positive number of activities", |v| v.parse().ok().filter(|s| *s > 0))?,
+            },
+            webhook: WebhookConfig{
+                poll_interval: raw.parse("WEBHOOK_POLL_INTERVAL", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                timeout: raw.parse("WEBHOOK_TIMEOUT", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                max_attempts: raw.parse("WEBHOOK_MAX_ATTEMPTS", "expected a positive number of attempts", |v| v.parse().ok().filter(|s| *s > 0))?,
+                backoff_base: raw.parse("WEBHOOK_BACKOFF_BASE", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                backoff_max: raw.parse("WEBHOOK_BACKOFF_MAX", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                batch: raw.parse("WEBHOOK_BATCH", "expected a positive number of deliveries", |v| v.parse().ok().filter(|s| *s > 0))?,
+            },
+            admin_key: raw.optional("ADMIN_KEY"),
+            auth: AuthConfig{
+                secret: raw.optional("AUTH_SECRET"),
+                token_ttl: raw.parse("AUTH_TOKEN_TTL", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+                challenge_ttl: raw.parse("AUTH_CHALLENGE_TTL", "expected a positive number of seconds", |v| v.parse().ok().filter(|s| *s > 0))?,
+            },
+            comment: CommentConfig{
+                max_length: raw.parse("COMMENT_MAX_LENGTH", "expected a positive number of characters", |v| v.parse().ok().filter(|s| *s > 0))?,
+                max_depth: raw.parse("COMMENT_MAX_DEPTH", "expected a non negative depth", |v| v.parse().ok())?,
+                moderators: raw.parse("COMMENT_MODERATORS", "expected comma separated wallet addresses", |v| Some(v.split(',').map(|w| w.trim().to_string()).filter(|w| !w.is_empty()).collect()))?,
+            },
+            attestation: AttestationConfig{
+                keys: raw.parse("ATTESTATION_KEYS", "expected comma separated <key id>:<ed25519 seed as 64 hex chars> pairs with unique ids", parse_attestation_keys)?,
+                active: raw.optional("ATTESTATION_ACTIVE_KEY"),
+            },
+        };
+        match config.attestation.active.as_ref(){
+            Some(_) if config.attestation.active_key().is_none() => {
+                return Err(raw.invalid("ATTESTATION_ACTIVE_KEY", "must be one of the ATTESTATION_KEYS ids")?);
+            },
+            Some(_) => {},
+            None => config.attestation.active = config.attestation.keys.last().map(|k| k.id.clone()), //-- the newest key signs by default
+        }
+        if config.event.expiration < config.event.min_duration || config.event.expiration > config.event.max_duration{ //-- it's the window of the events that don't ask for one
+            return Err(raw.invalid("EVENT_EXPIRATION", "must be between EVENT_MIN_DURATION and EVENT_MAX_DURATION")?);
+        }
+        if config.event.reveal_period >= config.event.max_duration{ //-- no commit-reveal event could have a commit phase, the shorter windows are refused one by one when the event is created
+            return Err(raw.invalid("EVENT_REVEAL_PERIOD", "must be less than EVENT_MAX_DURATION")?);
+        }
+        if config.ws.client_timeout <= config.ws.heartbeat_interval{ //-- the client would be timed out before it even gets a chance to answer a ping
+            return Err(raw.invalid("WS_CLIENT_TIMEOUT", "must be greater than WS_HEARTBEAT_INTERVAL")?);
+        }
+        Ok(config)
+    }
+}
+
+
+
+struct Raw(BTreeMap<&'static str, (String, Source)>);
+
+impl Raw{
+
+    fn defaults() -> Raw{
+        Raw(KEYS.iter().filter_map(|(key, default)| default.map(|d| (*key, (d.to_string(), Source::Default)))).collect())
+    }
+
+    fn layered(file: Option<&Path>, cli: &Cli) -> Result<Raw, ConfigError>{ //-- each layer overrides the ones before it
+        let mut raw = Raw::defaults();
+        if let Some(path) = file{
+            raw.merge_file(path)?;
+        }
+        raw.merge_env();
+        for (key, value) in cli.overrides(){
+            raw.set(key, value.clone(), Source::Cli);
+        }
+        Ok(raw)
+    }
+
+    fn set(&mut self, key: &'static str, value: String, source: Source){
+        self.0.insert(key, (value, source));
+    }
+
+    fn merge_env(&mut self){
+        for (key, _) in KEYS{
+            if let Ok(value) = env::var(key){
+                self.set(key, value, Source::Env);
+            }
+        }
+    }
+
+    fn merge_file(&mut self, path: &Path) -> Result<(), ConfigError>{
+        let content = fs::read_to_string(path).map_err(|e| ConfigError::File{ path: path.to_path_buf(), reason: e.to_string() })?;
+        let table = content.parse::<toml::Value>().map_err(|e| ConfigError::File{ path: path.to_path_buf(), reason: e.to_string() })?;
+        let mut flat = vec![];
+        flatten(None, &table, &mut flat);
+        for (name, value) in flat{
+            let key = KEYS.iter().map(|(k, _)| *k).find(|k| *k == name.to_uppercase()).ok_or_else(|| ConfigError::UnknownKey{ key: name.clone(), path: path.to_path_buf() })?;
+            self.set(key, value, Source::File);
+        }
+        Ok(())
+    }
+
+    fn get(&self, key: &'static str) -> Result<&(String, Source), ConfigError>{
+        self.0.get(key).ok_or(ConfigError::Missing(key))
+    }
+
+    fn optional(&self, key: &'static str) -> Option<String>{
+        self.0.get(key).map(|(v, _)| v.clone()).filter(|v| !v.is_empty())
+    }
+
+    fn non_empty(&self, key: &'static str) -> Result<String, ConfigError>{
+        self.parse(key, "must not be empty", |v| Some(v.to_string()).filter(|v| !v.trim().is_empty()))
+    }
+
+    fn parse<T, F>(&self, key: &'static str, reason: &'static str, f: F) -> Result<T, ConfigError>
+        where F: Fn(&str) -> Option<T>
+    {
+        let (value, _) = self.get(key)?;
+        match f(value.trim()){
+            Some(parsed) => Ok(parsed),
+            None => Err(self.invalid(key, reason)?),
+        }
+    }
+
+    fn invalid(&self, key: &'static str, reason: &'static str) -> Result<ConfigError, ConfigError>{ //-- the error for the current value of the key
+        let (value, source) = self.get(key)?;
+        Ok(ConfigError::Invalid{
+            key,
+            value: if SECRET_KEYS.contains(&key) { "<redacted>".to_string() } else { value.clone() },
+            source: *source,
+            reason,
+        })
+    }
+}
+
+
+
+fn redacted<T>(secret: &Option<T>) -> &'static str{ //-- what the debug output shows instead of a secret
+    if secret.is_some() { "<redacted>" } else { "<unset>" }
+}
+
+
+
+fn parse_attestation_keys(value: &str) -> Option<Vec<AttestationKey>>{ //-- "2024-01:<hex>,2025-06:<hex>", empty means no key
+    let mut keys: Vec<AttestationKey> = vec![];
+    for pair in value.split(',').map(str::trim).filter(|p| !p.is_empty()){
+        let (id, seed) = pair.split_once(':')?;
+        let id = id.trim();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c)) || keys.iter().any(|k| k.id == id){
+            return None;
+        }
+        let seed = hex::decode(seed.trim()).ok()?.try_into().ok()?;
+        keys.push(AttestationKey{ id: id.to_string(), seed });
+    }
+    Some(keys)
+}
+
+
+
+fn flatten(prefix: Option<&str>, value: &toml::Value, out: &mut Vec<(String, String)>){ //-- turns `[db] host = "x"` into ("db_host", "x")
+    match value{
+        toml::Value::Table(table) => {
+            for (k, v) in table{
+                let name = match prefix{
+                    Some(p) => format!("{}_{}", p, k),
+                    None => k.clone(),
+                };
+                flatten(Some(&name), v, out);
+            }
+        },
+        toml::Value::String(s) => out.push((prefix.unwrap_or_default().to_string(), s.clone())),
+        other => out.push((prefix.unwrap_or_default().to_string(), other.to_string())),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests{
+
+    use super::*;
+
+    fn defaults() -> Raw{ //-- what a fresh install runs with, the delete key being the only required value
+        let mut raw = Raw::defaults();
+        raw.set("EVENT_DELETE_KEY", "delete-key".to_string(), Source::Env);
+        raw
+    }
+
+    fn with(pairs: &[(&'static str, &str)]) -> Raw{
+        let mut raw = defaults();
+        for (key, value) in pairs{
+            raw.set(key, value.to_string(), Source::Env);
+        }
+        raw
+    }
+
+    fn invalid_key(raw: &Raw) -> Option<&'static str>{
+        match Config::from_raw(raw){
+            Err(ConfigError::Invalid{ key, .. }) => Some(key),
+            _ => None,
+        }
+    }
+
+    fn toml_file(name: &str, content: &str) -> PathBuf{
+        let path = env::temp_dir().join(format!("biter-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn defaults_pass_validation(){
+        let config = Config::from_raw(&defaults()).unwrap();
+        assert!(config.event.reveal_period < config.event.max_duration);
+        assert!(config.event.min_duration <= config.event.expiration && config.event.expiration <= config.event.max_duration);
+    }
+
+    #[test]
+    fn delete_key_is_required(){
+        assert!(matches!(Config::from_raw(&Raw::defaults()), Err(ConfigError::Missing("EVENT_DELETE_KEY"))));
+    }
+
+    #[test]
+    fn each_layer_overrides_the_ones_before(){ //-- the only test touching the env vars of the process
+        let path = toml_file("layers", "company_name = \"From File\"\n[db]\nname = \"file_db\"\nhost = \"file-host\"\n[event]\nexpiration = 7200\ndelete_key = \"file-key\"\n");
+        env::set_var("DB_HOST", "env-host");
+        env::set_var("EVENT_EXPIRATION", "10800");
+        let cli = Cli{ db_host: Some("cli-host".to_string()), ..Cli::default() };
+        let raw = Raw::layered(Some(&path), &cli);
+        env::remove_var("DB_HOST");
+        env::remove_var("EVENT_EXPIRATION");
+        fs::remove_file(&path).unwrap();
+        let raw = raw.unwrap();
+        assert_eq!(raw.get("PORT").unwrap(), &("7435".to_string(), Source::Default));
+        assert_eq!(raw.get("COMPANY_NAME").unwrap(), &("From File".to_string(), Source::File));
+        assert_eq!(raw.get("DB_NAME").unwrap(), &("file_db".to_string(), Source::File));
+        assert_eq!(raw.get("EVENT_EXPIRATION").unwrap(), &("10800".to_string(), Source::Env));
+        assert_eq!(raw.get("DB_HOST").unwrap(), &("cli-host".to_string(), Source::Cli));
+        let config = Config::from_raw(&raw).unwrap();
+        assert_eq!((config.company_name.as_str(), config.db.host.as_str(), config.event.expiration), ("From File", "cli-host", 10800));
+    }
+
+    #[test]
+    fn file_errors(){
+        let path = toml_file("unknown", "[db]\nhots = \"typo\"\n");
+        let unknown = Raw::layered(Some(&path), &Cli::default()).err();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(unknown, Some(ConfigError::UnknownKey{ ref key, .. }) if key == "db_hots"));
+        let missing = env::temp_dir().join("biter-no-such-file.toml");
+        assert!(matches!(Raw::layered(Some(&missing), &Cli::default()), Err(ConfigError::File{ .. })));
+    }
+
+    #[test]
+    fn every_value_check_names_its_key(){
+        let cases: &[(&'static str, &str)] = &[
+            ("ENVIRONMENT", "staging"),
+            ("HOST", "localhost"),
+            ("PORT", "0"),
+            ("DB_ENGINE", "postgres"),
+            ("DB_HOST", " "),
+            ("DB_PORT", "70000"),
+            ("DB_NAME", "bit.rader"),
+            ("EVENT_EXPIRATION", "0"),
+            ("EVENT_QUORUM", "-1"),
+            ("EVENT_SWEEP_INTERVAL", "0"),
+            ("EVENT_REVEAL_PERIOD", "0"),
+            ("EVENT_MIN_DURATION", "0"),
+            ("EVENT_MAX_DURATION", "-5"),
+            ("EVENT_MAX_START_DELAY", "-1"),
+            ("EVENT_RATIONALE_MAX_LENGTH", "0"),
+            ("EVENT_MAX_SCORE", "0"),
+            ("EVENT_DELETE_KEY", " "),
+            ("CONVICTION_HALF_LIFE", "0"),
+            ("CONVICTION_FUNDS", "0"),
+            ("CONVICTION_SUPPLY", "0"),
+            ("CONVICTION_MAX_RATIO", "1.5"),
+            ("CONVICTION_WEIGHT", "inf"),
+            ("CONVICTION_INTERVAL", "0"),
+            ("WS_HEARTBEAT_INTERVAL", "0"),
+            ("WS_CLIENT_TIMEOUT", "0"),
+            ("WS_BUFFER", "0"),
+            ("SSE_KEEPALIVE_INTERVAL", "0"),
+            ("SSE_BUFFER", "0"),
+            ("SSE_REPLAY_LIMIT", "0"),
+            ("WEBHOOK_POLL_INTERVAL", "0"),
+            ("WEBHOOK_TIMEOUT", "0"),
+            ("WEBHOOK_MAX_ATTEMPTS", "0"),
+            ("WEBHOOK_BACKOFF_BASE", "0"),
+            ("WEBHOOK_BACKOFF_MAX", "0"),
+            ("WEBHOOK_BATCH", "0"),
+            ("AUTH_TOKEN_TTL", "0"),
+            ("AUTH_CHALLENGE_TTL", "0"),
+            ("COMMENT_MAX_LENGTH", "0"),
+            ("COMMENT_MAX_DEPTH", "-1"),
+            ("ATTESTATION_KEYS", "k1:not-hex"),
+        ];
+        for (key, value) in cases{
+            assert_eq!(invalid_key(&with(&[(key, value)])), Some(*key), "{}={}", key, value);
+        }
+    }
+
+    #[test]
+    fn every_cross_key_check_names_its_key(){
+        let seed = "11".repeat(32);
+        let cases: &[(&'static str, &[(&'static str, &str)])] = &[
+            ("ATTESTATION_ACTIVE_KEY", &[("ATTESTATION_KEYS", &format!("k1:{}", seed)), ("ATTESTATION_ACTIVE_KEY", "k2")]),
+            ("EVENT_EXPIRATION", &[("EVENT_EXPIRATION", "1800")]),
+            ("EVENT_EXPIRATION", &[("EVENT_MAX_DURATION", "86400")]),
+            ("EVENT_REVEAL_PERIOD", &[("EVENT_REVEAL_PERIOD", "2592000")]),
+            ("WS_CLIENT_TIMEOUT", &[("WS_CLIENT_TIMEOUT", "5")]),
+        ];
+        for (key, pairs) in cases{
+            assert_eq!(invalid_key(&with(pairs)), Some(*key), "{:?}", pairs);
+        }
+        assert_eq!(invalid_key(&with(&[("ATTESTATION_KEYS", &format!("k1:{},k1:{}", seed, seed))])), Some("ATTESTATION_KEYS")); //-- ids are unique
+    }
+
+    #[test]
+    fn newest_attestation_key_signs_by_default(){
+        let seed = "11".repeat(32);
+        let config = Config::from_raw(&with(&[("ATTESTATION_KEYS", &format!("old:{},new:{}", seed, seed))])).unwrap();
+        assert_eq!(config.attestation.active_key().map(|k| k.id.as_str()), Some("new"));
+    }
+
+    #[test]
+    fn secrets_are_redacted_inside_errors(){
+        let error = Config::from_raw(&with(&[("ATTESTATION_KEYS", "k1:leaked-seed")])).unwrap_err();
+        assert!(matches!(error, ConfigError::Invalid{ key: "ATTESTATION_KEYS", .. }));
+        assert!(!error.to_string().contains("leaked-seed"));
+        assert!(error.to_string().contains("<redacted>"));
+        let error = Config::from_raw(&with(&[("PORT", "not-a-port")])).unwrap_err();
+        assert!(error.to_string().contains("not-a-port")); //-- the other values are shown to help fixing them
+    }
+
+    #[test]
+    fn secrets_are_redacted_inside_the_dump(){
+        let seed = "ab".repeat(32);
+        let secrets = [
+            ("DB_PASSWORD", "db-password-secret".to_string()),
+            ("ADMIN_KEY", "admin-key-secret".to_string()),
+            ("AUTH_SECRET", "auth-secret-secret".to_string()),
+            ("ATTESTATION_KEYS", format!("k1:{}", seed)),
+            ("EVENT_DELETE_KEY", "delete-key-secret".to_string()),
+        ];
+        assert_eq!(secrets.iter().map(|(k, _)| *k).collect::<Vec<_>>(), SECRET_KEYS); //-- a new secret key needs a case here
+        let pairs = secrets.iter().map(|(k, v)| (*k, v.as_str())).collect::<Vec<_>>();
+        let dump = format!("{:?}", Config::from_raw(&with(&pairs)).unwrap());
+        for value in ["db-password-secret", "admin-key-secret", "auth-secret-secret", &seed, "delete-key-secret"]{
+            assert!(!dump.contains(value), "{} leaked", value);
+        }
+        assert!(dump.contains("AttestationKey(k1)"));
+        assert!(dump.contains("password: \"<redacted>\""));
+    }
+}
@@ -0,0 +1,136 @@
+
+
+
+
+use std::{collections::{HashMap, HashSet}, sync::{Arc, Mutex}};
+use futures::channel::mpsc;
+use serde::{Serialize, Deserialize};
+use crate::schemas::{commitment::RevealReport, conviction::ConvictionState, event::{Tally, Outcome, Extension}};
+
+
+
+
+
+
+
+// NOTE - the hub fans out proposal activities to every live connection, each subscriber has a bounded channel and a subscriber that can't keep up is dropped instead of slowing down the publisher
+// NOTE - we're using futures channels and a std mutex cause the hub is shared between the actix workers which are running on different threads
+// NOTE - activities are persisted with a sequence number before they're published (see utils::events::emit()) so sse clients can resume from the last one they saw
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Activity{
+    ProposalCreated{ proposal_id: String, title: String, creator_wallet_address: String, expire_at: Option<i64>, start_at: Option<i64> }, //-- start_at is None if voting is already open
+    VoteCast{ proposal_id: String, tally: Tally }, //-- a new ballot has been counted
+    VoteChanged{ proposal_id: String, tally: Tally }, //-- a voter switched side or their score changed
+    VoteRetracted{ proposal_id: String, tally: Tally },
+    QuorumReached{ proposal_id: String, tally: Tally }, //-- right after the vote that made the proposal reach its quorum
+    ProposalClosed{ proposal_id: String }, //-- voting is over
+    Outcome{ proposal_id: String, tally: Tally, outcome: Outcome, merkle_root: Option<String>, reveal_report: Option<RevealReport>, conviction: Option<ConvictionState> }, //-- the final result, always right after the close
+    ProposalEdited{ proposal_id: String, title: String, revision: u32 }, //-- the creator edited it before voting started
+    CommentPosted{ proposal_id: String, comment_id: String, parent_id: Option<String>, author: String }, //-- a new comment or reply in the discussion of the proposal
+    ProposalDeleted{ proposal_id: String },
+    ConvictionUpdated{ proposal_id: String, conviction: ConvictionState }, //-- a stake on a conviction proposal changed
+    DeadlineExtended{ proposal_id: String, extension: Extension }, //-- a late ballot flipped the lead and the anti-sniping rule pushed expire_at back
+}
+
+impl Activity{
+
+    pub const NAMES: &'static [&'static str] = &["proposal_created", "vote_cast", "vote_changed", "vote_retracted", "quorum_reached", "proposal_closed", "outcome", "proposal_edited", "comment_posted", "proposal_deleted", "conviction_updated", "deadline_extended"];
+
+    pub fn proposal_id(&self) -> &str{
+        match self{
+            Activity::ProposalCreated{proposal_id, ..} | Activity::VoteCast{proposal_id, ..} | Activity::VoteChanged{proposal_id, ..} | Activity::VoteRetracted{proposal_id, ..} | Activity::QuorumReached{proposal_id, ..} | Activity::ProposalClosed{proposal_id}
+            | Activity::Outcome{proposal_id, ..} | Activity::ProposalEdited{proposal_id, ..} | Activity::CommentPosted{proposal_id, ..} | Activity::ProposalDeleted{proposal_id} | Activity::ConvictionUpdated{proposal_id, ..} | Activity::DeadlineExtended{proposal_id, ..} => proposal_id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str{ //-- same as the serde tag, used as the sse event name
+        match self{
+            Activity::ProposalCreated{..} => "proposal_created",
+            Activity::VoteCast{..} => "vote_cast",
+            Activity::VoteChanged{..} => "vote_changed",
+            Activity::VoteRetracted{..} => "vote_retracted",
+            Activity::QuorumReached{..} => "quorum_reached",
+            Activity::ProposalClosed{..} => "proposal_closed",
+            Activity::Outcome{..} => "outcome",
+            Activity::ProposalEdited{..} => "proposal_edited",
+            Activity::CommentPosted{..} => "comment_posted",
+            Activity::ProposalDeleted{..} => "proposal_deleted",
+            Activity::ConvictionUpdated{..} => "conviction_updated",
+            Activity::DeadlineExtended{..} => "deadline_extended",
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Envelope{
+    pub seq: Option<i64>, //-- None if the activity couldn't be persisted, it's still delivered live but can't be resumed from
+    pub at: i64,
+    #[serde(flatten)]
+    pub activity: Activity,
+}
+
+
+
+struct Subscriber{
+    proposals: Option<HashSet<String>>, //-- None means every proposal
+    sender: mpsc::Sender<Envelope>,
+}
+
+
+
+#[derive(Clone, Default)]
+pub struct Hub{
+    inner: Arc<Mutex<HubInner>>,
+}
+
+#[derive(Default)]
+struct HubInner{
+    next_id: u64,
+    subscribers: HashMap<u64, Subscriber>,
+}
+
+impl Hub{
+
+    pub fn subscribe(&self, capacity: usize, proposals: Option<HashSet<String>>) -> (u64, mpsc::Receiver<Envelope>){ //-- the receiver ends once the subscriber is dropped for lagging behind
+        let (sender, receiver) = mpsc::channel(capacity);
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.subscribers.insert(id, Subscriber{ proposals, sender });
+        (id, receiver)
+    }
+
+    pub fn watch(&self, id: u64, proposals: impl IntoIterator<Item = String>){
+        if let Some(subscriber) = self.inner.lock().unwrap().subscribers.get_mut(&id){
+            subscriber.proposals.get_or_insert_with(HashSet::new).extend(proposals);
+        }
+    }
+
+    pub fn unwatch(&self, id: u64, proposals: impl IntoIterator<Item = String>){
+        if let Some(watched) = self.inner.lock().unwrap().subscribers.get_mut(&id).and_then(|s| s.proposals.as_mut()){
+            for proposal in proposals{
+                watched.remove(&proposal);
+            }
+        }
+    }
+
+    pub fn unsubscribe(&self, id: u64){
+        self.inner.lock().unwrap().subscribers.remove(&id);
+    }
+
+    pub fn publish(&self, envelope: Envelope){
+        let mut inner = self.inner.lock().unwrap();
+        inner.subscribers.retain(|_, subscriber| {
+            if !subscriber.proposals.as_ref().map(|p| p.contains(envelope.activity.proposal_id())).unwrap_or(true){
+                return true;
+            }
+            subscriber.sender.try_send(envelope.clone()).is_ok() //-- full or closed, either way this subscriber is gone
+        });
+    }
+}
@@ -35,15 +35,15 @@ mod constants;
 mod contexts;
 mod schemas;
 mod apis;
+mod migrations;
 
 
 
 
 
 
-use std::net::SocketAddr;
-use dotenv::dotenv;
 use std::env;
+use log::{info, error};
 use actix_web::{web, App, HttpServer, middleware::Logger};
 
 
@@ -74,21 +74,34 @@ async fn main() -> std::io::Result<()>{
     // -------------------------------- environment variables setup
     //
     // ---------------------------------------------------------------------
-    env::set_var("RUST_LOG", "actix_web=debug,actix_server=info");
+    env::set_var("RUST_LOG", "biter=info,actix_web=debug,actix_server=info");
     pretty_env_logger::init();
-    dotenv().expect("⚠️ .env file not found");
-    let io_buffer_size = env::var("IO_BUFFER_SIZE").expect("⚠️ no io buffer size variable set").parse::<u32>().unwrap() as usize; //-- usize is the minimum size in os which is 32 bits
-    let environment = env::var("ENVIRONMENT").expect("⚠️ no environment variable set");
-    let host = env::var("HOST").expect("⚠️ no host variable set");
-    let port = env::var("BITRADER_AUTH_PORT").expect("⚠️ no port variable set for bitrader service");
-    let server_addr = format!("{}:{}", host, port).as_str().parse::<SocketAddr>().unwrap(); //-- converting the host and port String into the as_str() then parse it based on SocketAddr generic type
+    let config = match contexts::config::Config::load(){ //-- loading defaults, the optional toml file, .env, env vars and cli flags all at once
+        Ok(config) => config,
+        Err(e) => {
+            error!("⚠️ {}", e);
+            std::process::exit(1);
+        }
+    };
+    let server_addr = config.server_addr();
+    info!("{} biter is running on {} in {:?} mode", config.company_name, server_addr, config.environment);
+    let storage = utils::db::connection(&config).await; //-- one shared db client for all workers
+    let hub = contexts::hub::Hub::default(); //-- one hub shared by all workers so a vote on one worker reaches the sockets of the others
+    if let Some(client) = storage.as_ref().and_then(|s| s.db.as_ref()).and_then(|db| db.instance.as_ref()){
+        match migrations::run(&client.database(&config.db.name)){ //-- indexes and data migrations must be in place before we accept any request
+            Ok(applied) => info!("applied {} migrations {:?} - {}", applied.len(), applied, chrono::Local::now().naive_local()),
+            Err(e) => {
+                error!("⚠️ migration failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        actix_web::rt::spawn(utils::events::sweep(client.database(&config.db.name), hub.clone(), config.clone()));
+        actix_web::rt::spawn(utils::conviction::sweep(client.database(&config.db.name), hub.clone(), config.clone()));
+        actix_web::rt::spawn(utils::webhooks::deliver(client.database(&config.db.name), config.clone()));
+    }
 
 
 
-    
-    
-
-    
 
 
 
@@ -99,9 +112,25 @@ async fn main() -> std::io::Result<()>{
     // ---------------------------------------------------------------------
     HttpServer::new(move || {
         App::new()
+            .data(config.clone()) //-- handlers extract this as web::Data<Config>
+            .data(storage.clone()) //-- handlers extract this as web::Data<Option<Arc<Storage>>>
+            .data(hub.clone()) //-- handlers extract this as web::Data<Hub>
             .service(
                 web::scope("/auth")
                             .configure(apis::event::register)
+                            .configure(apis::wallet::register)
+                            .configure(apis::admin::register)
+                            .configure(apis::attestation::register)
+                            .configure(apis::encryption::register)
+                            .configure(apis::delegation::register)
+                            .configure(apis::conviction::register)
+                            .configure(apis::revision::register)
+                            .configure(apis::auth::register)
+                            .configure(apis::comment::register)
+                            .configure(apis::taxonomy::register)
+                            .configure(apis::ws::register)
+                            .configure(apis::sse::register)
+                            .configure(apis::webhook::register)
             )
             .wrap(Logger::default())
     })
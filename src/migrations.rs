@@ -0,0 +1,552 @@
+
+
+
+
+use std::collections::HashSet;
+use log::{info, warn};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use mongodb::{IndexModel, options::IndexOptions, sync::Database, bson::{doc, Bson, Document}};
+use crate::schemas;
+
+
+
+
+
+
+
+// NOTE - migrations run once at startup in version order and each applied version is recorded inside the migrations collection, so they must never be edited or reordered once shipped, only appended
+// NOTE - data migrations bring documents written by older schemas up to the current schema_version of their collection
+
+
+
+pub const MIGRATIONS_COLLECTION: &str = "migrations";
+
+
+
+pub struct Migration{
+    pub version: u32,
+    pub name: &'static str,
+    pub up: fn(&Database) -> mongodb::error::Result<()>,
+}
+
+
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration{ version: 1, name: "events_v1_normalized_title", up: events_v1_normalized_title },
+    Migration{ version: 2, name: "events_indexes", up: events_indexes },
+    Migration{ version: 3, name: "events_list_indexes", up: events_list_indexes },
+    Migration{ version: 4, name: "ballots_indexes", up: ballots_indexes },
+    Migration{ version: 5, name: "events_v2_split_voters", up: events_v2_split_voters },
+    Migration{ version: 6, name: "ballots_wallet_index", up: ballots_wallet_index },
+    Migration{ version: 7, name: "activities_indexes", up: activities_indexes },
+    Migration{ version: 8, name: "webhook_deliveries_indexes", up: webhook_deliveries_indexes },
+    Migration{ version: 9, name: "vote_log_indexes", up: vote_log_indexes },
+    Migration{ version: 10, name: "vote_log_v1_backfill", up: vote_log_v1_backfill },
+    Migration{ version: 11, name: "vote_log_time_index", up: vote_log_time_index },
+    Migration{ version: 12, name: "events_merkle_roots", up: events_merkle_roots },
+    Migration{ version: 13, name: "commitments_indexes", up: commitments_indexes },
+    Migration{ version: 14, name: "encrypted_ballots_indexes", up: encrypted_ballots_indexes },
+    Migration{ version: 15, name: "delegations_indexes", up: delegations_indexes },
+    Migration{ version: 16, name: "stakes_indexes", up: stakes_indexes },
+    Migration{ version: 17, name: "revisions_indexes", up: revisions_indexes },
+    Migration{ version: 18, name: "comments_indexes", up: comments_indexes },
+    Migration{ version: 19, name: "rationales_index", up: rationales_index },
+    Migration{ version: 20, name: "taxonomy_and_search", up: taxonomy_and_search },
+    Migration{ version: 21, name: "challenges_indexes", up: challenges_indexes },
+];
+
+
+
+pub fn run(db: &Database) -> mongodb::error::Result<Vec<u32>>{ //-- returns the versions applied in this run
+    let records = db.collection::<Document>(MIGRATIONS_COLLECTION);
+    let mut applied = HashSet::new();
+    for record in records.find(None, None)?{
+        if let Ok(version) = record?.get_i64("_id"){
+            applied.insert(version as u32);
+        }
+    }
+    let mut ran = vec![];
+    for migration in MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)){
+        info!("applying migration {} {} - {}", migration.version, migration.name, chrono::Local::now().naive_local());
+        (migration.up)(db)?;
+        records.insert_one(doc!{"_id": migration.version as i64, "name": migration.name, "applied_at": Utc::now().timestamp()}, None)?;
+        ran.push(migration.version);
+    }
+    Ok(ran)
+}
+
+
+
+fn events_v1_normalized_title(db: &Database) -> mongodb::error::Result<()>{ //-- backfills title_normalized on events created before it existed, old duplicate titles get the event id appended so the unique index can still be built
+    let events = db.collection::<Document>("events");
+    let mut seen = HashSet::new();
+    for event in events.find(doc!{"title_normalized": {"$type": "string"}}, None)?{
+        if let Ok(title) = event?.get_str("title_normalized"){
+            seen.insert(title.to_string());
+        }
+    }
+    for event in events.find(doc!{"schema_version": {"$exists": false}}, None)?{
+        let event = event?;
+        let id = event.get_object_id("_id").unwrap();
+        let mut normalized = schemas::event::normalize_title(event.get_str("title").unwrap_or_default());
+        if !seen.insert(normalized.clone()){
+            warn!("event {} has a duplicate title `{}`, keeping it under a suffixed normalized title", id, normalized);
+            normalized = format!("{}#{}", normalized, id.to_hex());
+            seen.insert(normalized.clone());
+        }
+        events.update_one(doc!{"_id": id}, doc!{"$set": {"title_normalized": normalized, "schema_version": 1}}, None)?;
+    }
+    Ok(())
+}
+
+
+
+fn events_indexes(db: &Database) -> mongodb::error::Result<()>{
+    let events = db.collection::<Document>("events");
+    let indexes = vec![
+        IndexModel::builder()
+            .keys(doc!{"title_normalized": 1})
+            .options(IndexOptions::builder().name("title_normalized_unique".to_string()).unique(true).partial_filter_expression(doc!{"title_normalized": {"$type": "string"}}).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"is_expired": 1, "expire_at": 1})
+            .options(IndexOptions::builder().name("status_expire_at".to_string()).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"creator_wallet_address": 1})
+            .options(IndexOptions::builder().name("creator".to_string()).build())
+            .build(),
+    ];
+    events.create_indexes(indexes, None)?;
+    Ok(())
+}
+
+
+
+fn events_list_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- backs the sort orders and the tag filter of the list endpoint
+    let events = db.collection::<Document>("events");
+    let indexes = vec![
+        IndexModel::builder()
+            .keys(doc!{"is_expired": 1, "created_at": -1, "_id": -1})
+            .options(IndexOptions::builder().name("status_newest".to_string()).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"tags": 1})
+            .options(IndexOptions::builder().name("tags".to_string()).build())
+            .build(),
+    ];
+    events.create_indexes(indexes, None)?;
+    Ok(())
+}
+
+
+
+fn ballots_indexes(db: &Database) -> mongodb::error::Result<()>{
+    let ballots = db.collection::<Document>("ballots");
+    let indexes = vec![
+        IndexModel::builder()
+            .keys(doc!{"event_id": 1, "wallet_address": 1})
+            .options(IndexOptions::builder().name("event_wallet_unique".to_string()).unique(true).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"event_id": 1, "score": -1, "_id": 1})
+            .options(IndexOptions::builder().name("event_weight".to_string()).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"event_id": 1, "voted_at": 1, "_id": 1})
+            .options(IndexOptions::builder().name("event_time".to_string()).build())
+            .build(),
+    ];
+    ballots.create_indexes(indexes, None)?;
+    Ok(())
+}
+
+
+
+fn events_v2_split_voters(db: &Database) -> mongodb::error::Result<()>{ //-- moves the embedded voters array of v1 events into the ballots collection and rebuilds the counters from it, with the v1 ballot written out here instead of going through schemas::ballot which keeps growing
+    let events = db.collection::<Document>("events");
+    let ballots = db.collection::<Document>("ballots");
+    for event in events.find(doc!{"schema_version": {"$lt": 2}}, None)?{
+        let event = event?;
+        let id = event.get_object_id("_id").unwrap();
+        let voted_at = event.get_i64("created_at").unwrap_or_default(); //-- v1 voters have no vote time so the best we know is the event creation time
+        let voters = event.get_array("voters").map(|voters| voters.iter().filter_map(Bson::as_document).collect::<Vec<&Document>>()).unwrap_or_default();
+        for voter in voters{
+            let wallet_address = match voter.get_str("event_owner_wallet_address"){
+                Ok(wallet_address) => wallet_address,
+                Err(_) => continue, //-- not a voter the v1 api could have written
+            };
+            let ballot = doc!{
+                "event_id": id,
+                "wallet_address": wallet_address,
+                "is_upvote": voter.get_bool("is_upvote").unwrap_or(false),
+                "score": int(voter.get("score")),
+                "voted_at": voted_at,
+            };
+            match ballots.insert_one(ballot, None){
+                Ok(_) => {},
+                Err(e) if crate::utils::db::is_duplicate_key(&e) => {}, //-- a rerun after a crash or a duplicated voter, either way it's already inside the ballots
+                Err(e) => return Err(e),
+            }
+        }
+        let mut counters = doc!{"upvotes": 0, "downvotes": 0, "upvotes_weight": 0_i64, "downvotes_weight": 0_i64};
+        let pipeline = vec![
+            doc!{"$match": {"event_id": id}},
+            doc!{"$group": {"_id": "$is_upvote", "headcount": {"$sum": 1}, "weight": {"$sum": {"$toLong": "$score"}}}},
+        ];
+        for group in ballots.aggregate(pipeline, None)?{ //-- counting from the ballots collection so a rerun never counts a voter twice
+            let group = group?;
+            let choice = if group.get_bool("_id").unwrap_or(false) { "upvotes" } else { "downvotes" };
+            counters.insert(choice, group.get_i32("headcount").unwrap_or(0));
+            counters.insert(format!("{}_weight", choice), group.get_i64("weight").unwrap_or(0));
+        }
+        counters.insert("schema_version", 2);
+        events.update_one(doc!{"_id": id}, doc!{"$set": counters, "$unset": {"voters": ""}}, None)?;
+    }
+    Ok(())
+}
+
+
+
+fn ballots_wallet_index(db: &Database) -> mongodb::error::Result<()>{ //-- backs the voting history of a wallet
+    let ballots = db.collection::<Document>("ballots");
+    let index = IndexModel::builder()
+        .keys(doc!{"wallet_address": 1, "voted_at": -1, "_id": -1})
+        .options(IndexOptions::builder().name("wallet_time".to_string()).build())
+        .build();
+    ballots.create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn activities_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- backs the Last-Event-ID replay of the sse feed
+    let activities = db.collection::<Document>("activities");
+    let indexes = vec![
+        IndexModel::builder()
+            .keys(doc!{"seq": 1})
+            .options(IndexOptions::builder().name("seq_unique".to_string()).unique(true).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"proposal_id": 1, "seq": 1})
+            .options(IndexOptions::builder().name("proposal_seq".to_string()).build())
+            .build(),
+    ];
+    activities.create_indexes(indexes, None)?;
+    Ok(())
+}
+
+
+
+fn webhook_deliveries_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- backs the retry queue scan and the delivery logs
+    let deliveries = db.collection::<Document>("webhook_deliveries");
+    let indexes = vec![
+        IndexModel::builder()
+            .keys(doc!{"status": 1, "next_attempt_at": 1})
+            .options(IndexOptions::builder().name("status_due".to_string()).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"webhook_id": 1, "created_at": -1, "_id": -1})
+            .options(IndexOptions::builder().name("webhook_newest".to_string()).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"status": 1, "created_at": -1, "_id": -1})
+            .options(IndexOptions::builder().name("status_newest".to_string()).build())
+            .build(),
+    ];
+    deliveries.create_indexes(indexes, None)?;
+    Ok(())
+}
+
+
+
+fn vote_log_indexes(db: &Database) -> mongodb::error::Result<()>{
+    let vote_log = db.collection::<Document>("vote_log");
+    let indexes = vec![
+        IndexModel::builder()
+            .keys(doc!{"seq": 1})
+            .options(IndexOptions::builder().name("seq_unique".to_string()).unique(true).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc!{"event_id": 1, "seq": 1})
+            .options(IndexOptions::builder().name("event_seq".to_string()).build())
+            .build(),
+    ];
+    vote_log.create_indexes(indexes, None)?;
+    Ok(())
+}
+
+
+
+fn vote_log_v1_backfill(db: &Database) -> mongodb::error::Result<()>{ //-- ballots cast before the vote log existed get a cast record each, oldest first, so the projection of every event starts from them
+    let vote_log = db.collection::<Document>("vote_log");
+    let mut logged = HashSet::new();
+    for record in vote_log.find(None, None)?{ //-- a rerun after a crash skips what's already there
+        let record = record?;
+        if let (Ok(event_id), Ok(wallet_address)) = (record.get_object_id("event_id"), record.get_str("wallet_address")){
+            logged.insert((event_id, wallet_address.to_string()));
+        }
+    }
+    let counters = db.collection::<Document>("counters");
+    let seq_options = mongodb::options::FindOneAndUpdateOptions::builder().upsert(true).return_document(mongodb::options::ReturnDocument::After).build();
+    let options = mongodb::options::FindOptions::builder().sort(doc!{"voted_at": 1, "_id": 1}).build();
+    for ballot in db.collection::<Document>("ballots").find(None, options)?{ //-- the records are written as the v1 log had them, not through utils::votes which keeps moving
+        let ballot = ballot?;
+        let (event_id, wallet_address) = match (ballot.get_object_id("event_id"), ballot.get_str("wallet_address")){
+            (Ok(event_id), Ok(wallet_address)) => (event_id, wallet_address.to_string()),
+            _ => continue,
+        };
+        if logged.contains(&(event_id, wallet_address.clone())){
+            continue;
+        }
+        let seq = counters.find_one_and_update(doc!{"_id": "vote_log"}, doc!{"$inc": {"seq": 1_i64}}, seq_options.clone())?
+                            .and_then(|c| c.get_i64("seq").ok())
+                            .unwrap_or(1);
+        vote_log.insert_one(doc!{
+            "seq": seq,
+            "event_id": event_id,
+            "wallet_address": wallet_address,
+            "action": "cast",
+            "choice": {"is_upvote": ballot.get_bool("is_upvote").unwrap_or(false), "score": ballot.get("score").cloned().unwrap_or(Bson::Int64(0))},
+            "previous": null,
+            "at": ballot.get("voted_at").cloned().unwrap_or(Bson::Int64(0)),
+        }, None)?;
+    }
+    Ok(())
+}
+
+
+
+fn vote_log_time_index(db: &Database) -> mongodb::error::Result<()>{ //-- backs the point in time tallies and the tally series
+    let vote_log = db.collection::<Document>("vote_log");
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "at": 1, "seq": 1})
+        .options(IndexOptions::builder().name("event_time".to_string()).build())
+        .build();
+    vote_log.create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn events_merkle_roots(db: &Database) -> mongodb::error::Result<()>{ //-- seals the ballots of the events that closed before the roots existed, with the v1 leaves and tree written out here instead of going through utils::audit which keeps moving
+    let events = db.collection::<Document>("events");
+    for event in events.find(doc!{"is_expired": true, "merkle_root": {"$exists": false}}, None)?{
+        let id = event?.get_object_id("_id").unwrap();
+        let mut ballots = db.collection::<Document>("ballots").find(doc!{"event_id": id}, None)?.collect::<mongodb::error::Result<Vec<Document>>>()?;
+        ballots.sort_by(|a, b| a.get_str("wallet_address").unwrap_or_default().as_bytes().cmp(b.get_str("wallet_address").unwrap_or_default().as_bytes()));
+        let mut level = ballots.iter().map(|ballot| {
+            let leaf = format!("{}\n{}\n{}\n{}\n{}", id.to_hex(), ballot.get_str("wallet_address").unwrap_or_default(), ballot.get_bool("is_upvote").unwrap_or(false) as u8, int(ballot.get("score")), int(ballot.get("voted_at")));
+            sha256(&[&[0x00], leaf.as_bytes()])
+        }).collect::<Vec<[u8; 32]>>();
+        let leaves = level.len() as i64;
+        while level.len() > 1{
+            level = level.chunks(2).map(|pair| match pair{
+                [left, right] => sha256(&[&[0x01], left, right]),
+                [single] => *single, //-- carried up as is
+                _ => unreachable!(),
+            }).collect();
+        }
+        let root = level.first().copied().unwrap_or_else(|| sha256(&[]));
+        events.update_one(doc!{"_id": id}, doc!{"$set": {"merkle_root": hex::encode(root), "merkle_leaves": leaves}}, None)?;
+    }
+    Ok(())
+}
+
+
+
+fn sha256(parts: &[&[u8]]) -> [u8; 32]{
+    let mut hasher = Sha256::new();
+    for part in parts{
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+
+
+fn int(value: Option<&Bson>) -> i64{ //-- numbers may be stored as int32 or int64
+    match value{
+        Some(Bson::Int64(v)) => *v,
+        Some(Bson::Int32(v)) => *v as i64,
+        _ => 0,
+    }
+}
+
+
+
+fn commitments_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- one commitment per wallet and event
+    let commitments = db.collection::<Document>("commitments");
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "wallet_address": 1})
+        .options(IndexOptions::builder().name("event_wallet_unique".to_string()).unique(true).build())
+        .build();
+    commitments.create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn encrypted_ballots_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- one encrypted ballot per wallet and one decryption share per trustee on each event
+    let encrypted_ballots = db.collection::<Document>("encrypted_ballots");
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "wallet_address": 1})
+        .options(IndexOptions::builder().name("event_wallet_unique".to_string()).unique(true).build())
+        .build();
+    encrypted_ballots.create_index(index, None)?;
+    let decryption_shares = db.collection::<Document>("decryption_shares");
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "trustee": 1})
+        .options(IndexOptions::builder().name("event_trustee_unique".to_string()).unique(true).build())
+        .build();
+    decryption_shares.create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn delegations_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- one active delegation per delegator and scope, the revoked ones are left out so the history can grow
+    let delegations = db.collection::<Document>("delegations");
+    let index = IndexModel::builder()
+        .keys(doc!{"delegator": 1, "tag": 1})
+        .options(IndexOptions::builder().name("active_delegator_tag_unique".to_string()).unique(true).partial_filter_expression(doc!{"active": true}).build())
+        .build();
+    delegations.create_index(index, None)?;
+    let index = IndexModel::builder()
+        .keys(doc!{"delegate": 1, "active": 1})
+        .options(IndexOptions::builder().name("delegate_active".to_string()).build())
+        .build();
+    delegations.create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn stakes_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- one stake per wallet on each proposal, the stakes of a wallet are summed on every stake and the log is folded per proposal
+    let stakes = db.collection::<Document>("stakes");
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "wallet_address": 1})
+        .options(IndexOptions::builder().name("event_wallet_unique".to_string()).unique(true).build())
+        .build();
+    stakes.create_index(index, None)?;
+    let index = IndexModel::builder()
+        .keys(doc!{"wallet_address": 1, "released": 1})
+        .options(IndexOptions::builder().name("wallet_released".to_string()).build())
+        .build();
+    stakes.create_index(index, None)?;
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "seq": 1})
+        .options(IndexOptions::builder().name("event_seq".to_string()).build())
+        .build();
+    db.collection::<Document>("stake_log").create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn revisions_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- one revision per number on each event, it's what settles two edits racing each other
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "number": 1})
+        .options(IndexOptions::builder().name("event_number_unique".to_string()).unique(true).build())
+        .build();
+    db.collection::<Document>("revisions").create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn comments_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- the threads are paged newest or top first per level, edits and reactions are unique so concurrent writes can't double count
+    let comments = db.collection::<Document>("comments");
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "parent_id": 1, "created_at": -1, "_id": -1})
+        .options(IndexOptions::builder().name("thread_newest".to_string()).build())
+        .build();
+    comments.create_index(index, None)?;
+    let index = IndexModel::builder()
+        .keys(doc!{"event_id": 1, "parent_id": 1, "score": -1, "_id": -1})
+        .options(IndexOptions::builder().name("thread_top".to_string()).build())
+        .build();
+    comments.create_index(index, None)?;
+    let index = IndexModel::builder()
+        .keys(doc!{"comment_id": 1, "number": 1})
+        .options(IndexOptions::builder().name("comment_number_unique".to_string()).unique(true).build())
+        .build();
+    db.collection::<Document>("comment_edits").create_index(index, None)?;
+    let index = IndexModel::builder()
+        .keys(doc!{"comment_id": 1, "wallet_address": 1, "reaction": 1})
+        .options(IndexOptions::builder().name("comment_wallet_reaction_unique".to_string()).unique(true).build())
+        .build();
+    db.collection::<Document>("comment_reactions").create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn rationales_index(db: &Database) -> mongodb::error::Result<()>{ //-- the rationale feed pages the explained ballots newest first, the partial filter keeps the silent ones out of it
+    let index = IndexModel::builder()
+        .keys(doc!{"voted_at": -1, "_id": -1})
+        .options(IndexOptions::builder().name("rationale_newest".to_string()).partial_filter_expression(doc!{"rationale": {"$type": "string"}}).build())
+        .build();
+    db.collection::<Document>("ballots").create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn taxonomy_and_search(db: &Database) -> mongodb::error::Result<()>{ //-- the slugs are unique per kind, the tags already on the proposals and delegations become terms so they stay valid, and the text index backs the search with the title weighing more than the content
+    let terms = db.collection::<Document>("taxonomy");
+    let index = IndexModel::builder()
+        .keys(doc!{"kind": 1, "slug": 1})
+        .options(IndexOptions::builder().name("kind_slug_unique".to_string()).unique(true).build())
+        .build();
+    terms.create_index(index, None)?;
+    let mut tags = HashSet::new();
+    for tag in db.collection::<Document>("events").distinct("tags", None, None)?.into_iter().chain(db.collection::<Document>("delegations").distinct("tag", None, None)?){
+        if let Some(tag) = tag.as_str().filter(|t| !t.is_empty()){
+            tags.insert(tag.to_string());
+        }
+    }
+    let now = Utc::now().timestamp();
+    for tag in tags{
+        terms.update_one(
+            doc!{"kind": "tag", "slug": &tag},
+            doc!{"$setOnInsert": {"name": &tag, "description": null, "created_at": now, "updated_at": null}},
+            mongodb::options::UpdateOptions::builder().upsert(true).build(),
+        )?;
+    }
+    let events = db.collection::<Document>("events");
+    let index = IndexModel::builder()
+        .keys(doc!{"title": "text", "content": "text"})
+        .options(IndexOptions::builder().name("title_content_text".to_string()).weights(doc!{"title": 10, "content": 1}).build())
+        .build();
+    events.create_index(index, None)?;
+    let index = IndexModel::builder()
+        .keys(doc!{"category": 1, "created_at": -1})
+        .options(IndexOptions::builder().name("category_created_at".to_string()).build())
+        .build();
+    events.create_index(index, None)?;
+    Ok(())
+}
+
+
+
+fn challenges_indexes(db: &Database) -> mongodb::error::Result<()>{ //-- the login looks a challenge up by wallet and nonce, and mongodb drops it once its expires date passed, the ones asked for before that date existed get it from their expires_at
+    let challenges = db.collection::<Document>("challenges");
+    let index = IndexModel::builder()
+        .keys(doc!{"wallet_address": 1, "nonce": 1})
+        .options(IndexOptions::builder().name("wallet_nonce".to_string()).build())
+        .build();
+    challenges.create_index(index, None)?; //-- a no-op where the comments migration already built it
+    challenges.update_many(
+        doc!{"expires": {"$exists": false}},
+        vec![doc!{"$set": {"expires": {"$toDate": {"$multiply": ["$expires_at", 1000]}}}}],
+        None,
+    )?;
+    let index = IndexModel::builder()
+        .keys(doc!{"expires": 1})
+        .options(IndexOptions::builder().name("expires_ttl".to_string()).expire_after(std::time::Duration::from_secs(0)).build())
+        .build();
+    challenges.create_index(index, None)?;
+    Ok(())
+}
@@ -6,4 +6,18 @@
 
 
 
-pub mod event;
\ No newline at end of file
+pub mod event;
+pub mod ballot;
+pub mod vote_log;
+pub mod commitment;
+pub mod encryption;
+pub mod delegation;
+pub mod conviction;
+pub mod revision;
+pub mod auth;
+pub mod comment;
+pub mod taxonomy;
+pub mod attestation;
+pub mod ws;
+pub mod sse;
+pub mod webhook;
\ No newline at end of file
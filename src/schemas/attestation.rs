@@ -0,0 +1,124 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+use super::event::{EventInfo, Outcome, Tally};
+
+
+// NOTE - the attestation of a closed proposal is its result document serialized once into compact json plus the ed25519 signature of those exact bytes, verifiers must check the signature over the document string as is and only parse it afterwards
+// NOTE - the field order of ResultDocument is the canonical order, never reorder or rename its fields, bump ATTESTATION_VERSION and add new ones instead
+
+
+
+pub const ATTESTATION_VERSION: u32 = 2; //-- 2 added the abstain choice
+pub const ALGORITHM: &str = "ed25519";
+pub const STRATEGY: &str = "nft_balance_at_vote"; //-- a ballot weighs the number of NFTs its wallet owned when it was cast
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChoiceResult{
+    pub choice: String,
+    pub headcount: u32,
+    pub weight: u64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot{
+    pub vote_log_seq: Option<i64>, //-- the last vote log record of the proposal, the result is the projection of the log up to it
+    pub closed_at: Option<i64>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResultDocument{
+    pub version: u32,
+    pub proposal_id: String,
+    pub title: String,
+    pub creator_wallet_address: String,
+    pub choices: Vec<ChoiceResult>,
+    pub total_weight: u64,
+    pub quorum: u64,
+    pub quorum_reached: bool,
+    pub outcome: Outcome,
+    pub strategy: String,
+    pub snapshot: Snapshot,
+    pub merkle_root: Option<String>,
+    pub merkle_leaves: Option<u64>,
+}
+
+impl ResultDocument{
+
+    pub fn new(event: &EventInfo, tally: &Tally, vote_log_seq: Option<i64>) -> ResultDocument{
+        ResultDocument{
+            version: ATTESTATION_VERSION,
+            proposal_id: event._id.map(|id| id.to_hex()).unwrap_or_default(),
+            title: event.title.clone(),
+            creator_wallet_address: event.creator_wallet_address.clone(),
+            choices: vec![
+                ChoiceResult{ choice: "upvote".to_string(), headcount: tally.upvotes.headcount, weight: tally.upvotes.weight },
+                ChoiceResult{ choice: "downvote".to_string(), headcount: tally.downvotes.headcount, weight: tally.downvotes.weight },
+                ChoiceResult{ choice: "abstain".to_string(), headcount: tally.abstains.headcount, weight: tally.abstains.weight },
+            ],
+            total_weight: tally.total_weight,
+            quorum: tally.quorum,
+            quorum_reached: tally.quorum_reached,
+            outcome: tally.outcome(true),
+            strategy: STRATEGY.to_string(),
+            snapshot: Snapshot{ vote_log_seq, closed_at: event.closed_at },
+            merkle_root: event.merkle_root.clone(),
+            merkle_leaves: event.merkle_leaves,
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attestation{
+    pub _id: ObjectId, //-- the id of the proposal, one attestation per proposal
+    pub key_id: String,
+    pub algorithm: String,
+    pub document: String, //-- the canonical json of the ResultDocument, these are the signed bytes
+    pub signature: String, //-- hex
+    pub signed_at: i64,
+}
+
+impl Attestation{
+
+    pub fn info(&self) -> AttestationInfo{
+        AttestationInfo{
+            proposal_id: self._id.to_hex(),
+            key_id: self.key_id.clone(),
+            algorithm: self.algorithm.clone(),
+            document: self.document.clone(),
+            signature: self.signature.clone(),
+            signed_at: self.signed_at,
+            result: serde_json::from_str(&self.document).ok(),
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AttestationInfo{
+    pub proposal_id: String,
+    pub key_id: String,
+    pub algorithm: String,
+    pub document: String,
+    pub signature: String,
+    pub signed_at: i64,
+    pub result: Option<ResultDocument>, //-- the document parsed for convenience, it's not what's signed
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublicKeyInfo{
+    pub key_id: String,
+    pub algorithm: String,
+    pub public_key: String, //-- hex of the 32 bytes ed25519 public key
+    pub active: bool, //-- true for the key new attestations are signed with
+}
@@ -0,0 +1,59 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+
+
+// NOTE - a wallet logs in by signing a one time challenge with its ed25519 key, the wallet address being the base58 public key, and gets back a session token
+// NOTE - the token is a hs256 jwt signed with AUTH_SECRET whose subject is the wallet address, it goes inside the Authorization header as `Bearer <token>`
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChallengeRequest{
+    pub wallet_address: String,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Challenge{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub wallet_address: String,
+    pub nonce: String,
+    pub message: String, //-- what the wallet signs, bytes as is
+    pub expires_at: i64, //-- stored along with an `expires` date copy that the ttl index of the challenges goes by
+}
+
+impl Challenge{
+
+    pub fn message(wallet_address: &str, nonce: &str, expires_at: i64) -> String{
+        format!("Sign in to biter\nwallet: {}\nnonce: {}\nexpires at: {}", wallet_address, nonce, expires_at)
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoginRequest{
+    pub wallet_address: String,
+    pub nonce: String,
+    pub signature: String, //-- hex encoded ed25519 signature of the challenge message
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Claims{
+    pub sub: String, //-- the wallet address
+    pub iat: i64,
+    pub exp: i64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session{
+    pub token: String,
+    pub wallet_address: String,
+    pub expires_at: i64,
+}
@@ -0,0 +1,243 @@
+
+
+
+
+use serde::{Serialize, Deserialize};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use super::event::{Voter, EventStatus, Outcome};
+use super::vote_log::Choice;
+use super::delegation::DelegatedPower;
+
+
+// NOTE - every ballot is its own document inside the ballots collection, the unique (event_id, wallet_address) index is what stops a wallet from voting twice
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Ballot{
+    #[serde(skip_serializing_if = "Option::is_none")] //-- a null _id would be stored as is and every other ballot would then clash with it
+    pub _id: Option<ObjectId>,
+    pub event_id: ObjectId,
+    pub wallet_address: String,
+    pub is_upvote: bool,
+    #[serde(default)] //-- ballots cast before abstaining existed took a side
+    pub abstain: bool,
+    pub score: u32, // NOTE - this is the number of NFTs that the voter owned when the ballot was cast
+    pub voted_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegated: Option<DelegatedPower>, //-- the power delegated to the voter, frozen when the event closes and resolved on the fly before that
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u32>, //-- the revision of the proposal the ballot was cast against, None on ballots cast before the edits existed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>, //-- markdown, replaced along with the choice when the vote changes
+}
+
+impl Ballot{
+
+    pub fn new(event_id: ObjectId, voter: &Voter, voted_at: i64) -> Ballot{
+        Ballot{
+            _id: None, //-- mongodb fills it on insert
+            event_id,
+            wallet_address: voter.event_owner_wallet_address.clone(),
+            is_upvote: voter.is_upvote && !voter.abstain,
+            abstain: voter.abstain,
+            score: voter.score,
+            voted_at,
+            delegated: None,
+            revision: None,
+            rationale: voter.rationale(),
+        }
+    }
+
+    pub fn choice(&self) -> Choice{
+        Choice{ is_upvote: self.is_upvote, abstain: self.abstain, score: self.score }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RetractVoteRequest{
+    pub _id: String, //-- this is the id of the event took from the mongodb
+    pub wallet_address: String,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VoterSort{
+    Weight,
+    Time,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListVotersQuery{
+    pub sort: Option<VoterSort>, //-- defaults to weight
+    pub offset: Option<u64>,
+    pub limit: Option<i64>,
+}
+
+impl ListVotersQuery{
+
+    pub const DEFAULT_LIMIT: i64 = 50;
+    pub const MAX_LIMIT: i64 = 200;
+
+    pub fn limit(&self) -> i64{
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u64{
+        self.offset.unwrap_or(0)
+    }
+
+    pub fn sort(&self) -> Document{ //-- the id breaks the ties so pages never overlap
+        match self.sort.unwrap_or(VoterSort::Weight){
+            VoterSort::Weight => doc!{"score": -1, "_id": 1},
+            VoterSort::Time => doc!{"voted_at": 1, "_id": 1},
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoterList{
+    pub voters: Vec<Ballot>,
+    pub total: u64,
+    pub next_offset: Option<u64>, //-- None on the last page
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletVotesQuery{
+    pub status: Option<EventStatus>, //-- defaults to all, closed is an alias of expired
+    pub offset: Option<u64>,
+    pub limit: Option<i64>,
+}
+
+impl WalletVotesQuery{
+
+    pub const DEFAULT_LIMIT: i64 = 20;
+    pub const MAX_LIMIT: i64 = 100;
+
+    pub fn limit(&self) -> i64{
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u64{
+        self.offset.unwrap_or(0)
+    }
+
+    pub fn pipeline(&self, wallet_address: &str) -> Vec<Document>{ //-- joins every ballot of the wallet with its event, newest ballot first
+        let mut pipeline = vec![
+            doc!{"$match": {"wallet_address": wallet_address}},
+            doc!{"$sort": {"voted_at": -1, "_id": -1}},
+            doc!{"$lookup": {"from": "events", "localField": "event_id", "foreignField": "_id", "as": "event"}},
+            doc!{"$unwind": "$event"}, //-- drops the ballots of deleted events
+        ];
+        match self.status.unwrap_or(EventStatus::All){
+            EventStatus::Open | EventStatus::Upcoming => pipeline.push(doc!{"$match": {"event.is_expired": false}}), //-- a wallet can't have voted on an upcoming one
+            EventStatus::Expired => pipeline.push(doc!{"$match": {"event.is_expired": true}}),
+            EventStatus::All => {},
+        }
+        pipeline.push(doc!{"$facet": {
+            "total": [{"$count": "count"}],
+            "page": [{"$skip": self.offset() as i64}, {"$limit": self.limit()}],
+        }});
+        pipeline
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletVote{
+    pub event_id: ObjectId,
+    pub title: String,
+    pub is_upvote: bool,
+    pub abstain: bool,
+    pub score: u32,
+    pub voted_at: i64,
+    pub is_expired: bool,
+    pub expire_at: Option<i64>,
+    pub outcome: Outcome,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletVotes{
+    pub votes: Vec<WalletVote>,
+    pub total: u64,
+    pub next_offset: Option<u64>, //-- None on the last page
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RationaleFeedQuery{
+    pub wallet: Option<String>, //-- only the rationales of this wallet
+    pub offset: Option<u64>,
+    pub limit: Option<i64>,
+}
+
+impl RationaleFeedQuery{
+
+    pub const DEFAULT_LIMIT: i64 = 20;
+    pub const MAX_LIMIT: i64 = 100;
+
+    pub fn limit(&self) -> i64{
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u64{
+        self.offset.unwrap_or(0)
+    }
+
+    pub fn pipeline(&self) -> Vec<Document>{ //-- the ballots that carry a rationale across every proposal joined with the title of their proposal, newest first
+        let mut filter = doc!{"rationale": {"$type": "string"}};
+        if let Some(wallet) = self.wallet.as_ref(){
+            filter.insert("wallet_address", wallet);
+        }
+        vec![
+            doc!{"$match": filter},
+            doc!{"$sort": {"voted_at": -1, "_id": -1}},
+            doc!{"$lookup": {"from": "events", "localField": "event_id", "foreignField": "_id", "as": "event"}},
+            doc!{"$unwind": "$event"}, //-- drops the ballots of deleted events
+            doc!{"$addFields": {"title": "$event.title"}},
+            doc!{"$project": {"event": 0}},
+            doc!{"$facet": {
+                "total": [{"$count": "count"}],
+                "page": [{"$skip": self.offset() as i64}, {"$limit": self.limit()}],
+            }},
+        ]
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RationaleEntry{
+    pub event_id: ObjectId,
+    pub title: String,
+    pub wallet_address: String,
+    pub is_upvote: bool,
+    pub abstain: bool,
+    pub score: u32,
+    pub rationale: String,
+    pub revision: Option<u32>,
+    pub voted_at: i64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RationaleFeed{
+    pub rationales: Vec<RationaleEntry>,
+    pub total: u64,
+    pub next_offset: Option<u64>, //-- None on the last page
+}
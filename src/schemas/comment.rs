@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use super::event::EventCursor;
+
+
+// NOTE - the comments of a proposal form threads, a reply points to its parent and sits one level deeper, every comment is written by a logged in wallet
+// NOTE - an edit keeps the previous body inside the comment_edits collection, a hidden comment stays in its thread so the replies keep their place but its body isn't shown
+// NOTE - the counters on the comment (reactions, score, replies) and on the event (comments) are caches moved with $inc next to the write they follow
+
+
+
+pub const REACTIONS: &[&str] = &["+1", "-1", "heart", "laugh", "eyes", "rocket"];
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Comment{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub event_id: ObjectId,
+    pub parent_id: Option<ObjectId>, //-- None for a top level comment
+    pub depth: u32,
+    pub author: String,
+    pub body: String, //-- markdown
+    pub created_at: i64,
+    pub edited_at: Option<i64>,
+    pub edits: u32, //-- number of earlier bodies inside comment_edits
+    pub reactions: BTreeMap<String, u32>,
+    pub score: i64, //-- +1 reactions minus -1 reactions, what top sorts on
+    pub replies: u32, //-- direct replies, hidden ones included
+    pub hidden: Option<Moderation>,
+}
+
+impl Comment{
+
+    pub fn redacted(self) -> Comment{ //-- what the threads show of a hidden comment
+        match self.hidden{
+            Some(_) => Comment{ body: String::new(), ..self },
+            None => self,
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Moderation{
+    pub by: String, //-- the moderator wallet or "admin"
+    pub reason: Option<String>,
+    pub at: i64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentEdit{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub comment_id: ObjectId,
+    pub number: u32, //-- 1 for the original body
+    pub body: String, //-- the body before the edit
+    pub written_at: i64, //-- when this body was posted or last edited
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentHistory{
+    pub comment_id: String,
+    pub edits: Vec<CommentEdit>, //-- the earlier bodies, oldest first, the current one is on the comment
+    pub current: String,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reaction{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub comment_id: ObjectId,
+    pub wallet_address: String,
+    pub reaction: String,
+    pub at: i64,
+}
+
+impl Reaction{
+
+    pub fn score_of(reaction: &str) -> i64{
+        match reaction{
+            "+1" => 1,
+            "-1" => -1,
+            _ => 0,
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PostCommentRequest{
+    pub body: String,
+    pub parent_id: Option<String>, //-- the comment this one replies to
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EditCommentRequest{
+    pub body: String,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReactRequest{
+    pub reaction: String, //-- one of REACTIONS
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HideCommentRequest{
+    pub reason: Option<String>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentSort{
+    Newest,
+    Top,
+}
+
+impl CommentSort{
+
+    pub fn field(&self) -> &'static str{
+        match self{
+            CommentSort::Newest => "created_at",
+            CommentSort::Top => "score",
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListCommentsQuery{
+    pub sort: Option<CommentSort>, //-- defaults to newest
+    pub parent: Option<String>, //-- the replies of this comment, the top level comments without it
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl ListCommentsQuery{
+
+    pub const DEFAULT_LIMIT: i64 = 20;
+    pub const MAX_LIMIT: i64 = 100;
+
+    pub fn sort(&self) -> CommentSort{
+        self.sort.unwrap_or(CommentSort::Newest)
+    }
+
+    pub fn limit(&self) -> i64{
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn filter(&self, event_id: ObjectId, parent_id: Option<ObjectId>, cursor: Option<&EventCursor>) -> Document{ //-- newest first or highest score first, the id breaks the ties
+        let mut filter = doc!{"event_id": event_id, "parent_id": parent_id};
+        if let Some(cursor) = cursor{
+            let field = self.sort().field();
+            filter.insert("$or", vec![
+                doc!{field: {"$lt": cursor.value}},
+                doc!{field: cursor.value, "_id": {"$lt": cursor.id}},
+            ]);
+        }
+        filter
+    }
+
+    pub fn cursor_of(&self, comment: &Comment) -> Option<EventCursor>{
+        let value = match self.sort(){
+            CommentSort::Newest => comment.created_at,
+            CommentSort::Top => comment.score,
+        };
+        Some(EventCursor{ value, id: comment._id? })
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentPage{
+    pub comments: Vec<Comment>,
+    pub total: u64, //-- comments at this level of the thread across all pages
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,178 @@
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use super::vote_log::Choice;
+
+
+// NOTE - on a commit-reveal event a wallet first sends the hash of its ballot and a secret salt, nothing about its choice is known until it reveals them once the commit phase is over
+// NOTE - the commitment is hex(sha256("<event id hex>\n<wallet address>\n<1 for up, 0 for down, 2 for abstain>\n<score>\n<salt>")), the event and the wallet are inside so a commitment can't be copied to another event or by another wallet
+// NOTE - only revealed ballots become real ballots and get counted, the commitments left unrevealed are reported when the event closes
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Commitment{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub event_id: ObjectId,
+    pub wallet_address: String,
+    pub commitment: String, //-- lowercase hex
+    pub committed_at: i64,
+    pub revealed_at: Option<i64>,
+}
+
+impl Commitment{
+
+    pub fn hash(event_id: &ObjectId, wallet_address: &str, choice: &Choice, salt: &str) -> String{
+        let preimage = format!("{}\n{}\n{}\n{}\n{}", event_id.to_hex(), wallet_address, choice.code(), choice.score, salt);
+        hex::encode(Sha256::digest(preimage.as_bytes()))
+    }
+
+    pub fn opens_with(&self, choice: &Choice, salt: &str) -> bool{
+        Commitment::hash(&self.event_id, &self.wallet_address, choice, salt) == self.commitment
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommitVoteRequest{
+    pub _id: String, //-- this is the id of the event took from the mongodb
+    pub wallet_address: String,
+    pub commitment: String,
+}
+
+impl CommitVoteRequest{
+
+    pub fn commitment(&self) -> Option<String>{ //-- None unless it's a sha256 in hex
+        let commitment = self.commitment.trim().to_lowercase();
+        if commitment.len() == 64 && commitment.chars().all(|c| c.is_ascii_hexdigit()){
+            Some(commitment)
+        } else{
+            None
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevealVoteRequest{
+    pub _id: String, //-- this is the id of the event took from the mongodb
+    pub voter: super::event::Voter,
+    pub salt: String,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RevealReport{
+    pub committed: u64,
+    pub revealed: u64,
+    pub unrevealed: u64, //-- commitments that were never opened, they're not counted, the wallets are listed by /proposal/{id}/commitments?revealed=false
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListCommitmentsQuery{
+    pub revealed: Option<bool>, //-- both by default
+    pub offset: Option<u64>,
+    pub limit: Option<i64>,
+}
+
+impl ListCommitmentsQuery{
+
+    pub const DEFAULT_LIMIT: i64 = 50;
+    pub const MAX_LIMIT: i64 = 200;
+
+    pub fn limit(&self) -> i64{
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u64{
+        self.offset.unwrap_or(0)
+    }
+
+    pub fn filter(&self, event_id: ObjectId) -> Document{
+        let mut filter = doc!{"event_id": event_id};
+        match self.revealed{
+            Some(true) => { filter.insert("revealed_at", doc!{"$ne": null}); },
+            Some(false) => { filter.insert("revealed_at", Bson::Null); },
+            None => {},
+        }
+        filter
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommitmentList{
+    pub commitments: Vec<Commitment>,
+    pub total: u64,
+    pub next_offset: Option<u64>, //-- None on the last page
+}
+
+
+
+#[cfg(test)]
+mod tests{
+
+    use super::*;
+
+    const SALT: &str = "7f3a9c";
+
+    fn up(score: u32) -> Choice{
+        Choice{ is_upvote: true, abstain: false, score }
+    }
+
+    fn commitment(event_id: ObjectId, choice: &Choice) -> Commitment{
+        Commitment{
+            _id: None,
+            event_id,
+            wallet_address: "wallet".to_string(),
+            commitment: Commitment::hash(&event_id, "wallet", choice, SALT),
+            committed_at: 0,
+            revealed_at: None,
+        }
+    }
+
+    #[test]
+    fn hash_follows_the_documented_preimage(){ //-- what the clients compute on their side
+        let event_id = ObjectId::parse_str("65a1b2c3d4e5f60718293a4b").unwrap();
+        let expected = hex::encode(Sha256::digest(b"65a1b2c3d4e5f60718293a4b\nwallet\n1\n3\n7f3a9c"));
+        assert_eq!(Commitment::hash(&event_id, "wallet", &up(3), SALT), expected);
+        let abstain = Choice{ is_upvote: false, abstain: true, score: 3 };
+        assert_eq!(Commitment::hash(&event_id, "wallet", &abstain, SALT), hex::encode(Sha256::digest(b"65a1b2c3d4e5f60718293a4b\nwallet\n2\n3\n7f3a9c")));
+    }
+
+    #[test]
+    fn opens_only_with_the_same_ballot_and_salt(){
+        let event_id = ObjectId::new();
+        let commitment = commitment(event_id, &up(3));
+        assert!(commitment.opens_with(&up(3), SALT));
+        assert!(!commitment.opens_with(&up(3), "another salt"));
+        assert!(!commitment.opens_with(&up(4), SALT));
+        assert!(!commitment.opens_with(&Choice{ is_upvote: false, ..up(3) }, SALT));
+        assert!(!commitment.opens_with(&Choice{ abstain: true, ..up(3) }, SALT));
+    }
+
+    #[test]
+    fn commitment_is_bound_to_its_event_and_wallet(){
+        let event_id = ObjectId::new();
+        let copied = Commitment{ event_id: ObjectId::new(), ..commitment(event_id, &up(3)) };
+        assert!(!copied.opens_with(&up(3), SALT));
+        let copied = Commitment{ wallet_address: "another wallet".to_string(), ..commitment(event_id, &up(3)) };
+        assert!(!copied.opens_with(&up(3), SALT));
+    }
+
+    #[test]
+    fn commitment_has_to_be_a_hex_sha256(){
+        let request = |commitment: &str| CommitVoteRequest{ _id: String::new(), wallet_address: "wallet".to_string(), commitment: commitment.to_string() };
+        assert_eq!(request(&format!(" {} ", "AB".repeat(32))).commitment(), Some("ab".repeat(32)));
+        assert_eq!(request(&"ab".repeat(31)).commitment(), None);
+        assert_eq!(request(&"zz".repeat(32)).commitment(), None);
+        assert_eq!(request("").commitment(), None);
+    }
+}
@@ -0,0 +1,223 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+use crate::contexts::config::ConvictionConfig;
+
+
+// NOTE - a conviction proposal asks the grants pool for an amount and has no deadline, wallets stake part of their score on it for as long as they like instead of casting a ballot
+// NOTE - its conviction moves toward the total staked on it, halfway every CONVICTION_HALF_LIFE, and it passes as soon as the conviction crosses a threshold that grows with the share of the pool requested
+// NOTE - every stake change is appended to the stake_log, the conviction at any time is the fold of the log (see Fold) and the state on the event is only a cache refreshed by utils::conviction
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Stake{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub event_id: ObjectId,
+    pub wallet_address: String,
+    pub amount: u64, //-- part of the wallet's score, the stakes of a wallet on the open proposals can't add up to more than its score
+    pub updated_at: i64,
+    #[serde(default)]
+    pub released: bool, //-- true once the proposal closed, the amount is free again
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StakeRequest{
+    pub _id: String, //-- this is the id of the event took from the mongodb
+    pub wallet_address: String,
+    pub amount: u64, //-- the new stake on this proposal, 0 withdraws it
+    pub score: u32, // NOTE - this is the number of NFTs that the wallet owns
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StakeRecord{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub seq: i64, //-- the order of the log, taken from the stake_log counter
+    pub event_id: ObjectId,
+    pub wallet_address: String,
+    pub amount: u64, //-- the stake after this record
+    pub previous: u64, //-- the stake before it
+    pub at: i64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ConvictionState{
+    pub staked: u64,
+    pub conviction: f64,
+    pub threshold: Option<f64>, //-- None if the requested amount is too big to ever pass with the current funds
+    pub progress: f64, //-- conviction over threshold
+    pub projected_pass_at: Option<i64>, //-- when it passes if the stakes don't change
+    pub passed_at: Option<i64>,
+    pub updated_at: i64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Fold{ //-- the conviction of a proposal while going through its stake log
+    pub at: i64,
+    pub staked: u64,
+    pub conviction: f64,
+    pub passed_at: Option<i64>, //-- the first time the conviction reached the threshold
+}
+
+impl Fold{
+
+    pub fn new(created_at: i64) -> Fold{
+        Fold{ at: created_at, staked: 0, conviction: 0.0, passed_at: None }
+    }
+
+    pub fn advance(&mut self, to: i64, curve: &ConvictionConfig, threshold: Option<f64>){ //-- lets the current stake work until `to`
+        if to <= self.at{
+            return;
+        }
+        if let (None, Some(threshold)) = (self.passed_at, threshold){
+            if let Some(after) = curve.time_to(self.conviction, self.staked, threshold).filter(|after| self.at + after <= to){
+                self.passed_at = Some(self.at + after);
+            }
+        }
+        self.conviction = curve.grow(self.conviction, self.staked, to - self.at);
+        self.at = to;
+    }
+
+    pub fn apply(&mut self, record: &StakeRecord, curve: &ConvictionConfig, threshold: Option<f64>){
+        self.advance(record.at, curve, threshold);
+        self.staked = (self.staked + record.amount).saturating_sub(record.previous);
+    }
+
+    pub fn state(&self, curve: &ConvictionConfig, threshold: Option<f64>) -> ConvictionState{
+        ConvictionState{
+            staked: self.staked,
+            conviction: self.conviction,
+            threshold,
+            progress: threshold.map(|t| self.conviction / t).unwrap_or(0.0),
+            projected_pass_at: match self.passed_at{
+                Some(passed_at) => Some(passed_at),
+                None => threshold.and_then(|t| curve.time_to(self.conviction, self.staked, t)).map(|after| self.at + after),
+            },
+            passed_at: self.passed_at,
+            updated_at: self.at,
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConvictionPoint{
+    pub at: i64, //-- end of the bucket
+    pub staked: u64,
+    pub conviction: f64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConvictionCurve{
+    pub proposal_id: String,
+    pub requested_amount: u64,
+    pub half_life: i64,
+    pub threshold: Option<f64>,
+    pub from: i64,
+    pub to: i64,
+    pub bucket: i64, //-- the bucket that was actually used
+    pub points: Vec<ConvictionPoint>,
+    pub state: ConvictionState, //-- as of `to`
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StakeList{
+    pub stakes: Vec<Stake>, //-- biggest first
+    pub staked: u64,
+}
+
+
+
+#[cfg(test)]
+mod tests{
+
+    use super::*;
+
+    const DAY: i64 = 86400;
+
+    fn curve() -> ConvictionConfig{ //-- the shipped defaults
+        ConvictionConfig{ half_life: 3 * DAY, funds: 100000, supply: 10000, max_ratio: 0.2, weight: 0.0025, interval: 300 }
+    }
+
+    fn stake(amount: u64, previous: u64, at: i64) -> StakeRecord{
+        StakeRecord{ _id: None, seq: 0, event_id: ObjectId::new(), wallet_address: "w".to_string(), amount, previous, at }
+    }
+
+    fn close(a: f64, b: f64) -> bool{
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn conviction_moves_halfway_every_half_life(){
+        let curve = curve();
+        assert!(close(curve.grow(0.0, 1000, 0), 0.0));
+        assert!(close(curve.grow(0.0, 1000, 3 * DAY), 500.0));
+        assert!(close(curve.grow(0.0, 1000, 6 * DAY), 750.0));
+        assert!(close(curve.grow(1000.0, 0, 3 * DAY), 500.0)); //-- it decays the same way once the stake is gone
+        assert!(close(curve.grow(400.0, 1000, -DAY), 400.0));
+    }
+
+    #[test]
+    fn threshold_grows_with_the_share_requested(){
+        let curve = curve();
+        assert!(close(curve.threshold(0).unwrap(), 0.0025 * 10000.0 / 0.04));
+        assert!(curve.threshold(10000).unwrap() > curve.threshold(5000).unwrap());
+        assert_eq!(curve.threshold(20000), None); //-- max_ratio of the funds
+        assert_eq!(curve.threshold(50000), None);
+    }
+
+    #[test]
+    fn time_to_matches_the_growth(){
+        let curve = curve();
+        let after = curve.time_to(0.0, 1000, 750.0).unwrap();
+        assert!((after - 6 * DAY).abs() <= 1);
+        assert!(curve.grow(0.0, 1000, after) >= 750.0 - 1e-6);
+        assert_eq!(curve.time_to(800.0, 1000, 750.0), Some(0));
+        assert_eq!(curve.time_to(0.0, 750, 750.0), None); //-- it only gets there asymptotically
+        assert_eq!(curve.time_to(0.0, 500, 750.0), None);
+    }
+
+    #[test]
+    fn fold_records_when_it_passed(){
+        let curve = curve();
+        let threshold = Some(750.0);
+        let mut fold = Fold::new(0);
+        fold.apply(&stake(1000, 0, DAY), &curve, threshold);
+        assert_eq!(fold.passed_at, None);
+        fold.advance(10 * DAY, &curve, threshold);
+        let passed_at = fold.passed_at.unwrap();
+        assert!((passed_at - 7 * DAY).abs() <= 1); //-- six days of a 1000 stake after it started
+        fold.apply(&stake(0, 1000, 11 * DAY), &curve, threshold);
+        fold.advance(40 * DAY, &curve, threshold);
+        assert_eq!(fold.passed_at, Some(passed_at)); //-- passing is for good
+        assert_eq!(fold.staked, 0);
+        assert!(fold.conviction < 750.0);
+        let state = fold.state(&curve, threshold);
+        assert_eq!((state.passed_at, state.projected_pass_at, state.updated_at), (Some(passed_at), Some(passed_at), 40 * DAY));
+    }
+
+    #[test]
+    fn projection_without_a_pass(){
+        let curve = curve();
+        let mut fold = Fold::new(0);
+        fold.apply(&stake(1000, 0, 0), &curve, Some(750.0));
+        let state = fold.state(&curve, Some(750.0));
+        assert!((state.projected_pass_at.unwrap() - 6 * DAY).abs() <= 1);
+        assert_eq!(fold.state(&curve, Some(2000.0)).projected_pass_at, None);
+        assert_eq!(fold.state(&curve, None).progress, 0.0);
+    }
+}
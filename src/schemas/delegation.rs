@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+use super::event::Counters;
+use super::vote_log::Choice;
+
+
+// NOTE - a wallet can hand its voting power to another wallet for every proposal or only for the proposals with a given tag, the tagged delegation wins over the global one
+// NOTE - the power follows the chain of delegates until it reaches a wallet that voted on the proposal and goes with its choice, abstaining included, it's lost if the chain ends without a ballot or loops back on itself
+// NOTE - a delegator that votes directly takes its power back for that proposal, the delegated weight is resolved on the fly while a proposal is open and frozen on it when it closes
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Delegation{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub delegator: String,
+    pub delegate: String,
+    pub tag: Option<String>, //-- None for a global delegation
+    pub score: u32, // NOTE - this is the number of NFTs the delegator owns, it's the power it hands over
+    pub active: bool, //-- false once revoked or replaced, kept for the history
+    pub created_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+
+
+pub fn normalize_tag(tag: Option<&String>) -> Option<String>{ //-- same rules as the tags of the events, an empty tag means global
+    tag.map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty())
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DelegateRequest{
+    pub delegator: String,
+    pub delegate: String,
+    pub tag: Option<String>,
+    pub score: u32,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevokeDelegationRequest{
+    pub delegator: String,
+    pub tag: Option<String>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletDelegations{
+    pub given: Vec<Delegation>, //-- the active delegations of the wallet, one per scope at most
+    pub received: Vec<Delegation>, //-- the active delegations made to it, not following the chains
+}
+
+
+
+pub type Edges = HashMap<String, (String, u32)>; //-- delegator to its delegate and power for a proposal
+
+
+
+pub fn edges(delegations: &[Delegation], tags: &[String]) -> Edges{ //-- the delegation of each delegator that applies to a proposal with these tags, the earliest matching tag of the proposal wins over global
+    let rank = |d: &Delegation| match d.tag.as_ref(){
+        Some(tag) => tags.iter().position(|t| t == tag),
+        None => Some(tags.len()),
+    };
+    let mut best: HashMap<&str, (usize, &Delegation)> = HashMap::new();
+    for delegation in delegations.iter().filter(|d| d.active){
+        if let Some(rank) = rank(delegation){
+            match best.get(delegation.delegator.as_str()){
+                Some((current, _)) if *current <= rank => {},
+                _ => { best.insert(&delegation.delegator, (rank, delegation)); },
+            }
+        }
+    }
+    best.into_iter().map(|(delegator, (_, d))| (delegator.to_string(), (d.delegate.clone(), d.score))).collect()
+}
+
+
+
+pub fn loops_back(edges: &Edges, from: &str) -> bool{ //-- true if following the delegates from this wallet comes back to it
+    let mut seen = HashSet::new();
+    let mut current = from;
+    while let Some((delegate, _)) = edges.get(current){
+        if delegate == from{
+            return true;
+        }
+        if !seen.insert(delegate.as_str()){ //-- a loop that doesn't go through `from`
+            return false;
+        }
+        current = delegate;
+    }
+    false
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DelegatedPower{
+    pub weight: u64,
+    pub delegators: u32,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Resolution{
+    pub counters: Counters, //-- what the delegations add to the tally, the headcounts are the delegators
+    pub voters: HashMap<String, DelegatedPower>, //-- the power each voter received
+    pub unused_weight: u64, //-- power whose chain ended without a ballot
+    pub cycles: u32, //-- delegators stuck in a loop of delegates
+}
+
+impl Resolution{
+
+    pub fn resolve(edges: &Edges, ballots: &HashMap<String, Choice>) -> Resolution{
+        let mut resolution = Resolution::default();
+        for (delegator, (delegate, score)) in edges{
+            if ballots.contains_key(delegator){ //-- the direct vote overrides the delegation
+                continue;
+            }
+            let mut seen = HashSet::from([delegator.as_str()]);
+            let mut current = delegate.as_str();
+            loop{
+                if let Some(choice) = ballots.get(current){
+                    resolution.counters = resolution.counters.add(&Counters::of_choice(&Choice{ score: *score, ..*choice }));
+                    let power = resolution.voters.entry(current.to_string()).or_default();
+                    power.weight += *score as u64;
+                    power.delegators += 1;
+                    break;
+                }
+                if !seen.insert(current){
+                    resolution.cycles += 1;
+                    resolution.unused_weight += *score as u64;
+                    break;
+                }
+                match edges.get(current){
+                    Some((next, _)) => current = next,
+                    None => {
+                        resolution.unused_weight += *score as u64;
+                        break;
+                    },
+                }
+            }
+        }
+        resolution
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests{
+
+    use super::*;
+
+    fn delegation(delegator: &str, delegate: &str, tag: Option<&str>, score: u32) -> Delegation{
+        Delegation{
+            _id: None,
+            delegator: delegator.to_string(),
+            delegate: delegate.to_string(),
+            tag: tag.map(str::to_string),
+            score,
+            active: true,
+            created_at: 0,
+            revoked_at: None,
+        }
+    }
+
+    fn chain(links: &[(&str, &str, u32)]) -> Edges{ //-- global delegations only
+        let delegations = links.iter().map(|(from, to, score)| delegation(from, to, None, *score)).collect::<Vec<_>>();
+        edges(&delegations, &[])
+    }
+
+    fn ballots(votes: &[(&str, bool, bool)]) -> HashMap<String, Choice>{ //-- (wallet, is_upvote, abstain) with a score of 1
+        votes.iter().map(|(wallet, is_upvote, abstain)| (wallet.to_string(), Choice{ is_upvote: *is_upvote, abstain: *abstain, score: 1 })).collect()
+    }
+
+    #[test]
+    fn tagged_delegation_wins_over_the_global_one(){
+        let delegations = vec![
+            delegation("a", "global", None, 5),
+            delegation("a", "treasury", Some("treasury"), 5),
+            delegation("a", "grants", Some("grants"), 5),
+            delegation("b", "global", None, 3),
+            delegation("c", "other", Some("other"), 2), //-- not a tag of the proposal
+            Delegation{ active: false, ..delegation("d", "revoked", None, 1) },
+        ];
+        let edges = edges(&delegations, &["grants".to_string(), "treasury".to_string()]);
+        assert_eq!(edges.get("a"), Some(&("grants".to_string(), 5))); //-- the earliest tag of the proposal
+        assert_eq!(edges.get("b"), Some(&("global".to_string(), 3)));
+        assert_eq!(edges.get("c"), None);
+        assert_eq!(edges.get("d"), None);
+        assert_eq!(self::edges(&delegations, &[]).get("a"), Some(&("global".to_string(), 5)));
+    }
+
+    #[test]
+    fn power_follows_the_chain_to_the_first_ballot(){
+        let edges = chain(&[("a", "b", 5), ("b", "c", 3), ("c", "d", 2)]);
+        let resolution = Resolution::resolve(&edges, &ballots(&[("c", true, false), ("d", false, false)]));
+        assert_eq!(resolution.voters.get("c"), Some(&DelegatedPower{ weight: 8, delegators: 2 })); //-- a through b, and b
+        assert_eq!(resolution.voters.get("d"), None); //-- c voted so its own delegation doesn't apply
+        assert_eq!((resolution.counters.upvotes, resolution.counters.upvotes_weight), (2, 8));
+        assert_eq!(resolution.counters.downvotes_weight, 0);
+        assert_eq!((resolution.unused_weight, resolution.cycles), (0, 0));
+    }
+
+    #[test]
+    fn direct_vote_overrides_the_delegation(){
+        let edges = chain(&[("a", "b", 5)]);
+        let resolution = Resolution::resolve(&edges, &ballots(&[("a", false, false), ("b", true, false)]));
+        assert!(resolution.voters.is_empty());
+        assert_eq!(resolution.counters, Counters::default());
+    }
+
+    #[test]
+    fn abstaining_delegate_takes_the_power_along(){
+        let resolution = Resolution::resolve(&chain(&[("a", "b", 5)]), &ballots(&[("b", true, true)]));
+        assert_eq!((resolution.counters.abstains, resolution.counters.abstains_weight), (1, 5));
+        assert_eq!(resolution.counters.upvotes_weight, 0);
+    }
+
+    #[test]
+    fn chain_without_a_ballot_or_looping_counts_for_nothing(){
+        let resolution = Resolution::resolve(&chain(&[("a", "b", 5), ("b", "c", 3)]), &HashMap::new());
+        assert_eq!((resolution.unused_weight, resolution.cycles), (8, 0));
+        let resolution = Resolution::resolve(&chain(&[("a", "b", 5), ("b", "a", 3), ("x", "a", 1)]), &HashMap::new());
+        assert_eq!((resolution.unused_weight, resolution.cycles), (9, 3));
+        assert_eq!(resolution.counters, Counters::default());
+    }
+
+    #[test]
+    fn loops_back_only_through_the_start(){
+        let edges = chain(&[("a", "b", 1), ("b", "c", 1), ("c", "a", 1), ("x", "b", 1)]);
+        assert!(loops_back(&edges, "a"));
+        assert!(loops_back(&edges, "c"));
+        assert!(!loops_back(&edges, "x")); //-- reaches a loop that doesn't come back to x
+        assert!(!loops_back(&chain(&[("a", "b", 1)]), "a"));
+    }
+}
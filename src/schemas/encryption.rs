@@ -0,0 +1,162 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+use crate::utils::elgamal::{self, BallotProof, Ciphertext, DecryptionShare, Point};
+
+
+// NOTE - on an encrypted event every ballot is a vector of exponential elgamal ciphertexts, one per choice, that nobody but a threshold of trustees can decrypt, see utils/elgamal.rs
+// NOTE - the ballots are only ever added up, the score of a ballot is public and scales its ciphertexts so the weights are summed without decrypting anything
+// NOTE - the sum is frozen into encrypted_tally when the event closes, then each trustee sends a decryption share of every ciphertext of it and the counters are filled once there are enough of them
+
+
+
+pub const CHOICES: [&str; 3] = ["upvote", "downvote", "abstain"]; //-- the order of the ciphertexts inside a ballot
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptionSetup{
+    pub threshold: u32, //-- trustees needed to decrypt
+    pub trustees: u32, //-- trustees are numbered from 1 to this
+    pub commitments: Vec<Point>, //-- feldman commitments of the key polynomial, the first one is the public key
+}
+
+impl EncryptionSetup{
+
+    pub const MAX_TRUSTEES: u32 = 64;
+
+    pub fn validate(&self) -> Result<(), &'static str>{
+        if self.threshold == 0 || self.threshold > self.trustees{
+            return Err("threshold must be between 1 and the number of trustees");
+        }
+        if self.trustees > Self::MAX_TRUSTEES{
+            return Err("too many trustees");
+        }
+        if self.commitments.len() != self.threshold as usize{
+            return Err("expected one commitment per threshold trustee");
+        }
+        Ok(())
+    }
+
+    pub fn public_key(&self) -> Point{
+        self.commitments[0]
+    }
+
+    pub fn verification_key(&self, trustee: u32) -> Point{
+        Point(elgamal::verification_key(&self.commitments.iter().map(|c| c.0).collect::<Vec<_>>(), trustee as u64))
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CastEncryptedVoteRequest{
+    pub _id: String, //-- this is the id of the event took from the mongodb
+    pub wallet_address: String,
+    pub score: u32, // NOTE - this is the number of NFTs that the voter owns, it's public like on the other ballots
+    pub ciphertexts: Vec<Ciphertext>,
+    pub proof: BallotProof,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptedBallot{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub event_id: ObjectId,
+    pub wallet_address: String,
+    pub score: u32,
+    pub ciphertexts: Vec<Ciphertext>,
+    pub proof: BallotProof, //-- kept so anyone can check every ballot again
+    pub voted_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u32>, //-- the revision of the proposal the ballot was cast against
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptedChoice{
+    pub choice: String,
+    pub headcount: Ciphertext, //-- encrypts the number of ballots for this choice
+    pub weight: Ciphertext, //-- encrypts the sum of their scores
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptedTally{
+    pub choices: Vec<EncryptedChoice>,
+    pub ballots: u64, //-- the headcounts add up to this
+    pub total_weight: u64, //-- and the weights to this, both are public
+}
+
+impl Default for EncryptedTally{
+
+    fn default() -> EncryptedTally{ //-- the tally of no ballot
+        EncryptedTally{
+            choices: CHOICES.iter().map(|choice| EncryptedChoice{ choice: choice.to_string(), headcount: Ciphertext::zero(), weight: Ciphertext::zero() }).collect(),
+            ballots: 0,
+            total_weight: 0,
+        }
+    }
+}
+
+impl EncryptedTally{
+
+    pub fn add(&mut self, ballot: &EncryptedBallot){
+        for (choice, ciphertext) in self.choices.iter_mut().zip(&ballot.ciphertexts){
+            choice.headcount = choice.headcount.add(ciphertext);
+            choice.weight = choice.weight.add(&ciphertext.scale(ballot.score as u64));
+        }
+        self.ballots += 1;
+        self.total_weight += ballot.score as u64;
+    }
+
+    pub fn ciphertexts(&self) -> Vec<(Ciphertext, u64)>{ //-- what the trustees decrypt, headcount then weight of each choice, with the largest value each can hold
+        self.choices.iter().flat_map(|choice| [(choice.headcount, self.ballots), (choice.weight, self.total_weight)]).collect()
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DecryptionShareRequest{
+    pub trustee: u32,
+    pub shares: Vec<DecryptionShare>, //-- one per ciphertext of the encrypted tally in the order of EncryptedTally::ciphertexts()
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredDecryptionShare{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub event_id: ObjectId,
+    pub trustee: u32,
+    pub shares: Vec<DecryptionShare>,
+    pub submitted_at: i64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrusteeKey{
+    pub trustee: u32,
+    pub verification_key: Point,
+    pub submitted: bool, //-- true once its decryption share is in
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptionStatus{
+    pub proposal_id: String,
+    pub choices: Vec<String>,
+    pub public_key: Point, //-- encrypt the ballots to this
+    pub threshold: u32,
+    pub trustees: Vec<TrusteeKey>,
+    pub ballots: u64,
+    pub encrypted_tally: Option<EncryptedTally>, //-- set once the proposal is closed
+    pub decrypted_at: Option<i64>,
+}
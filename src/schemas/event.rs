@@ -4,18 +4,154 @@
 
 
 use serde::{Serialize, Deserialize};
-use mongodb::bson::{oid::ObjectId};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use super::commitment::RevealReport;
+use super::encryption::{EncryptedTally, EncryptionSetup};
+use super::vote_log::Choice;
+use super::conviction::ConvictionState;
+use crate::contexts::config::EventConfig;
 
 
 // NOTE - a mongodb document has been serialized into the BSON format before inserting into the collection
 
 
 
+pub const EVENT_SCHEMA_VERSION: u32 = 2; //-- bump this and add a data migration inside migrations.rs whenever the shape of a stored event changes
+
+
+
+pub fn normalize_title(title: &str) -> String{ //-- titles are unique regardless of case and extra whitespaces
+    title.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+}
+
+
+
+pub fn normalize_tags(tags: &[String]) -> Vec<String>{ //-- lowercased, trimmed and deduplicated while keeping the order
+    let mut normalized: Vec<String> = vec![];
+    for tag in tags.iter().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()){
+        if !normalized.contains(&tag){
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+
+
+pub fn parse_duration(text: &str) -> Option<i64>{ //-- "3d12h", "90m", "1w2d" or plain seconds
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<i64>(){
+        return Some(seconds).filter(|s| *s >= 0);
+    }
+    let mut total: i64 = 0;
+    let mut number = String::new();
+    for c in text.chars(){
+        if c.is_ascii_digit(){
+            number.push(c);
+            continue;
+        }
+        let unit = match c{
+            'w' => 604800,
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let amount: i64 = number.parse().ok()?; //-- also refuses a unit without a number
+        total = total.checked_add(amount.checked_mul(unit)?)?;
+        number.clear();
+    }
+    if number.is_empty() && !text.is_empty() { Some(total) } else { None } //-- a number left without its unit is a typo
+}
+
+
+
+pub fn parse_time(text: &str, base: i64) -> Option<i64>{ //-- an rfc 3339 timestamp or a duration after `base`
+    match chrono::DateTime::parse_from_rfc3339(text.trim()){
+        Ok(time) => Some(time.timestamp()),
+        Err(_) => parse_duration(text).and_then(|duration| base.checked_add(duration)),
+    }
+}
+
+
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Voter{
     pub event_owner_wallet_address: String,
     pub is_upvote: bool,
+    #[serde(default)]
+    pub abstain: bool, //-- takes part without taking a side, is_upvote is ignored then
     pub score: u32, // NOTE - this is the number of NFTs that this owner owns
+    #[serde(default)]
+    pub rationale: Option<String>, //-- markdown explaining the vote, published with the ballot
+}
+
+impl Voter{
+
+    pub fn rationale(&self) -> Option<String>{ //-- trimmed, None if it's blank
+        self.rationale.as_ref().map(|r| r.trim().to_string()).filter(|r| !r.is_empty())
+    }
+
+    pub fn check_rationale(&self, max_length: usize) -> Result<(), &'static str>{
+        match self.rationale(){
+            Some(rationale) if rationale.chars().count() > max_length => Err("the rationale is longer than EVENT_RATIONALE_MAX_LENGTH characters"),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingMode{
+    Open, //-- ballots are cast in the clear and the tally is live
+    CommitReveal, //-- wallets commit to a hidden ballot first then reveal it once the commit phase is over, see schemas::commitment
+    Encrypted, //-- ballots are encrypted to the key of the trustees and only their sum is ever decrypted, see schemas::encryption
+    Conviction, //-- no ballots nor deadline, wallets stake on it until its conviction passes the threshold, see schemas::conviction
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AntiSniping{ //-- a ballot that flips the leading outcome within the last `window_minutes` pushes expire_at back by `extension_minutes`, at most `max_extensions` times
+    pub window_minutes: u32,
+    pub extension_minutes: u32,
+    pub max_extensions: u32,
+}
+
+impl AntiSniping{
+
+    pub fn check(&self, start: i64, end: i64, bounds: &EventConfig) -> Result<(), &'static str>{ //-- the rule of a new proposal voting between start and end
+        if self.window_minutes == 0 || self.extension_minutes == 0 || self.max_extensions == 0{
+            return Err("the anti-sniping minutes and max_extensions must be greater than 0");
+        }
+        if self.window_minutes as i64 * 60 > end - start{
+            return Err("the anti-sniping window is longer than the voting window");
+        }
+        if end - start + self.max_extensions as i64 * self.extension_minutes as i64 * 60 > bounds.max_duration{ //-- fully extended it still has to fit the bounds
+            return Err("the anti-sniping extensions can push the voting window past the maximum duration");
+        }
+        Ok(())
+    }
+
+    pub fn is_closing(&self, at: i64, expire_at: i64) -> bool{ //-- true if a ballot at this time falls inside the final window
+        at < expire_at && at >= expire_at - self.window_minutes as i64 * 60
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Extension{
+    pub number: u32, //-- 1 for the first extension
+    pub wallet_address: String, //-- the voter whose ballot flipped the lead
+    pub leader_before: Outcome, //-- the side ahead right before and right after the ballot, see Tally::leader()
+    pub leader_after: Outcome,
+    pub previous_expire_at: i64,
+    pub expire_at: i64,
+    pub at: i64,
 }
 
 
@@ -33,12 +169,58 @@ pub struct EventAddRequest{
     pub title: String,
     pub content: String,
     pub creator_wallet_address: String,
-    pub upvotes: Option<u16>, // NOTE - we set this field to Option cause we don't want to pass the upvotes inside the request body, we'll fill it inside the server
-    pub downvotes: Option<u16>, // NOTE - we set this field to Option cause we don't want to pass the downvotes inside the request body, we'll fill it inside the server
-    pub voters: Option<Vec<Voter>>, // NOTE - we set this field to Option cause we don't want to pass the voters inside the request body, we'll update it later on using cast-vote route
+    pub upvotes: Option<u32>, // NOTE - we set this field to Option cause we don't want to pass the upvotes inside the request body, we'll fill it inside the server
+    pub downvotes: Option<u32>, // NOTE - we set this field to Option cause we don't want to pass the downvotes inside the request body, we'll fill it inside the server
+    pub upvotes_weight: Option<u64>, // NOTE - we set this field to Option cause we don't want to pass the upvotes weight inside the request body, we'll fill it inside the server
+    pub downvotes_weight: Option<u64>, // NOTE - we set this field to Option cause we don't want to pass the downvotes weight inside the request body, we'll fill it inside the server
+    pub abstains: Option<u32>, // NOTE - we set this field to Option cause we don't want to pass the abstains inside the request body, we'll fill it inside the server
+    pub abstains_weight: Option<u64>, // NOTE - we set this field to Option cause we don't want to pass the abstains weight inside the request body, we'll fill it inside the server
     pub is_expired: Option<bool>, // NOTE - we set this field to Option cause we don't want to pass the is_expired inside the request body, we'll update it once a event reached the deadline
     pub expire_at: Option<i64>, // NOTE - we set this field to Option cause we don't want to pass the expire_at inside the request body, we'll update it while we want to create a new event object
+    pub start_at: Option<i64>, // NOTE - we set this field to Option cause we don't want to pass the start_at inside the request body, we'll fill it from voting_start
+    #[serde(default, skip_serializing)]
+    pub voting_start: Option<String>, //-- an rfc 3339 time or a duration from now like "1d", voting opens right away without it
+    #[serde(default, skip_serializing)]
+    pub voting_end: Option<String>, //-- an rfc 3339 time or a duration from the start like "3d12h", EVENT_EXPIRATION after the start without it
     pub created_at: Option<i64>, // NOTE - we set this field to Option cause we don't want to pass the created time inside the request body, we'll fill it inside the server
+    pub category: Option<String>, //-- the slug of a category from the taxonomy
+    pub tags: Option<Vec<String>>, //-- slugs of tags from the taxonomy
+    pub title_normalized: Option<String>, // NOTE - we set this field to Option cause we don't want to pass the normalized title inside the request body, we'll fill it inside the server
+    pub schema_version: Option<u32>, // NOTE - we set this field to Option cause we don't want to pass the schema version inside the request body, we'll fill it inside the server
+    pub voting_mode: Option<VotingMode>, //-- defaults to open
+    pub reveal_at: Option<i64>, // NOTE - we set this field to Option cause we don't want to pass the reveal time inside the request body, we'll fill it inside the server for commit-reveal events
+    pub encryption: Option<EncryptionSetup>, //-- the key of the trustees, required for encrypted events
+    pub requested_amount: Option<u64>, //-- what a conviction event asks the grants pool for, required for them
+    pub anti_sniping: Option<AntiSniping>, //-- only for open events
+    pub revision: Option<u32>, // NOTE - we set this field to Option cause we don't want to pass the revision inside the request body, a new event starts at 1
+    pub comments: Option<u32>, // NOTE - we set this field to Option cause we don't want to pass the comments inside the request body, we'll fill it inside the server
+}
+
+impl EventAddRequest{
+
+    pub fn window(&self, now: i64, bounds: &EventConfig) -> Result<(i64, i64), &'static str>{ //-- when voting starts and ends, within the bounds set by the admin
+        let start = match self.voting_start.as_ref(){
+            Some(start) => parse_time(start, now).ok_or("voting_start must be an rfc 3339 time or a duration like 1d12h")?.max(now), //-- a start in the past means now
+            None => now,
+        };
+        if start - now > bounds.max_start_delay{
+            return Err("voting_start is too far in the future");
+        }
+        let end = match self.voting_end.as_ref(){
+            Some(end) => parse_time(end, start).ok_or("voting_end must be an rfc 3339 time or a duration like 3d12h")?,
+            None => start + bounds.expiration,
+        };
+        if end - start < bounds.min_duration{
+            return Err("the voting window is shorter than the minimum duration");
+        }
+        if end - start > bounds.max_duration{
+            return Err("the voting window is longer than the maximum duration");
+        }
+        if self.voting_mode == Some(VotingMode::CommitReveal) && end - start <= bounds.reveal_period{ //-- reveal_at would fall before the start
+            return Err("the voting window leaves no commit phase before the reveal period");
+        }
+        Ok((start, end))
+    }
 }
 
 
@@ -49,12 +231,63 @@ pub struct EventInfo{
     pub title: String,
     pub content: String,
     pub creator_wallet_address: String,
-    pub upvotes: Option<u16>,
-    pub downvotes: Option<u16>,
-    pub voters: Option<Vec<Voter>>,
+    pub upvotes: Option<u32>, //-- headcount of the upvoters, the ballots themselves live inside the ballots collection
+    pub downvotes: Option<u32>,
+    pub upvotes_weight: Option<u64>, //-- sum of the upvoters' scores
+    pub downvotes_weight: Option<u64>,
+    pub abstains: Option<u32>, //-- None on events created before abstaining existed
+    pub abstains_weight: Option<u64>,
     pub is_expired: Option<bool>,
     pub expire_at: Option<i64>,
+    pub start_at: Option<i64>, //-- None on events that opened when they were created
     pub created_at: Option<i64>,
+    pub category: Option<String>, //-- None on events created without one or before the taxonomy existed
+    pub tags: Option<Vec<String>>,
+    pub title_normalized: Option<String>,
+    pub schema_version: Option<u32>,
+    pub closed_at: Option<i64>, //-- when voting actually ended, it can be a bit after expire_at cause of the sweep interval
+    pub merkle_root: Option<String>, //-- hex root of the merkle tree over the final ballots, set when the event closes
+    pub merkle_leaves: Option<u64>, //-- number of ballots under the root
+    pub voting_mode: Option<VotingMode>, //-- None on events created before the modes existed, which are open
+    pub reveal_at: Option<i64>, //-- end of the commit phase and start of the reveal phase of a commit-reveal event
+    pub reveal_report: Option<RevealReport>, //-- set when a commit-reveal event closes
+    pub encryption: Option<EncryptionSetup>,
+    pub encrypted_tally: Option<EncryptedTally>, //-- the sum of the encrypted ballots, frozen when an encrypted event closes
+    pub decrypted_at: Option<i64>, //-- when the trustees' shares filled the counters of an encrypted event
+    pub delegated: Option<Counters>, //-- what came in through delegations, frozen when the event closes and resolved on the fly before that
+    pub requested_amount: Option<u64>,
+    pub conviction: Option<ConvictionState>, //-- the cached conviction of a conviction event, refreshed on every stake and every CONVICTION_INTERVAL
+    pub anti_sniping: Option<AntiSniping>,
+    pub extensions: Option<Vec<Extension>>, //-- every time the anti-sniping rule pushed expire_at back, oldest first
+    pub revision: Option<u32>, //-- the number of the current title and content, None on events created before the edits existed which are at 1
+    pub comments: Option<u32>, //-- the comments that aren't hidden, None until the first comment on events created before the discussions existed
+}
+
+impl EventInfo{
+
+    pub fn voting_mode(&self) -> VotingMode{
+        self.voting_mode.unwrap_or(VotingMode::Open)
+    }
+
+    pub fn has_started(&self, now: i64) -> bool{ //-- ballots and stakes are refused before the start
+        self.start_at.map(|start_at| now >= start_at).unwrap_or(true)
+    }
+
+    pub fn is_over(&self, now: i64) -> bool{ //-- ballots and stakes are refused from expire_at on, without waiting for the sweep to close it
+        self.is_expired.unwrap_or(false) || self.expire_at.map(|expire_at| now >= expire_at).unwrap_or(false)
+    }
+
+    pub fn revision(&self) -> u32{
+        self.revision.unwrap_or(1)
+    }
+
+    pub fn is_editable(&self, now: i64) -> bool{ //-- the content is locked once voting starts
+        !self.is_expired.unwrap_or(false) && !self.has_started(now)
+    }
+
+    pub fn is_revealing(&self, now: i64) -> bool{ //-- false during the commit phase
+        self.reveal_at.map(|reveal_at| now >= reveal_at).unwrap_or(true)
+    }
 }
 
 
@@ -62,6 +295,247 @@ pub struct EventInfo{
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AvailableEvents{
     pub events: Vec<EventInfo>,
+    pub total: u64, //-- number of events matching the filters across all pages
+    pub next_cursor: Option<String>, //-- pass this as the cursor query param to get the next page, it's None on the last page
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventStatus{
+    Upcoming, //-- voting hasn't started yet
+    Open,
+    #[serde(alias = "closed")]
+    Expired,
+    All,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSort{
+    Newest,
+    EndingSoonest,
+    MostVotes,
+}
+
+impl EventSort{
+
+    fn field(&self) -> &'static str{
+        match self{
+            EventSort::Newest => "created_at",
+            EventSort::EndingSoonest => "deadline", //-- expire_at with the events that have none ranked last
+            EventSort::MostVotes => "total_votes",
+        }
+    }
+
+    fn direction(&self) -> i32{
+        match self{
+            EventSort::EndingSoonest => 1,
+            _ => -1,
+        }
+    }
+}
+
+
+
+const NO_DEADLINE: i64 = i64::MAX; //-- the sort key of the events without an expire_at, like the conviction ones, so they come after every deadline
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventCursor{ //-- the sort key and the id of the last event in a page, hex encoded json so clients treat it as an opaque string
+    pub value: i64,
+    pub id: ObjectId,
+}
+
+impl EventCursor{
+
+    pub fn encode(&self) -> String{
+        hex::encode(serde_json::to_vec(self).unwrap())
+    }
+
+    pub fn decode(cursor: &str) -> Option<EventCursor>{
+        hex::decode(cursor).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok())
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListEventsQuery{
+    pub status: Option<EventStatus>, //-- defaults to open
+    pub sort: Option<EventSort>, //-- defaults to newest
+    pub creator: Option<String>,
+    pub tag: Option<String>,
+    pub category: Option<String>,
+    pub created_from: Option<i64>,
+    pub created_to: Option<i64>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl ListEventsQuery{
+
+    pub const DEFAULT_LIMIT: i64 = 20;
+    pub const MAX_LIMIT: i64 = 100;
+
+    pub fn sort(&self) -> EventSort{
+        self.sort.unwrap_or(EventSort::Newest)
+    }
+
+    pub fn limit(&self) -> i64{
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn filter(&self) -> Document{ //-- the filters without the cursor which is also used to count the total
+        let mut filter = doc!{};
+        let now = chrono::Utc::now().timestamp();
+        match self.status.unwrap_or(EventStatus::Open){
+            EventStatus::Upcoming => {
+                filter.insert("is_expired", false);
+                filter.insert("start_at", doc!{"$gt": now});
+            },
+            EventStatus::Open => {
+                filter.insert("is_expired", false);
+                filter.insert("$or", vec![doc!{"start_at": null}, doc!{"start_at": {"$lte": now}}]);
+            },
+            EventStatus::Expired => { filter.insert("is_expired", true); },
+            EventStatus::All => {},
+        }
+        if let Some(creator) = self.creator.as_ref(){
+            filter.insert("creator_wallet_address", creator.clone());
+        }
+        if let Some(tag) = self.tag.as_ref(){
+            filter.insert("tags", tag.trim().to_lowercase());
+        }
+        if let Some(category) = self.category.as_ref(){
+            filter.insert("category", category.trim().to_lowercase());
+        }
+        let mut created_at = doc!{};
+        if let Some(from) = self.created_from{
+            created_at.insert("$gte", from);
+        }
+        if let Some(to) = self.created_to{
+            created_at.insert("$lte", to);
+        }
+        if !created_at.is_empty(){
+            filter.insert("created_at", created_at);
+        }
+        filter
+    }
+
+    pub fn pipeline(&self, cursor: Option<&EventCursor>) -> Vec<Document>{
+        let sort = self.sort();
+        let field = sort.field();
+        let mut pipeline = vec![
+            doc!{"$match": self.filter()},
+            doc!{"$addFields": {
+                "total_votes": {"$add": [{"$ifNull": ["$upvotes", 0]}, {"$ifNull": ["$downvotes", 0]}, {"$ifNull": ["$abstains", 0]}]},
+                "deadline": {"$ifNull": ["$expire_at", NO_DEADLINE]},
+            }},
+        ];
+        if let Some(cursor) = cursor{ //-- everything strictly after the last event of the previous page in the sort order, the id breaks the ties
+            let op = if sort.direction() == 1 { "$gt" } else { "$lt" };
+            pipeline.push(doc!{"$match": {"$or": [
+                {field: {op: cursor.value}},
+                {field: cursor.value, "_id": {op: cursor.id}},
+            ]}});
+        }
+        pipeline.push(doc!{"$sort": {field: sort.direction(), "_id": sort.direction()}});
+        pipeline.push(doc!{"$limit": self.limit() + 1}); //-- one more than the page to know if there is a next page
+        pipeline.push(doc!{"$project": {"total_votes": 0, "deadline": 0}});
+        pipeline
+    }
+
+    pub fn cursor_of(&self, event: &Document) -> Option<EventCursor>{ //-- builds the cursor pointing after this raw event document
+        let value = match self.sort(){
+            EventSort::MostVotes => as_i64(event.get("upvotes")) + as_i64(event.get("downvotes")) + as_i64(event.get("abstains")),
+            EventSort::EndingSoonest => match event.get("expire_at"){
+                None | Some(Bson::Null) => NO_DEADLINE,
+                expire_at => as_i64(expire_at),
+            },
+            sort => as_i64(event.get(sort.field())),
+        };
+        Some(EventCursor{ value, id: event.get_object_id("_id").ok()? })
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchQuery{
+    pub q: Option<String>, //-- words matched against the title and the content, "quoted phrases" and -excluded words work too
+    pub status: Option<EventStatus>, //-- defaults to all
+    pub tag: Option<String>,
+    pub category: Option<String>,
+    pub from: Option<i64>, //-- created at or after
+    pub to: Option<i64>, //-- created at or before
+    pub offset: Option<u64>,
+    pub limit: Option<i64>,
+}
+
+impl SearchQuery{
+
+    pub const DEFAULT_LIMIT: i64 = 20;
+    pub const MAX_LIMIT: i64 = 100;
+
+    pub fn text(&self) -> Option<&str>{
+        self.q.as_deref().map(str::trim).filter(|q| !q.is_empty())
+    }
+
+    pub fn limit(&self) -> i64{
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u64{
+        self.offset.unwrap_or(0)
+    }
+
+    pub fn pipeline(&self, text: &str) -> Vec<Document>{ //-- the matching events with the best text score first, the title weighs more than the content inside the text index
+        let mut filter = ListEventsQuery{
+            status: Some(self.status.unwrap_or(EventStatus::All)),
+            sort: None,
+            creator: None,
+            tag: self.tag.clone(),
+            category: self.category.clone(),
+            created_from: self.from,
+            created_to: self.to,
+            cursor: None,
+            limit: None,
+        }.filter();
+        filter.insert("$text", doc!{"$search": text});
+        vec![
+            doc!{"$match": filter}, //-- $text has to be inside the first stage
+            doc!{"$addFields": {"relevance": {"$meta": "textScore"}}},
+            doc!{"$facet": {
+                "total": [{"$count": "count"}],
+                "page": [
+                    {"$sort": {"relevance": -1, "_id": -1}},
+                    {"$skip": self.offset() as i64},
+                    {"$limit": self.limit()},
+                ],
+            }},
+        ]
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchHit{
+    pub event: EventInfo,
+    pub relevance: f64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchResults{
+    pub results: Vec<SearchHit>,
+    pub total: u64,
+    pub next_offset: Option<u64>, //-- None on the last page
 }
 
 
@@ -71,14 +545,414 @@ pub struct ExpireEventRequest{
 }
 
 
-impl EventInfo{
+fn as_i64(value: Option<&Bson>) -> i64{ //-- counters may be stored as int32 or int64 depending on who wrote them last
+    match value{
+        Some(Bson::Int64(v)) => *v,
+        Some(Bson::Int32(v)) => *v as i64,
+        _ => 0,
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters{ //-- the aggregate counters of an event, signed so they can also hold the change a single vote makes
+    pub upvotes: i64,
+    pub downvotes: i64,
+    pub upvotes_weight: i64,
+    pub downvotes_weight: i64,
+    #[serde(default)]
+    pub abstains: i64,
+    #[serde(default)]
+    pub abstains_weight: i64,
+}
+
+impl Counters{
+
+    pub fn of_event(event: &EventInfo) -> Counters{
+        Counters{
+            upvotes: event.upvotes.unwrap_or(0) as i64,
+            downvotes: event.downvotes.unwrap_or(0) as i64,
+            upvotes_weight: event.upvotes_weight.unwrap_or(0) as i64,
+            downvotes_weight: event.downvotes_weight.unwrap_or(0) as i64,
+            abstains: event.abstains.unwrap_or(0) as i64,
+            abstains_weight: event.abstains_weight.unwrap_or(0) as i64,
+        }
+    }
+
+    pub fn of_choice(choice: &Choice) -> Counters{ //-- what a single ballot adds
+        if choice.abstain{
+            Counters{ abstains: 1, abstains_weight: choice.score as i64, ..Default::default() }
+        } else if choice.is_upvote{
+            Counters{ upvotes: 1, upvotes_weight: choice.score as i64, ..Default::default() }
+        } else{
+            Counters{ downvotes: 1, downvotes_weight: choice.score as i64, ..Default::default() }
+        }
+    }
+
+    pub fn add(&self, other: &Counters) -> Counters{
+        Counters{
+            upvotes: self.upvotes + other.upvotes,
+            downvotes: self.downvotes + other.downvotes,
+            upvotes_weight: self.upvotes_weight + other.upvotes_weight,
+            downvotes_weight: self.downvotes_weight + other.downvotes_weight,
+            abstains: self.abstains + other.abstains,
+            abstains_weight: self.abstains_weight + other.abstains_weight,
+        }
+    }
+
+    pub fn sub(&self, other: &Counters) -> Counters{
+        Counters{
+            upvotes: self.upvotes - other.upvotes,
+            downvotes: self.downvotes - other.downvotes,
+            upvotes_weight: self.upvotes_weight - other.upvotes_weight,
+            downvotes_weight: self.downvotes_weight - other.downvotes_weight,
+            abstains: self.abstains - other.abstains,
+            abstains_weight: self.abstains_weight - other.abstains_weight,
+        }
+    }
+
+    pub fn weight(&self) -> i64{
+        self.upvotes_weight + self.downvotes_weight + self.abstains_weight
+    }
+
+    pub fn is_zero(&self) -> bool{
+        *self == Counters::default()
+    }
+
+    pub fn as_document(&self) -> Document{ //-- the $inc to apply on the event counters
+        doc!{"upvotes": self.upvotes, "downvotes": self.downvotes, "upvotes_weight": self.upvotes_weight, "downvotes_weight": self.downvotes_weight, "abstains": self.abstains, "abstains_weight": self.abstains_weight}
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ChoiceTally{
+    pub headcount: u32, //-- direct ballots only
+    pub weight: u64, //-- sum of the voters' scores and of the power delegated to them
+    pub delegated_weight: u64, //-- the part of the weight that came in through delegations
+    pub delegators: u32,
+    pub percentage: f64, //-- share of the total weight, abstentions included
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Tally{
+    pub upvotes: ChoiceTally,
+    pub downvotes: ChoiceTally,
+    pub abstains: ChoiceTally, //-- counts toward the quorum and the turnout but never toward the outcome
+    pub headcount: u32,
+    pub total_weight: u64,
+    pub delegated_weight: u64,
+    pub quorum: u64, //-- total weight needed for the result to count, 0 means no quorum
+    pub quorum_progress: f64, //-- total weight over quorum, can go above 1
+    pub quorum_reached: bool,
+}
+
+impl Tally{
+
+    pub fn new(event: &EventInfo, quorum: u64) -> Tally{ //-- computed from the aggregate counters kept on the event
+        Tally::with_delegated(&Counters::of_event(event), &event.delegated.unwrap_or_default(), quorum)
+    }
+
+    pub fn with_delegated(direct: &Counters, delegated: &Counters, quorum: u64) -> Tally{ //-- the delegated weight counts toward the quorum and the outcome like any other
+        let choice = |headcount: i64, weight: i64, delegators: i64, delegated_weight: i64| ChoiceTally{
+            headcount: headcount.max(0) as u32,
+            weight: (weight.max(0) + delegated_weight.max(0)) as u64,
+            delegated_weight: delegated_weight.max(0) as u64,
+            delegators: delegators.max(0) as u32,
+            percentage: 0.0,
+        };
+        let mut tally = Tally{
+            upvotes: choice(direct.upvotes, direct.upvotes_weight, delegated.upvotes, delegated.upvotes_weight),
+            downvotes: choice(direct.downvotes, direct.downvotes_weight, delegated.downvotes, delegated.downvotes_weight),
+            abstains: choice(direct.abstains, direct.abstains_weight, delegated.abstains, delegated.abstains_weight),
+            quorum,
+            ..Default::default()
+        };
+        tally.headcount = tally.upvotes.headcount + tally.downvotes.headcount + tally.abstains.headcount;
+        tally.total_weight = tally.upvotes.weight + tally.downvotes.weight + tally.abstains.weight;
+        tally.delegated_weight = tally.upvotes.delegated_weight + tally.downvotes.delegated_weight + tally.abstains.delegated_weight;
+        if tally.total_weight > 0{
+            let total_weight = tally.total_weight as f64;
+            for choice in [&mut tally.upvotes, &mut tally.downvotes, &mut tally.abstains]{
+                choice.percentage = choice.weight as f64 * 100.0 / total_weight;
+            }
+        }
+        tally.quorum_progress = if quorum == 0 { 1.0 } else { tally.total_weight as f64 / quorum as f64 };
+        tally.quorum_reached = tally.total_weight >= quorum;
+        tally
+    }
 
-    pub async fn add_voter(self, voter: Voter) -> Vec<Voter>{ //-- we don't take a reference to self cause we can't dereference a shared reference and if we do that then cannot borrow `*voters` as mutable, cause it is behind a `&` reference and `voters` is a `&` reference, so the data it refers to cannot be borrowed as mutable cause we have to define the first argument as &mut self
-        let mut voters = self.voters.unwrap();
-        let index = voters.iter().position(|v| v.event_owner_wallet_address == voter.event_owner_wallet_address); //-- this owner has alreay voted to this event
-        if index == None{
-            voters.push(voter);
+    pub fn outcome(&self, is_expired: bool) -> Outcome{ //-- only the sides decide, the abstentions have already done their part by helping the quorum
+        if !is_expired{
+            Outcome::Pending
+        } else if !self.quorum_reached{
+            Outcome::NoQuorum
+        } else{
+            self.leader()
         }
-        voters
+    }
+
+    pub fn leader(&self) -> Outcome{ //-- which side is ahead, whatever the quorum, only Passed, Rejected or Tied
+        match self.upvotes.weight.cmp(&self.downvotes.weight){
+            std::cmp::Ordering::Greater => Outcome::Passed,
+            std::cmp::Ordering::Less => Outcome::Rejected,
+            std::cmp::Ordering::Equal => Outcome::Tied,
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome{
+    Pending, //-- voting is still open
+    Passed,
+    Rejected,
+    Tied,
+    NoQuorum,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProposalDetail{
+    pub proposal: EventInfo, //-- use the voters route to page through the ballots
+    pub tally: Tally,
+    pub time_remaining: i64, //-- seconds until expire_at, 0 once it's passed
+    pub time_until_start: i64, //-- seconds until voting starts, 0 once it has
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CreatorStats{
+    pub proposals_created: u64,
+    pub active: u64, //-- proposals still open for voting
+    pub closed: u64,
+    pub passed: u64,
+    pub pass_rate: f64, //-- passed over closed proposals, open ones have no outcome yet
+    pub total_votes: u64, //-- ballots received across all proposals
+    pub average_turnout: f64, //-- average number of ballots per proposal
+}
+
+impl CreatorStats{
+
+    pub fn pipeline(creator: &str, quorum: u64) -> Vec<Document>{ //-- the passed condition must match Tally::outcome()
+        let upvotes_weight = doc!{"$add": [{"$ifNull": ["$upvotes_weight", 0]}, {"$ifNull": ["$delegated.upvotes_weight", 0]}]};
+        let downvotes_weight = doc!{"$add": [{"$ifNull": ["$downvotes_weight", 0]}, {"$ifNull": ["$delegated.downvotes_weight", 0]}]};
+        let abstains_weight = doc!{"$add": [{"$ifNull": ["$abstains_weight", 0]}, {"$ifNull": ["$delegated.abstains_weight", 0]}]};
+        let total_weight = doc!{"$add": [upvotes_weight.clone(), downvotes_weight.clone(), abstains_weight]};
+        let total_votes = doc!{"$add": [{"$ifNull": ["$upvotes", 0]}, {"$ifNull": ["$downvotes", 0]}, {"$ifNull": ["$abstains", 0]}]};
+        let passed = doc!{"$or": [
+            {"$and": [
+                {"$eq": ["$is_expired", true]},
+                {"$ne": ["$voting_mode", "conviction"]},
+                {"$gte": [total_weight, quorum as i64]},
+                {"$gt": [upvotes_weight, downvotes_weight]},
+            ]},
+            {"$gt": ["$conviction.passed_at", null]}, //-- conviction events pass on their own
+        ]};
+        vec![
+            doc!{"$match": {"creator_wallet_address": creator}},
+            doc!{"$group": {
+                "_id": null,
+                "proposals_created": {"$sum": 1},
+                "active": {"$sum": {"$cond": [{"$eq": ["$is_expired", true]}, 0, 1]}},
+                "closed": {"$sum": {"$cond": [{"$eq": ["$is_expired", true]}, 1, 0]}},
+                "passed": {"$sum": {"$cond": [passed, 1, 0]}},
+                "total_votes": {"$sum": total_votes.clone()},
+                "average_turnout": {"$avg": total_votes},
+            }},
+        ]
+    }
+
+    pub fn from_group(group: &Document) -> CreatorStats{
+        let count = |key: &str| match group.get(key){
+            Some(Bson::Int64(v)) => *v as u64,
+            Some(Bson::Int32(v)) => *v as u64,
+            _ => 0,
+        };
+        let closed = count("closed");
+        let passed = count("passed");
+        CreatorStats{
+            proposals_created: count("proposals_created"),
+            active: count("active"),
+            closed,
+            passed,
+            pass_rate: if closed == 0 { 0.0 } else { passed as f64 / closed as f64 },
+            total_votes: count("total_votes"),
+            average_turnout: group.get_f64("average_turnout").unwrap_or(0.0),
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreatorDashboard{
+    pub stats: CreatorStats,
+    pub proposals: AvailableEvents,
+}
+
+
+
+#[cfg(test)]
+mod tests{
+
+    use super::*;
+
+    const NOW: i64 = 1_800_000_000;
+
+    fn bounds() -> EventConfig{ //-- the shipped defaults
+        EventConfig{
+            expiration: 172800,
+            quorum: 0,
+            sweep_interval: 30,
+            reveal_period: 86400,
+            min_duration: 3600,
+            max_duration: 2592000,
+            max_start_delay: 2592000,
+            rationale_max_length: 2000,
+            max_score: 1000000,
+            delete_key: "delete-key".to_string(),
+        }
+    }
+
+    fn request(voting_mode: Option<VotingMode>, voting_start: Option<&str>, voting_end: Option<&str>) -> EventAddRequest{
+        serde_json::from_value(serde_json::json!({
+            "title": "t",
+            "content": "c",
+            "creator_wallet_address": "w",
+            "voting_mode": voting_mode,
+            "voting_start": voting_start,
+            "voting_end": voting_end,
+        })).unwrap()
+    }
+
+    fn event(fields: serde_json::Value) -> EventInfo{ //-- every field left out is None
+        let mut event = serde_json::json!({"_id": null, "title": "t", "content": "c", "creator_wallet_address": "w"});
+        event.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(event).unwrap()
+    }
+
+    #[test]
+    fn window_defaults_to_the_expiration_from_now(){
+        assert_eq!(request(None, None, None).window(NOW, &bounds()), Ok((NOW, NOW + 172800)));
+        assert_eq!(request(None, Some("1d"), Some("2h")).window(NOW, &bounds()), Ok((NOW + 86400, NOW + 86400 + 7200)));
+    }
+
+    #[test]
+    fn window_stays_within_the_bounds(){
+        assert!(request(None, None, Some("59m")).window(NOW, &bounds()).is_err());
+        assert!(request(None, None, Some("31d")).window(NOW, &bounds()).is_err());
+        assert!(request(None, Some("31d"), None).window(NOW, &bounds()).is_err());
+        assert!(request(None, None, Some("1h")).window(NOW, &bounds()).is_ok());
+    }
+
+    #[test]
+    fn commit_reveal_window_needs_a_commit_phase(){
+        let commit_reveal = Some(VotingMode::CommitReveal);
+        assert!(request(commit_reveal, None, Some("1h")).window(NOW, &bounds()).is_err()); //-- fine for an open event, all reveal for this one
+        assert!(request(commit_reveal, None, Some("1d")).window(NOW, &bounds()).is_err());
+        assert!(request(commit_reveal, None, Some("1d1s")).window(NOW, &bounds()).is_ok());
+        assert!(request(commit_reveal, None, None).window(NOW, &bounds()).is_ok());
+    }
+
+    #[test]
+    fn is_over_from_expire_at_even_before_the_sweep(){
+        let open = event(serde_json::json!({"is_expired": false, "expire_at": NOW}));
+        assert!(!open.is_over(NOW - 1));
+        assert!(open.is_over(NOW));
+        assert!(event(serde_json::json!({"is_expired": true, "expire_at": NOW})).is_over(NOW - 1)); //-- closed by hand
+        assert!(!event(serde_json::json!({"is_expired": false})).is_over(NOW)); //-- conviction events have no deadline
+    }
+
+    #[test]
+    fn closing_window_includes_its_start_and_stops_at_the_deadline(){
+        let rule = AntiSniping{ window_minutes: 10, extension_minutes: 15, max_extensions: 3 };
+        let expire_at = NOW;
+        assert!(!rule.is_closing(expire_at - 601, expire_at));
+        assert!(rule.is_closing(expire_at - 600, expire_at));
+        assert!(rule.is_closing(expire_at - 1, expire_at));
+        assert!(!rule.is_closing(expire_at, expire_at)); //-- the ballot is refused there, see is_over()
+        assert!(event(serde_json::json!({"is_expired": false, "expire_at": expire_at})).is_over(expire_at));
+    }
+
+    #[test]
+    fn anti_sniping_has_to_fit_the_bounds(){
+        let rule = AntiSniping{ window_minutes: 10, extension_minutes: 15, max_extensions: 3 };
+        assert!(rule.check(NOW, NOW + 3600, &bounds()).is_ok());
+        assert!(rule.check(NOW, NOW + 599, &bounds()).is_err()); //-- the window is longer than the voting
+        assert!(rule.check(NOW, NOW + 2592000, &bounds()).is_err()); //-- the extensions go past the maximum duration
+        assert!(AntiSniping{ max_extensions: 0, ..rule }.check(NOW, NOW + 3600, &bounds()).is_err());
+    }
+
+    fn counters(upvotes_weight: i64, downvotes_weight: i64, abstains_weight: i64) -> Counters{ //-- one voter per side that has weight
+        Counters{
+            upvotes: (upvotes_weight > 0) as i64,
+            downvotes: (downvotes_weight > 0) as i64,
+            abstains: (abstains_weight > 0) as i64,
+            upvotes_weight,
+            downvotes_weight,
+            abstains_weight,
+        }
+    }
+
+    #[test]
+    fn leader_is_the_sign_of_the_weight_difference_whatever_the_quorum(){
+        let quorum = 100;
+        assert_eq!(Tally::with_delegated(&counters(3, 2, 0), &Counters::default(), quorum).leader(), Outcome::Passed);
+        assert_eq!(Tally::with_delegated(&counters(2, 3, 0), &Counters::default(), quorum).leader(), Outcome::Rejected);
+        assert_eq!(Tally::with_delegated(&counters(2, 2, 50), &Counters::default(), quorum).leader(), Outcome::Tied);
+        let below = Tally::with_delegated(&counters(3, 2, 0), &Counters::default(), quorum);
+        let above = Tally::with_delegated(&counters(3, 2, 200), &Counters::default(), quorum);
+        assert_ne!(below.outcome(true), above.outcome(true)); //-- reaching the quorum changes the outcome
+        assert_eq!(below.leader(), above.leader()); //-- but it's no lead flip
+    }
+
+    #[test]
+    fn outcome_waits_for_the_close_and_the_quorum(){
+        let tally = Tally::with_delegated(&counters(30, 20, 0), &Counters::default(), 100);
+        assert_eq!(tally.outcome(false), Outcome::Pending);
+        assert_eq!(tally.outcome(true), Outcome::NoQuorum);
+        assert_eq!(Tally::with_delegated(&counters(60, 40, 0), &Counters::default(), 100).outcome(true), Outcome::Passed);
+        assert_eq!(Tally::with_delegated(&counters(40, 60, 0), &Counters::default(), 100).outcome(true), Outcome::Rejected);
+        assert_eq!(Tally::with_delegated(&counters(50, 50, 0), &Counters::default(), 100).outcome(true), Outcome::Tied);
+        assert_eq!(Tally::with_delegated(&Counters::default(), &Counters::default(), 0).outcome(true), Outcome::Tied); //-- no quorum asked and nobody voted
+    }
+
+    #[test]
+    fn abstentions_reach_the_quorum_without_deciding(){
+        let tally = Tally::with_delegated(&counters(2, 1, 97), &Counters::default(), 100);
+        assert!(tally.quorum_reached);
+        assert_eq!(tally.total_weight, 100);
+        assert_eq!(tally.outcome(true), Outcome::Passed);
+        assert_eq!(tally.abstains.percentage, 97.0);
+        assert_eq!(Tally::with_delegated(&counters(1, 1, 500), &Counters::default(), 100).outcome(true), Outcome::Tied);
+    }
+
+    #[test]
+    fn delegated_weight_counts_like_any_other(){
+        let tally = Tally::with_delegated(&counters(40, 50, 0), &counters(20, 0, 0), 100);
+        assert_eq!(tally.upvotes.weight, 60);
+        assert_eq!(tally.upvotes.delegated_weight, 20);
+        assert_eq!(tally.upvotes.delegators, 1);
+        assert_eq!(tally.upvotes.headcount, 1); //-- the delegators aren't voters
+        assert_eq!((tally.total_weight, tally.delegated_weight), (110, 20));
+        assert_eq!(tally.outcome(true), Outcome::Passed);
+        assert_eq!(Tally::with_delegated(&counters(40, 50, 0), &Counters::default(), 100).outcome(true), Outcome::NoQuorum);
+    }
+
+    #[test]
+    fn has_started_at_start_at(){
+        let scheduled = event(serde_json::json!({"start_at": NOW}));
+        assert!(!scheduled.has_started(NOW - 1));
+        assert!(scheduled.has_started(NOW));
+        assert!(event(serde_json::json!({})).has_started(NOW));
     }
 }
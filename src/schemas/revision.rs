@@ -0,0 +1,127 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+
+
+// NOTE - the creator of a proposal can edit its title, content and tags until voting starts, every version is kept as a numbered revision and the original is revision 1
+// NOTE - the event only holds the current version and its number, ballots record the number they were cast against which, with the content locked once voting starts, is always the last one
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Revision{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub event_id: ObjectId,
+    pub number: u32, //-- 1 for the content the proposal was created with
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub editor: String, //-- the wallet that made this revision, always the creator
+    pub note: Option<String>, //-- what the edit was about, like a commit message
+    pub created_at: i64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EditEventRequest{
+    pub _id: String, //-- this is the id of the event took from the mongodb
+    pub creator_wallet_address: String,
+    pub title: Option<String>, //-- the fields left out stay as they are
+    pub content: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub note: Option<String>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevisionList{
+    pub current: u32,
+    pub revisions: Vec<Revision>, //-- oldest first
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp{
+    Same,
+    Added,
+    Removed,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffLine{
+    pub op: DiffOp,
+    pub text: String,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevisionDiff{
+    pub proposal_id: String,
+    pub from: u32,
+    pub to: u32,
+    pub title: Vec<DiffLine>,
+    pub content: Vec<DiffLine>,
+    pub tags_added: Vec<String>,
+    pub tags_removed: Vec<String>,
+}
+
+impl RevisionDiff{
+
+    pub fn between(from: &Revision, to: &Revision) -> RevisionDiff{
+        RevisionDiff{
+            proposal_id: to.event_id.to_hex(),
+            from: from.number,
+            to: to.number,
+            title: diff_lines(&from.title, &to.title),
+            content: diff_lines(&from.content, &to.content),
+            tags_added: to.tags.iter().filter(|t| !from.tags.contains(t)).cloned().collect(),
+            tags_removed: from.tags.iter().filter(|t| !to.tags.contains(t)).cloned().collect(),
+        }
+    }
+}
+
+
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DiffQuery{
+    pub against: Option<u32>, //-- the revision to compare with, the previous one by default
+}
+
+
+
+pub fn diff_lines(before: &str, after: &str) -> Vec<DiffLine>{ //-- line by line diff over the longest common subsequence, removals come before additions at every change
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+    let mut lcs = vec![vec![0_u32; b.len() + 1]; a.len() + 1]; //-- lcs[i][j] is the length of the lcs of a[i..] and b[j..]
+    for i in (0..a.len()).rev(){
+        for j in (0..b.len()).rev(){
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let line = |op, text: &str| DiffLine{ op, text: text.to_string() };
+    let (mut i, mut j) = (0, 0);
+    let mut lines = vec![];
+    while i < a.len() && j < b.len(){
+        if a[i] == b[j]{
+            lines.push(line(DiffOp::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1]{
+            lines.push(line(DiffOp::Removed, a[i]));
+            i += 1;
+        } else{
+            lines.push(line(DiffOp::Added, b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(a[i..].iter().map(|text| line(DiffOp::Removed, text)));
+    lines.extend(b[j..].iter().map(|text| line(DiffOp::Added, text)));
+    lines
+}
@@ -0,0 +1,20 @@
+
+
+
+
+use serde::{Serialize, Deserialize};
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StreamQuery{
+    pub proposals: Option<String>, //-- comma separated proposal ids, every proposal if it's not set
+    pub last_event_id: Option<i64>, //-- for clients that can't set the Last-Event-ID header, the header wins if both are set
+}
+
+impl StreamQuery{
+
+    pub fn proposals(&self) -> Vec<String>{
+        self.proposals.as_deref().unwrap_or_default().split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+
+
+// NOTE - the categories and tags a proposal can carry are managed by the admins, a proposal has at most one category and any number of tags, both stored on it by slug
+// NOTE - renaming a term only changes its display name so the slugs on the proposals stay valid, a term still used by a proposal can't be deleted
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TermKind{
+    Category,
+    Tag,
+}
+
+impl TermKind{
+
+    pub fn field(&self) -> &'static str{ //-- where the slugs of this kind live on an event
+        match self{
+            TermKind::Category => "category",
+            TermKind::Tag => "tags",
+        }
+    }
+}
+
+
+
+pub fn slugify(text: &str) -> String{ //-- "Treasury & Grants" becomes "treasury-grants"
+    let mut slug = String::new();
+    for c in text.trim().to_lowercase().chars(){
+        if c.is_alphanumeric(){
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-'){
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Term{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub kind: TermKind,
+    pub slug: String, //-- what the proposals store, unique per kind
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: i64,
+    pub updated_at: Option<i64>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TermRequest{
+    pub kind: TermKind,
+    pub name: String,
+    pub slug: Option<String>, //-- made from the name without it
+    pub description: Option<String>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UpdateTermRequest{
+    pub name: Option<String>, //-- the fields left out stay as they are
+    pub description: Option<String>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Taxonomy{
+    pub categories: Vec<Term>,
+    pub tags: Vec<Term>,
+}
@@ -0,0 +1,176 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+use super::event::{Counters, Outcome, Tally, Voter};
+
+
+// NOTE - the vote log is append only, every cast, change and retraction of a ballot is a new record and records are never updated nor deleted by the app, only dropped along with their deleted event
+// NOTE - the counters on the event are only a cache, they're incremented with the delta of each record and can always be rebuilt by folding the log of the event (see utils::votes::project())
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteAction{
+    Cast,
+    Change,
+    Retract,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Choice{
+    pub is_upvote: bool, //-- always false on an abstention
+    #[serde(default)]
+    pub abstain: bool,
+    pub score: u32,
+}
+
+impl Choice{
+
+    pub fn of(voter: &Voter) -> Choice{
+        Choice{ is_upvote: voter.is_upvote && !voter.abstain, abstain: voter.abstain, score: voter.score }
+    }
+
+    pub fn code(&self) -> u8{ //-- how the choice is written inside the hashes, 1 for up, 0 for down and 2 for abstain
+        if self.abstain { 2 } else { self.is_upvote as u8 }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoteRecord{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub seq: i64, //-- the order of the log, taken from the vote_log counter
+    pub event_id: ObjectId,
+    pub wallet_address: String,
+    pub action: VoteAction,
+    pub choice: Option<Choice>, //-- the ballot after this record, None on a retraction
+    pub previous: Option<Choice>, //-- the ballot before this record, None on a cast
+    pub at: i64,
+}
+
+impl VoteRecord{
+
+    pub fn delta(&self) -> Counters{ //-- what this record changes on the counters of its event
+        let after = self.choice.map(|c| Counters::of_choice(&c)).unwrap_or_default();
+        let before = self.previous.map(|c| Counters::of_choice(&c)).unwrap_or_default();
+        after.sub(&before)
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Projection{ //-- the result of folding the log of an event
+    pub counters: Counters,
+    pub records: u64,
+    pub casts: u64,
+    pub changes: u64,
+    pub retractions: u64,
+    pub last_vote_at: Option<i64>,
+}
+
+impl Projection{
+
+    pub fn apply(&mut self, record: &VoteRecord){
+        self.counters = self.counters.add(&record.delta());
+        self.records += 1;
+        match record.action{
+            VoteAction::Cast => self.casts += 1,
+            VoteAction::Change => self.changes += 1,
+            VoteAction::Retract => self.retractions += 1,
+        }
+        self.last_vote_at = Some(self.last_vote_at.unwrap_or(record.at).max(record.at));
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TallyDrift{
+    pub event_id: String,
+    pub stored: Counters, //-- the counters on the event
+    pub projected: Counters, //-- the counters rebuilt from the vote log
+    pub drift: Counters, //-- stored minus projected, all zero if they agree
+    pub drifted: bool,
+    pub repaired: bool, //-- true if the stored counters have been replaced by the projected ones
+    pub records: u64, //-- number of log records folded into the projection
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecomputeQuery{
+    pub repair: Option<bool>, //-- only reports the drift unless it's true
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TallyAtQuery{
+    pub at: Option<i64>, //-- unix seconds, defaults to now
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TallyAt{
+    pub proposal_id: String,
+    pub at: i64,
+    pub tally: Tally,
+    pub outcome: Outcome, //-- pending unless the proposal was already over at that time
+    pub casts: u64, //-- ballots cast up to that time, including the retracted ones
+    pub changes: u64,
+    pub retractions: u64,
+    pub last_vote_at: Option<i64>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TallySeriesQuery{
+    pub from: Option<i64>, //-- unix seconds, defaults to the creation of the proposal
+    pub to: Option<i64>, //-- unix seconds, defaults to its end or now if it's still open
+    pub bucket: Option<i64>, //-- seconds per point, it's widened if the range would have more than MAX_POINTS points
+}
+
+impl TallySeriesQuery{
+
+    pub const DEFAULT_BUCKET: i64 = 3600;
+    pub const MIN_BUCKET: i64 = 60;
+    pub const MAX_POINTS: i64 = 1000;
+
+    pub fn bucket(&self, from: i64, to: i64) -> i64{
+        let span = (to - from).max(0);
+        let fitting = (span + Self::MAX_POINTS - 1) / Self::MAX_POINTS; //-- the smallest bucket that keeps the points under the max
+        self.bucket.unwrap_or(Self::DEFAULT_BUCKET).max(Self::MIN_BUCKET).max(fitting)
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SeriesPoint{
+    pub at: i64, //-- end of the bucket, the counters include every record up to and including it
+    pub upvotes: i64,
+    pub downvotes: i64,
+    pub upvotes_weight: i64,
+    pub downvotes_weight: i64,
+    pub abstains: i64,
+    pub abstains_weight: i64,
+    pub delegated: Counters, //-- what the delegations in force at that time added on top, the headcounts are the delegators
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TallySeries{
+    pub proposal_id: String,
+    pub from: i64,
+    pub to: i64,
+    pub bucket: i64, //-- the bucket that was actually used
+    pub points: Vec<SeriesPoint>,
+}
@@ -0,0 +1,192 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use crate::contexts::hub::Activity;
+
+
+// NOTE - a webhook subscribes an url to some of the activity names (or all of them if events is empty), every matching activity becomes a delivery inside the webhook_deliveries collection which is the persistent retry queue
+// NOTE - the secret is only returned once when the webhook is created or its secret is rotated, receivers use it to check the X-Biter-Signature header
+
+
+
+pub const PING: &str = "ping"; //-- the test delivery sent by the ping endpoint, it's not an activity so nobody can subscribe to it
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Webhook{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>, //-- activity names, empty means every activity
+    pub description: Option<String>,
+    pub active: bool,
+    pub created_at: i64,
+}
+
+impl Webhook{
+
+    pub fn info(&self) -> WebhookInfo{
+        WebhookInfo{
+            _id: self._id.map(|id| id.to_hex()).unwrap_or_default(),
+            url: self.url.clone(),
+            events: self.events.clone(),
+            description: self.description.clone(),
+            active: self.active,
+            created_at: self.created_at,
+        }
+    }
+
+    pub fn subscribers_of(event: &str) -> Document{ //-- the filter of the active webhooks that want this activity
+        doc!{"active": true, "$or": [{"events": {"$size": 0}}, {"events": event}]}
+    }
+
+    pub fn new_secret() -> String{
+        format!("whsec_{}{}", uuid::Uuid::new_v4().to_simple(), uuid::Uuid::new_v4().to_simple())
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebhookInfo{ //-- what the admin api shows of a webhook, everything but the secret
+    pub _id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub description: Option<String>,
+    pub active: bool,
+    pub created_at: i64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebhookSecret{ //-- the answer of the creation and of the update, the secret is only there if it's new
+    pub webhook: WebhookInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebhookRequest{ //-- used for both the creation and the update, on update only the fields that are set are changed
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub active: Option<bool>,
+    pub rotate_secret: Option<bool>, //-- update only
+}
+
+impl WebhookRequest{
+
+    pub fn validate(&self) -> Result<(), String>{
+        if let Some(url) = self.url.as_ref(){
+            if !(url.starts_with("http://") || url.starts_with("https://")) || url.contains(char::is_whitespace){
+                return Err(format!("invalid webhook url `{}`, expected an http or https url", url));
+            }
+        }
+        if let Some(unknown) = self.events.iter().flatten().find(|e| !Activity::NAMES.contains(&e.as_str())){
+            return Err(format!("unknown event `{}`, expected one of {}", unknown, Activity::NAMES.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus{
+    Pending, //-- waiting for its next attempt
+    Delivered,
+    Dead, //-- ran out of attempts, it stays in the dead letters until an admin retries it
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeliveryAttempt{
+    pub at: i64,
+    pub status_code: Option<u16>, //-- None if the receiver couldn't be reached
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Delivery{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub webhook_id: ObjectId,
+    pub event: String,
+    pub body: String, // NOTE - kept as the exact bytes we sign so every retry sends the same payload
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+    pub created_at: i64,
+    pub delivered_at: Option<i64>,
+    pub log: Vec<DeliveryAttempt>,
+}
+
+impl Delivery{
+
+    pub fn new(webhook_id: ObjectId, event: &str, body: String, now: i64) -> Delivery{
+        Delivery{
+            _id: None, //-- mongodb fills it on insert
+            webhook_id,
+            event: event.to_string(),
+            body,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            created_at: now,
+            delivered_at: None,
+            log: vec![],
+        }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListDeliveriesQuery{
+    pub status: Option<DeliveryStatus>, //-- every status if it's not set
+    pub offset: Option<u64>,
+    pub limit: Option<i64>,
+}
+
+impl ListDeliveriesQuery{
+
+    pub const DEFAULT_LIMIT: i64 = 50;
+    pub const MAX_LIMIT: i64 = 200;
+
+    pub fn limit(&self) -> i64{
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u64{
+        self.offset.unwrap_or(0)
+    }
+
+    pub fn filter(&self, webhook_id: Option<ObjectId>) -> Document{
+        let mut filter = doc!{};
+        if let Some(webhook_id) = webhook_id{
+            filter.insert("webhook_id", webhook_id);
+        }
+        if let Some(status) = self.status{
+            filter.insert("status", mongodb::bson::to_bson(&status).unwrap());
+        }
+        filter
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeliveryList{
+    pub deliveries: Vec<Delivery>,
+    pub total: u64,
+    pub next_offset: Option<u64>,
+}
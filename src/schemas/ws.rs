@@ -0,0 +1,41 @@
+
+
+
+
+use serde::{Serialize, Deserialize};
+
+
+// NOTE - every text frame is a json message, clients send actions and get back acks, errors and the hub activities
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientMessage{
+    Subscribe{ proposals: Vec<String> },
+    Unsubscribe{ proposals: Vec<String> },
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage{
+    Subscribed{ proposals: Vec<String> },
+    Unsubscribed{ proposals: Vec<String> },
+    Error{ message: String },
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubscribeQuery{
+    pub proposals: Option<String>, //-- comma separated proposal ids to subscribe to right after the handshake
+}
+
+impl SubscribeQuery{
+
+    pub fn proposals(&self) -> Vec<String>{
+        self.proposals.as_deref().unwrap_or_default().split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+    }
+}
@@ -5,22 +5,19 @@
 pub mod db{
 
     
-    use std::{sync::Arc, env};
+    use std::sync::Arc;
     use crate::contexts as ctx;
     use log::{info, error};
     use uuid::Uuid;
+    use mongodb::{sync::Database, bson::{self, Document}, error::{ErrorKind, WriteFailure, WriteError}};
+    use crate::schemas;
 
 
 
-    pub async fn connection() -> Option<Arc<ctx::app::Storage>>{
+    pub async fn connection(config: &ctx::config::Config) -> Option<Arc<ctx::app::Storage>>{
 
-        let db_username = env::var("DB_USERNAME").expect("⚠️ no db username variable set");
-        let db_password = env::var("DB_PASSWORD").expect("⚠️ no db password variable set");
-        let db_host = env::var("DB_HOST").expect("⚠️ no db host variable set");
-        let db_port = env::var("DB_PORT").expect("⚠️ no db port variable set");
-        let db_engine = env::var("DB_ENGINE").expect("⚠️ no db engine variable set");
-        let db_addr = format!("{}://{}:{}", db_engine, db_host, db_port);
-        // let db_addr = format!("{}://{}:{}@{}:{}", db_engine, db_username, db_password, db_host, db_port); //------ UNCOMMENT THIS FOR PRODUCTION
+        let db_engine = config.db.engine.clone();
+        let db_addr = config.db.url(config.environment); //-- credentials are only put inside the url in prod
 
 
         let db = if db_engine.as_str() == "mongodb"{
@@ -30,7 +27,7 @@ pub mod db{
                     init_db.engine = Some(db_engine);
                     init_db.url = Some(db_addr);
                     info!("getting mongodb instance - {}", chrono::Local::now().naive_local());
-                    let mongodb_instance = init_db.GetMongoDbInstance().await; //-- the first argument of this method must be &self in order to have the init_db after calling this method cause self as the first argument will move the instance after calling the related method
+                    let mongodb_instance = init_db.get_mongodb_instance().await; //-- the first argument of this method must be &self in order to have the init_db after calling this method cause self as the first argument will move the instance after calling the related method
                     Some( //-- putting the Arc-ed db inside the Option
                         Arc::new( //-- cloning app_storage to move it between threads
                             ctx::app::Storage{ //-- defining db context 
@@ -60,5 +57,1456 @@ pub mod db{
 
     }
 
+
+
+    pub fn is_duplicate_key(e: &mongodb::error::Error) -> bool{ //-- 11000 is the mongodb code for a unique index violation
+        matches!(e.kind.as_ref(), ErrorKind::Write(WriteFailure::WriteError(WriteError{code: 11000, ..})))
+    }
+
+
+
+    pub fn list_events(db: &Database, query: &schemas::event::ListEventsQuery, cursor: Option<&schemas::event::EventCursor>) -> mongodb::error::Result<schemas::event::AvailableEvents>{ //-- one page of events plus the total and the cursor of the next page
+        let events = db.collection::<Document>("events"); //-- selecting events collection as raw documents cause the pipeline adds and removes fields before we deserialize them into the EventInfo struct
+        let limit = query.limit() as usize;
+        let total = events.count_documents(query.filter(), None)?;
+
+        // ---------------------------------------
+        // NOTE - uncomment this for async mongodb
+        // ---------------------------------------
+        // while let Some(event) = cursor.try_next().await.unwrap(){ //-- calling try_next() method on cursor needs the cursor to be mutable - reading while awaiting on try_next() method doesn't return None
+        //     available_events.events.push(event);
+        // }
+
+        let mut page = vec![];
+        for event in events.aggregate(query.pipeline(cursor), None)?{
+            page.push(event?);
+        }
+        let next_cursor = if page.len() > limit{ //-- we fetched one extra event so there is a next page
+            page.truncate(limit);
+            page.last().and_then(|event| query.cursor_of(event)).map(|c| c.encode())
+        } else{
+            None
+        };
+        Ok(
+            schemas::event::AvailableEvents{
+                events: page.into_iter().map(|event| bson::from_document(event).unwrap()).collect(),
+                total,
+                next_cursor,
+            }
+        )
+    }
+
+
+
+
+    pub fn search_events(db: &Database, query: &schemas::event::SearchQuery, text: &str) -> mongodb::error::Result<schemas::event::SearchResults>{ //-- one page of the events matching the text, the most relevant first
+        let events = db.collection::<Document>("events");
+        let facet = match events.aggregate(query.pipeline(text), None)?.next(){ //-- $facet always outputs a single document
+            Some(facet) => facet?,
+            None => Document::new(),
+        };
+        let total = facet.get_array("total").ok()
+                            .and_then(|t| t.first())
+                            .and_then(|t| t.as_document())
+                            .and_then(|t| t.get_i32("count").ok())
+                            .unwrap_or(0) as u64;
+        let mut results = vec![];
+        for hit in facet.get_array("page").cloned().unwrap_or_default(){
+            let mut hit = hit.as_document().cloned().unwrap_or_default();
+            let relevance = hit.remove("relevance").and_then(|r| r.as_f64()).unwrap_or(0.0);
+            results.push(schemas::event::SearchHit{
+                event: bson::from_document(hit).unwrap(),
+                relevance,
+            });
+        }
+        let end = query.offset() + results.len() as u64;
+        Ok(
+            schemas::event::SearchResults{
+                results,
+                total,
+                next_offset: if end < total { Some(end) } else { None },
+            }
+        )
+    }
+
+}
+
+
+
+
+pub mod events{
+
+
+    use std::time::Duration;
+    use chrono::Utc;
+    use log::{info, error};
+    use mongodb::{sync::Database, bson::{self, doc, oid::ObjectId, Document}, options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument}};
+    use crate::contexts as ctx;
+    use crate::schemas;
+
+
+
+    pub const OWNED: &[&str] = &["ballots", "vote_log", "commitments", "encrypted_ballots", "decryption_shares", "stakes", "stake_log", "revisions", "comments"]; //-- the collections whose documents belong to a single event through their event_id, add any new one here
+
+
+
+    pub fn purge(db: &Database, event_id: ObjectId) -> mongodb::error::Result<()>{ //-- deletes what a deleted event leaves behind in the other collections
+        let options = FindOptions::builder().projection(doc!{"_id": 1}).build();
+        let comment_ids = db.collection::<Document>("comments").find(doc!{"event_id": event_id}, options)?
+                            .filter_map(|comment| comment.map(|c| c.get_object_id("_id").ok()).transpose())
+                            .collect::<mongodb::error::Result<Vec<ObjectId>>>()?;
+        for name in ["comment_edits", "comment_reactions"]{ //-- these belong to a comment, so they go before the comments themselves
+            db.collection::<Document>(name).delete_many(doc!{"comment_id": {"$in": &comment_ids}}, None)?;
+        }
+        for name in OWNED{
+            db.collection::<Document>(name).delete_many(doc!{"event_id": event_id}, None)?;
+        }
+        db.collection::<Document>("attestations").delete_one(doc!{"_id": event_id}, None)?; //-- keyed by the event id itself
+        Ok(())
+    }
+
+
+
+    pub fn close(db: &Database, hub: &ctx::hub::Hub, config: &ctx::config::Config, event_id: ObjectId) -> mongodb::error::Result<Option<schemas::event::EventInfo>>{ //-- returns None if the event doesn't exist or is already closed
+        let events = db.collection::<schemas::event::EventInfo>("events");
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        let mut closed = events.find_one_and_update(doc!{"_id": event_id, "is_expired": false}, doc!{"$set": {"is_expired": true, "closed_at": Utc::now().timestamp()}}, options)?; //-- the is_expired filter makes sure only one caller gets to close it
+        if let Some(event) = closed.as_mut(){
+            match super::audit::seal(db, event_id){ //-- the ballots can't change anymore so this is the root we publish with the result
+                Ok((root, leaves)) => {
+                    event.merkle_root = Some(root);
+                    event.merkle_leaves = Some(leaves);
+                },
+                Err(e) => error!("can't build the ballots merkle tree of event {} {} - {}", event_id, e, chrono::Local::now().naive_local()),
+            }
+            if event.voting_mode() == schemas::event::VotingMode::CommitReveal{
+                match super::commitments::report(db, event_id){ //-- the commitments that were never revealed aren't counted but everyone gets to know about them
+                    Ok(report) => {
+                        if report.unrevealed > 0{
+                            info!("event {} closed with {} of {} commitments unrevealed - {}", event_id, report.unrevealed, report.committed, chrono::Local::now().naive_local());
+                        }
+                        event.reveal_report = Some(report);
+                    },
+                    Err(e) => error!("can't report the commitments of event {} {} - {}", event_id, e, chrono::Local::now().naive_local()),
+                }
+            }
+            if event.voting_mode() == schemas::event::VotingMode::Conviction{ //-- no ballots to count, it passed if its conviction crossed the threshold before the close
+                if let Err(e) = super::conviction::release(db, event_id){
+                    error!("can't release the stakes of event {} {} - {}", event_id, e, chrono::Local::now().naive_local());
+                }
+                match super::conviction::freeze(db, config, event){
+                    Ok(state) => event.conviction = Some(state),
+                    Err(e) => error!("can't compute the conviction of event {} {} - {}", event_id, e, chrono::Local::now().naive_local()),
+                }
+                let passed = event.conviction.as_ref().and_then(|c| c.passed_at).is_some();
+                emit(db, hub, ctx::hub::Activity::ProposalClosed{ proposal_id: event_id.to_hex() });
+                emit(db, hub, ctx::hub::Activity::Outcome{
+                    proposal_id: event_id.to_hex(),
+                    tally: schemas::event::Tally::new(event, 0),
+                    outcome: if passed { schemas::event::Outcome::Passed } else { schemas::event::Outcome::Rejected },
+                    merkle_root: None,
+                    reveal_report: None,
+                    conviction: event.conviction.clone(),
+                });
+                return Ok(closed);
+            }
+            if event.voting_mode() == schemas::event::VotingMode::Encrypted{ //-- the result is only known once the trustees decrypt it, the outcome and the attestation come from encryption::finalize() then
+                match super::encryption::freeze(db, event_id){
+                    Ok(tally) => event.encrypted_tally = Some(tally),
+                    Err(e) => error!("can't add up the encrypted ballots of event {} {} - {}", event_id, e, chrono::Local::now().naive_local()),
+                }
+                emit(db, hub, ctx::hub::Activity::ProposalClosed{ proposal_id: event_id.to_hex() });
+                return Ok(closed);
+            }
+            match super::delegation::freeze(db, event){ //-- the delegations can still change after the close, the result can't
+                Ok(resolution) => event.delegated = Some(resolution.counters),
+                Err(e) => error!("can't resolve the delegations of event {} {} - {}", event_id, e, chrono::Local::now().naive_local()),
+            }
+            let tally = schemas::event::Tally::new(event, config.event.quorum);
+            if let Err(e) = super::attestation::attest(db, config, event, &tally){ //-- an admin can still attest it later
+                error!("can't attest the result of event {} {} - {}", event_id, e, chrono::Local::now().naive_local());
+            }
+            emit(db, hub, ctx::hub::Activity::ProposalClosed{ proposal_id: event_id.to_hex() });
+            emit(db, hub, ctx::hub::Activity::Outcome{
+                proposal_id: event_id.to_hex(),
+                outcome: tally.outcome(true),
+                tally,
+                merkle_root: event.merkle_root.clone(),
+                reveal_report: event.reveal_report.clone(),
+                conviction: None,
+            });
+        }
+        Ok(closed)
+    }
+
+
+
+    pub fn extend(db: &Database, hub: &ctx::hub::Hub, event: &schemas::event::EventInfo, wallet_address: &str, at: i64, leader_before: schemas::event::Outcome, leader_after: schemas::event::Outcome) -> mongodb::error::Result<Option<schemas::event::Extension>>{ //-- applies the anti-sniping rule of the event to a ballot that flipped the lead, None if the rule doesn't apply or it's out of extensions
+        let (rule, expire_at, event_id) = match (event.anti_sniping, event.expire_at, event._id){
+            (Some(rule), Some(expire_at), Some(event_id)) if rule.is_closing(at, expire_at) => (rule, expire_at, event_id),
+            _ => return Ok(None),
+        };
+        let extension = schemas::event::Extension{
+            number: event.extensions.as_ref().map(|e| e.len()).unwrap_or(0) as u32 + 1,
+            wallet_address: wallet_address.to_string(),
+            leader_before,
+            leader_after,
+            previous_expire_at: expire_at,
+            expire_at: expire_at + rule.extension_minutes as i64 * 60,
+            at,
+        };
+        if extension.number > rule.max_extensions{
+            return Ok(None);
+        }
+        let filter = doc!{ //-- the expire_at we read acts as a version, two ballots flipping the lead at once only extend it once and the sweep can't close it in between
+            "_id": event_id,
+            "is_expired": false,
+            "expire_at": expire_at,
+            format!("extensions.{}", rule.max_extensions - 1): {"$exists": false},
+        };
+        let update = doc!{
+            "$set": {"expire_at": extension.expire_at},
+            "$push": {"extensions": bson::to_bson(&extension).unwrap()},
+        };
+        if db.collection::<Document>("events").update_one(filter, update, None)?.modified_count == 0{
+            return Ok(None);
+        }
+        info!("event {} extended to {} by the ballot of {} - {}", event_id, extension.expire_at, wallet_address, chrono::Local::now().naive_local());
+        emit(db, hub, ctx::hub::Activity::DeadlineExtended{ proposal_id: event_id.to_hex(), extension: extension.clone() });
+        Ok(Some(extension))
+    }
+
+
+
+    pub fn emit(db: &Database, hub: &ctx::hub::Hub, activity: ctx::hub::Activity){ //-- persists the activity under the next sequence number then pushes it to the live subscribers
+        let envelope = ctx::hub::Envelope{
+            seq: None,
+            at: Utc::now().timestamp(),
+            activity,
+        };
+        let persisted = next_seq(db, "activities").and_then(|seq| {
+            let envelope = ctx::hub::Envelope{ seq: Some(seq), ..envelope.clone() };
+            db.collection::<ctx::hub::Envelope>("activities").insert_one(&envelope, None).map(|_| envelope)
+        });
+        let envelope = match persisted{
+            Ok(envelope) => envelope,
+            Err(e) => { //-- live subscribers still get it, only resuming past it won't be possible
+                error!("can't persist the {} activity {} - {}", envelope.activity.name(), e, chrono::Local::now().naive_local());
+                envelope
+            }
+        };
+        if let Err(e) = super::webhooks::enqueue(db, envelope.activity.name(), &serde_json::to_string(&envelope).unwrap()){
+            error!("can't queue the {} webhooks {} - {}", envelope.activity.name(), e, chrono::Local::now().naive_local());
+        }
+        hub.publish(envelope);
+    }
+
+
+
+    pub fn next_seq(db: &Database, name: &str) -> mongodb::error::Result<i64>{ //-- atomic counter kept inside the counters collection
+        let options = FindOneAndUpdateOptions::builder().upsert(true).return_document(ReturnDocument::After).build();
+        let counter = db.collection::<Document>("counters").find_one_and_update(doc!{"_id": name}, doc!{"$inc": {"seq": 1_i64}}, options)?;
+        Ok(counter.and_then(|c| c.get_i64("seq").ok()).unwrap_or(1))
+    }
+
+
+
+    pub fn replay(db: &Database, after: i64, proposals: Option<&[String]>, limit: i64) -> mongodb::error::Result<Vec<ctx::hub::Envelope>>{ //-- the persisted activities after the given sequence number, oldest first
+        let mut filter = doc!{"seq": {"$gt": after}};
+        if let Some(proposals) = proposals{
+            filter.insert("proposal_id", doc!{"$in": proposals});
+        }
+        let options = FindOptions::builder().sort(doc!{"seq": 1}).limit(limit).build();
+        let mut envelopes = vec![];
+        for envelope in db.collection::<Document>("activities").find(filter, options)?{
+            match bson::from_document(envelope?){
+                Ok(envelope) => envelopes.push(envelope),
+                Err(e) => error!("skipping an activity we can't read {} - {}", e, chrono::Local::now().naive_local()),
+            }
+        }
+        Ok(envelopes)
+    }
+
+
+
+    pub async fn sweep(db: Database, hub: ctx::hub::Hub, config: ctx::config::Config){ //-- closes the events that passed their expire_at, runs for the whole life of the server
+        let mut ticker = actix_web::rt::time::interval(Duration::from_secs(config.event.sweep_interval));
+        loop{
+            ticker.tick().await;
+            let options = FindOptions::builder().projection(doc!{"_id": 1}).build();
+            let due = match db.collection::<Document>("events").find(doc!{"is_expired": false, "expire_at": {"$lte": Utc::now().timestamp()}}, options){
+                Ok(cursor) => cursor.filter_map(|event| event.ok()?.get_object_id("_id").ok()).collect::<Vec<ObjectId>>(),
+                Err(e) => {
+                    error!("can't fetch the expired events {} - {}", e, chrono::Local::now().naive_local());
+                    continue;
+                }
+            };
+            for event_id in due{
+                match close(&db, &hub, &config, event_id){
+                    Ok(Some(_)) => info!("event {} expired - {}", event_id, chrono::Local::now().naive_local()),
+                    Ok(None) => {}, //-- someone else closed it in between
+                    Err(e) => error!("can't expire event {} {} - {}", event_id, e, chrono::Local::now().naive_local()),
+                }
+            }
+        }
+    }
+
+}
+
+
+
+
+
+
+
+
+
+pub mod votes{
+
+
+    use mongodb::{sync::Database, bson::{doc, oid::ObjectId}, options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument}};
+    use crate::schemas::{event::{Counters, EventInfo}, vote_log::{Choice, Projection, SeriesPoint, TallyDrift, VoteAction, VoteRecord}};
+
+
+
+    pub fn append(db: &Database, event_id: ObjectId, wallet_address: &str, action: VoteAction, choice: Option<Choice>, previous: Option<Choice>, at: i64) -> mongodb::error::Result<VoteRecord>{ //-- the only way anything is written into the vote log
+        let record = VoteRecord{
+            _id: None, //-- mongodb fills it on insert
+            seq: super::events::next_seq(db, "vote_log")?,
+            event_id,
+            wallet_address: wallet_address.to_string(),
+            action,
+            choice,
+            previous,
+            at,
+        };
+        db.collection::<VoteRecord>("vote_log").insert_one(&record, None)?;
+        Ok(record)
+    }
+
+
+
+    pub fn apply(db: &Database, record: &VoteRecord) -> mongodb::error::Result<Option<EventInfo>>{ //-- moves the cached counters of the event by the delta of the record, returns the updated event
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        db.collection::<EventInfo>("events").find_one_and_update(doc!{"_id": record.event_id}, doc!{"$inc": record.delta().as_document()}, options) //-- $inc is atomic so concurrent votes never overwrite each other's counters
+    }
+
+
+
+    pub fn project(db: &Database, event_id: ObjectId, until: Option<i64>) -> mongodb::error::Result<Projection>{ //-- folds the log of the event, only the records up to `until` if it's set
+        let mut filter = doc!{"event_id": event_id};
+        if let Some(until) = until{
+            filter.insert("at", doc!{"$lte": until});
+        }
+        let options = FindOptions::builder().sort(doc!{"seq": 1}).build();
+        let mut projection = Projection::default();
+        for record in db.collection::<VoteRecord>("vote_log").find(filter, options)?{
+            projection.apply(&record?);
+        }
+        Ok(projection)
+    }
+
+
+
+    pub fn series(db: &Database, event_id: ObjectId, from: i64, to: i64, bucket: i64) -> mongodb::error::Result<Vec<SeriesPoint>>{ //-- the cumulative counters at the end of every bucket between from and to
+        let options = FindOptions::builder().sort(doc!{"at": 1, "seq": 1}).build();
+        let mut records = db.collection::<VoteRecord>("vote_log").find(doc!{"event_id": event_id, "at": {"$lte": to}}, options)?.peekable();
+        let mut counters = Counters::default();
+        let mut points = vec![];
+        let mut end = from;
+        while end < to{
+            end = (end + bucket).min(to);
+            while let Some(record) = records.next_if(|r| r.as_ref().map(|r| r.at <= end).unwrap_or(true)){ //-- errors are let through to be returned
+                counters = counters.add(&record?.delta());
+            }
+            points.push(SeriesPoint{
+                at: end,
+                upvotes: counters.upvotes,
+                downvotes: counters.downvotes,
+                upvotes_weight: counters.upvotes_weight,
+                downvotes_weight: counters.downvotes_weight,
+                abstains: counters.abstains,
+                abstains_weight: counters.abstains_weight,
+                delegated: Counters::default(), //-- see delegation::series()
+            });
+        }
+        Ok(points)
+    }
+
+
+
+    pub fn recompute(db: &Database, event_id: ObjectId, repair: bool) -> mongodb::error::Result<Option<TallyDrift>>{ //-- None if the event doesn't exist
+        let events = db.collection::<EventInfo>("events");
+        let event = match events.find_one(doc!{"_id": event_id}, None)?{
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        let stored = Counters::of_event(&event);
+        let Projection{counters: projected, records, ..} = project(db, event_id, None)?;
+        let drift = stored.sub(&projected); // NOTE - a vote landing between the two reads shows up as a drift of that single vote, run it again to be sure before repairing a busy proposal
+        let drifted = !drift.is_zero();
+        if drifted && repair{
+            let correction = Counters::default().sub(&drift);
+            events.update_one(doc!{"_id": event_id}, doc!{"$inc": correction.as_document()}, None)?; //-- an $inc instead of a $set so a vote counted in the meantime isn't lost
+        }
+        Ok(Some(TallyDrift{
+            event_id: event_id.to_hex(),
+            stored,
+            projected,
+            drift,
+            drifted,
+            repaired: drifted && repair,
+            records,
+        }))
+    }
+
+}
+
+
+
+
+
+
+
+
+
+pub mod merkle;
+pub mod elgamal;
+
+pub mod audit{
+
+
+    use mongodb::{sync::Database, bson::{doc, oid::ObjectId}};
+    use crate::schemas::ballot::Ballot;
+    use super::merkle::{self, InclusionProof, Tree};
+
+
+
+    pub fn tree(db: &Database, event_id: ObjectId) -> mongodb::error::Result<(Tree, Vec<Ballot>)>{ //-- the merkle tree over the current ballots of the event with the ballots in leaf order
+        let mut ballots = db.collection::<Ballot>("ballots").find(doc!{"event_id": event_id}, None)?.collect::<mongodb::error::Result<Vec<Ballot>>>()?;
+        ballots.sort_by(|a, b| a.wallet_address.as_bytes().cmp(b.wallet_address.as_bytes())); //-- the canonical order, sorted here so it never depends on a collation
+        let leaves = ballots.iter().map(|b| merkle::leaf(&event_id.to_hex(), &b.wallet_address, b.choice().code(), b.score, b.voted_at)).collect();
+        Ok((Tree::new(leaves), ballots))
+    }
+
+
+
+    pub fn seal(db: &Database, event_id: ObjectId) -> mongodb::error::Result<(String, u64)>{ //-- builds the tree and stores its root on the event
+        let (tree, _) = tree(db, event_id)?;
+        let root = hex::encode(tree.root());
+        let leaves = tree.leaves() as u64;
+        db.collection::<mongodb::bson::Document>("events").update_one(doc!{"_id": event_id}, doc!{"$set": {"merkle_root": &root, "merkle_leaves": leaves as i64}}, None)?;
+        Ok((root, leaves))
+    }
+
+
+
+    pub fn prove(db: &Database, event_id: ObjectId, wallet_address: &str) -> mongodb::error::Result<(String, Option<InclusionProof>)>{ //-- the root rebuilt from the current ballots and the proof of the wallet, None if it has no ballot
+        let (tree, ballots) = tree(db, event_id)?;
+        let root = hex::encode(tree.root());
+        let proof = ballots.iter().position(|b| b.wallet_address == wallet_address).map(|index| {
+            let ballot = &ballots[index];
+            InclusionProof{
+                event_id: event_id.to_hex(),
+                wallet_address: ballot.wallet_address.clone(),
+                is_upvote: ballot.is_upvote,
+                abstain: ballot.abstain,
+                score: ballot.score,
+                voted_at: ballot.voted_at,
+                leaf: hex::encode(merkle::leaf(&event_id.to_hex(), &ballot.wallet_address, ballot.choice().code(), ballot.score, ballot.voted_at)),
+                index: index as u64,
+                leaves: tree.leaves() as u64,
+                path: tree.proof(index),
+                root: root.clone(),
+            }
+        });
+        Ok((root, proof))
+    }
+
+}
+
+
+
+
+
+
+
+
+pub mod commitments{
+
+
+    use mongodb::{sync::Database, bson::{doc, oid::ObjectId, Bson, Document}};
+    use crate::schemas::commitment::{Commitment, RevealReport};
+
+
+
+    pub fn report(db: &Database, event_id: ObjectId) -> mongodb::error::Result<RevealReport>{ //-- counts the commitments of an event and stores the report on it
+        let commitments = db.collection::<Commitment>("commitments");
+        let committed = commitments.count_documents(doc!{"event_id": event_id}, None)?;
+        let revealed = commitments.count_documents(doc!{"event_id": event_id, "revealed_at": {"$ne": Bson::Null}}, None)?;
+        let report = RevealReport{ committed, revealed, unrevealed: committed.saturating_sub(revealed) };
+        db.collection::<Document>("events").update_one(doc!{"_id": event_id}, doc!{"$set": {"reveal_report": {"committed": committed as i64, "revealed": revealed as i64, "unrevealed": report.unrevealed as i64}}}, None)?;
+        Ok(report)
+    }
+
+}
+
+
+
+
+
+
+
+
+pub mod revisions{
+
+
+    use mongodb::{sync::Database, bson::{doc, oid::ObjectId}, options::FindOptions};
+    use crate::schemas::{event::EventInfo, revision::Revision};
+
+
+
+    pub fn original(event: &EventInfo) -> Revision{ //-- revision 1 rebuilt from an event that was never edited
+        Revision{
+            _id: None, //-- mongodb fills it on insert
+            event_id: event._id.unwrap(),
+            number: 1,
+            title: event.title.clone(),
+            content: event.content.clone(),
+            tags: event.tags.clone().unwrap_or_default(),
+            editor: event.creator_wallet_address.clone(),
+            note: None,
+            created_at: event.created_at.unwrap_or(0),
+        }
+    }
+
+
+
+    pub fn history(db: &Database, event: &EventInfo) -> mongodb::error::Result<Vec<Revision>>{ //-- every revision of the event oldest first, the events created before the edits existed only have their original
+        let options = FindOptions::builder().sort(doc!{"number": 1}).build();
+        let revisions = db.collection::<Revision>("revisions").find(doc!{"event_id": event._id}, options)?.collect::<mongodb::error::Result<Vec<Revision>>>()?;
+        Ok(if revisions.is_empty() { vec![original(event)] } else { revisions })
+    }
+
+
+
+    pub fn ensure_original(db: &Database, event: &EventInfo) -> mongodb::error::Result<()>{ //-- stores revision 1 before the first edit if add_event couldn't
+        let revisions = db.collection::<Revision>("revisions");
+        if revisions.find_one(doc!{"event_id": event._id, "number": 1}, None)?.is_none(){
+            match revisions.insert_one(original(event), None){
+                Err(e) if !super::db::is_duplicate_key(&e) => return Err(e),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+
+
+    pub fn discard(db: &Database, event_id: ObjectId, number: u32) -> mongodb::error::Result<()>{ //-- drops a revision whose edit didn't make it onto the event
+        db.collection::<Revision>("revisions").delete_one(doc!{"event_id": event_id, "number": number}, None)?;
+        Ok(())
+    }
+
+}
+
+
+
+
+
+
+
+pub mod taxonomy{
+
+
+    use mongodb::{sync::Database, bson::doc};
+    use crate::constants::*;
+    use crate::schemas::taxonomy::{Term, TermKind};
+
+
+
+    pub fn check(db: &Database, category: Option<&str>, tags: &[String]) -> mongodb::error::Result<Option<&'static str>>{ //-- why a proposal can't carry this category or these tags, None if they're all in the taxonomy
+        let terms = db.collection::<Term>("taxonomy");
+        if let Some(category) = category{
+            if terms.find_one(doc!{"kind": "category", "slug": category}, None)?.is_none(){
+                return Ok(Some(UNKNOWN_CATEGORY));
+            }
+        }
+        if !tags.is_empty(){
+            let known = terms.count_documents(doc!{"kind": "tag", "slug": {"$in": tags}}, None)?;
+            if known != tags.len() as u64{ //-- the tags are deduplicated and the slugs are unique per kind
+                return Ok(Some(UNKNOWN_TAG));
+            }
+        }
+        Ok(None)
+    }
+
+
+
+    pub fn in_use(db: &Database, term: &Term) -> mongodb::error::Result<bool>{ //-- true if some proposal still carries the term
+        let field = term.kind.field();
+        let events = db.collection::<mongodb::bson::Document>("events").count_documents(doc!{field: &term.slug}, None)?;
+        Ok(events > 0 || (term.kind == TermKind::Tag && db.collection::<mongodb::bson::Document>("delegations").count_documents(doc!{"tag": &term.slug, "active": true}, None)? > 0))
+    }
+
+}
+
+
+
+
+
+
+
+pub mod delegation{
+
+
+    use std::collections::{HashMap, HashSet};
+    use mongodb::{sync::Database, bson::{doc, oid::ObjectId, Document}, options::FindOptions};
+    use crate::schemas::{ballot::Ballot, delegation::{self, Delegation, Edges, Resolution}, event::{EventInfo, VotingMode}, vote_log::{Choice, SeriesPoint, VoteRecord}};
+
+
+
+    pub fn active(db: &Database, tags: Option<&[String]>) -> mongodb::error::Result<Vec<Delegation>>{ //-- the active delegations that can apply to a proposal with these tags, all of them if None
+        let mut filter = doc!{"active": true};
+        if let Some(tags) = tags{
+            filter.insert("$or", vec![doc!{"tag": null}, doc!{"tag": {"$in": tags}}]);
+        }
+        db.collection::<Delegation>("delegations").find(filter, None)?.collect()
+    }
+
+
+
+    pub fn edges_of(db: &Database, event: &EventInfo) -> mongodb::error::Result<Edges>{
+        let tags = event.tags.clone().unwrap_or_default();
+        Ok(delegation::edges(&active(db, Some(&tags))?, &tags))
+    }
+
+
+
+    fn ballots_of(db: &Database, event_id: ObjectId, edges: &Edges) -> mongodb::error::Result<HashMap<String, Choice>>{ //-- the ballots of the wallets taking part in the delegations, the others don't matter
+        let wallets = edges.iter().flat_map(|(delegator, (delegate, _))| [delegator.clone(), delegate.clone()]).collect::<HashSet<String>>().into_iter().collect::<Vec<String>>();
+        let mut ballots = HashMap::new();
+        for ballot in db.collection::<Ballot>("ballots").find(doc!{"event_id": event_id, "wallet_address": {"$in": wallets}}, None)?{
+            let ballot = ballot?;
+            ballots.insert(ballot.wallet_address.clone(), ballot.choice());
+        }
+        Ok(ballots)
+    }
+
+
+
+    pub fn resolve(db: &Database, event: &EventInfo) -> mongodb::error::Result<Resolution>{ //-- where the delegated power of the event goes with the current ballots and delegations
+        let event_id = match event._id{
+            Some(event_id) if event.voting_mode() != VotingMode::Encrypted => event_id, //-- nobody knows what an encrypted ballot chose so the power can't follow it
+            _ => return Ok(Resolution::default()),
+        };
+        let edges = edges_of(db, event)?;
+        if edges.is_empty(){
+            return Ok(Resolution::default());
+        }
+        Ok(Resolution::resolve(&edges, &ballots_of(db, event_id, &edges)?))
+    }
+
+
+
+    pub fn around(db: &Database, event: &EventInfo, record: &VoteRecord) -> mongodb::error::Result<(Resolution, Resolution)>{ //-- the resolution before and after the record, the record is already applied to the ballots
+        let edges = edges_of(db, event)?;
+        if edges.is_empty(){
+            return Ok((Resolution::default(), Resolution::default()));
+        }
+        let after = ballots_of(db, record.event_id, &edges)?;
+        let mut before = after.clone();
+        match record.previous{
+            Some(previous) => { before.insert(record.wallet_address.clone(), previous); },
+            None => { before.remove(&record.wallet_address); },
+        }
+        Ok((Resolution::resolve(&edges, &before), Resolution::resolve(&edges, &after)))
+    }
+
+
+
+    fn history(db: &Database, event: &EventInfo, until: i64) -> mongodb::error::Result<Option<(Vec<Delegation>, Vec<VoteRecord>)>>{ //-- every delegation that could apply to the event up to `until`, revoked ones included, and its vote log up to then, None if no delegation can apply
+        let event_id = match event._id{
+            Some(event_id) if event.voting_mode() != VotingMode::Encrypted => event_id,
+            _ => return Ok(None),
+        };
+        let tags = event.tags.clone().unwrap_or_default();
+        let filter = doc!{"created_at": {"$lte": until}, "$or": [{"tag": null}, {"tag": {"$in": &tags}}]};
+        let delegations = db.collection::<Delegation>("delegations").find(filter, None)?.collect::<mongodb::error::Result<Vec<Delegation>>>()?;
+        if delegations.is_empty(){
+            return Ok(None);
+        }
+        let options = FindOptions::builder().sort(doc!{"seq": 1}).build();
+        let records = db.collection::<VoteRecord>("vote_log").find(doc!{"event_id": event_id, "at": {"$lte": until}}, options)?.collect::<mongodb::error::Result<Vec<VoteRecord>>>()?;
+        Ok(Some((delegations, records)))
+    }
+
+
+
+    fn resolve_with(delegations: &[Delegation], records: &[VoteRecord], tags: &[String], at: i64) -> Resolution{ //-- the delegations in force at `at` applied to the ballots folded from the log up to then
+        let in_force = delegations.iter()
+                            .filter(|d| d.created_at <= at && d.revoked_at.map(|r| r > at).unwrap_or(true))
+                            .map(|d| Delegation{ active: true, ..d.clone() }) //-- revoked since but active back then
+                            .collect::<Vec<Delegation>>();
+        let edges = delegation::edges(&in_force, tags);
+        if edges.is_empty(){
+            return Resolution::default();
+        }
+        let mut ballots = HashMap::new();
+        for record in records.iter().filter(|r| r.at <= at){
+            match record.choice{
+                Some(choice) => { ballots.insert(record.wallet_address.clone(), choice); },
+                None => { ballots.remove(&record.wallet_address); },
+            }
+        }
+        Resolution::resolve(&edges, &ballots)
+    }
+
+
+
+    pub fn resolve_at(db: &Database, event: &EventInfo, at: i64) -> mongodb::error::Result<Resolution>{ //-- where the delegated power of the event went at `at`, a closed event stops at its close like its frozen counters
+        let at = event.closed_at.map(|closed_at| closed_at.min(at)).unwrap_or(at);
+        let tags = event.tags.clone().unwrap_or_default();
+        Ok(match history(db, event, at)?{
+            Some((delegations, records)) => resolve_with(&delegations, &records, &tags, at),
+            None => Resolution::default(),
+        })
+    }
+
+
+
+    pub fn series(db: &Database, event: &EventInfo, points: &mut [SeriesPoint]) -> mongodb::error::Result<()>{ //-- fills what the delegations added at the end of every bucket
+        let until = match points.last(){
+            Some(point) => event.closed_at.map(|closed_at| closed_at.min(point.at)).unwrap_or(point.at),
+            None => return Ok(()),
+        };
+        let tags = event.tags.clone().unwrap_or_default();
+        if let Some((delegations, records)) = history(db, event, until)?{
+            for point in points.iter_mut(){
+                point.delegated = resolve_with(&delegations, &records, &tags, point.at.min(until)).counters;
+            }
+        }
+        Ok(())
+    }
+
+
+
+    pub fn refresh(db: &Database, event: &mut EventInfo) -> mongodb::error::Result<Option<Resolution>>{ //-- fills the delegated counters of an open event, a closed one keeps the frozen ones and gets None
+        if event.is_expired.unwrap_or(false){
+            return Ok(None);
+        }
+        let resolution = resolve(db, event)?;
+        event.delegated = Some(resolution.counters);
+        Ok(Some(resolution))
+    }
+
+
+
+    pub fn freeze(db: &Database, event: &EventInfo) -> mongodb::error::Result<Resolution>{ //-- stores the delegated counters on the event and the power each voter received on its ballot
+        let resolution = resolve(db, event)?;
+        if let Some(event_id) = event._id{
+            db.collection::<Document>("events").update_one(doc!{"_id": event_id}, doc!{"$set": {"delegated": resolution.counters.as_document()}}, None)?;
+            for (wallet_address, power) in &resolution.voters{
+                db.collection::<Document>("ballots").update_one(
+                    doc!{"event_id": event_id, "wallet_address": wallet_address},
+                    doc!{"$set": {"delegated": {"weight": power.weight as i64, "delegators": power.delegators as i64}}},
+                    None,
+                )?;
+            }
+        }
+        Ok(resolution)
+    }
+
+
+
+    pub fn would_loop(db: &Database, candidate: &Delegation) -> mongodb::error::Result<bool>{ //-- true if adding the delegation closes a loop on some proposal
+        let mut delegations = active(db, None)?;
+        delegations.retain(|d| !(d.delegator == candidate.delegator && d.tag == candidate.tag)); //-- the one it replaces
+        delegations.push(candidate.clone());
+        let scopes = match candidate.tag.as_ref(){
+            Some(tag) => vec![vec![tag.clone()]],
+            None => { //-- a global delegation applies on every proposal where the delegator has no tagged one
+                let mut scopes = vec![vec![]];
+                let tags = delegations.iter().filter_map(|d| d.tag.clone()).collect::<HashSet<String>>();
+                scopes.extend(tags.into_iter().map(|tag| vec![tag]));
+                scopes
+            },
+        };
+        Ok(scopes.iter().any(|tags| delegation::loops_back(&delegation::edges(&delegations, tags), &candidate.delegator)))
+    }
+
+
+
+    #[cfg(test)]
+    mod tests{
+
+        use super::*;
+        use crate::schemas::{event::Counters, vote_log::VoteAction};
+
+        fn delegation(delegator: &str, delegate: &str, created_at: i64, revoked_at: Option<i64>) -> Delegation{
+            Delegation{
+                _id: None,
+                delegator: delegator.to_string(),
+                delegate: delegate.to_string(),
+                tag: None,
+                score: 5,
+                active: revoked_at.is_none(),
+                created_at,
+                revoked_at,
+            }
+        }
+
+        fn record(seq: i64, wallet_address: &str, choice: Option<bool>, at: i64) -> VoteRecord{ //-- an upvote, a downvote or a retraction
+            VoteRecord{
+                _id: None,
+                seq,
+                event_id: ObjectId::new(),
+                wallet_address: wallet_address.to_string(),
+                action: if choice.is_some() { VoteAction::Cast } else { VoteAction::Retract },
+                choice: choice.map(|is_upvote| Choice{ is_upvote, abstain: false, score: 1 }),
+                previous: None,
+                at,
+            }
+        }
+
+        #[test]
+        fn resolves_the_delegations_in_force_at_the_time(){
+            let delegations = vec![delegation("a", "v", 100, Some(300)), delegation("b", "v", 200, None)];
+            let records = vec![record(1, "v", Some(true), 50)];
+            let weight = |at| resolve_with(&delegations, &records, &[], at).counters.upvotes_weight;
+            assert_eq!(weight(99), 0);
+            assert_eq!(weight(100), 5);
+            assert_eq!(weight(250), 10); //-- a was revoked later but still in force
+            assert_eq!(weight(300), 5);
+        }
+
+        #[test]
+        fn follows_the_ballots_of_the_log_at_the_time(){
+            let delegations = vec![delegation("a", "v", 0, None)];
+            let records = vec![record(1, "v", Some(true), 100), record(2, "v", None, 200), record(3, "v", Some(false), 300), record(4, "a", Some(true), 400)];
+            let counters = |at| resolve_with(&delegations, &records, &[], at).counters;
+            assert_eq!(counters(99), Counters::default()); //-- the delegate hadn't voted yet
+            assert_eq!(counters(150).upvotes_weight, 5);
+            assert_eq!(counters(250), Counters::default()); //-- retracted
+            assert_eq!(counters(350).downvotes_weight, 5);
+            assert_eq!(counters(450), Counters::default()); //-- the delegator voted itself
+        }
+    }
+}
+
+
+
+
+
+
+
+
+pub mod conviction{
+
+
+    use std::time::Duration;
+    use chrono::Utc;
+    use log::{info, error};
+    use mongodb::{sync::Database, bson::{self, doc, oid::ObjectId, Document}, options::FindOptions};
+    use crate::contexts as ctx;
+    use crate::schemas::{conviction::{ConvictionPoint, ConvictionState, Fold, StakeRecord}, event::EventInfo};
+
+
+
+    pub fn append(db: &Database, event_id: ObjectId, wallet_address: &str, amount: u64, previous: u64, at: i64) -> mongodb::error::Result<StakeRecord>{ //-- the only way anything is written into the stake log
+        let record = StakeRecord{
+            _id: None, //-- mongodb fills it on insert
+            seq: super::events::next_seq(db, "stake_log")?,
+            event_id,
+            wallet_address: wallet_address.to_string(),
+            amount,
+            previous,
+            at,
+        };
+        db.collection::<StakeRecord>("stake_log").insert_one(&record, None)?;
+        Ok(record)
+    }
+
+
+
+    pub fn committed(db: &Database, wallet_address: &str, except: ObjectId) -> mongodb::error::Result<u64>{ //-- what the wallet has staked on the other open proposals
+        let pipeline = vec![
+            doc!{"$match": {"wallet_address": wallet_address, "released": {"$ne": true}, "event_id": {"$ne": except}}},
+            doc!{"$group": {"_id": null, "total": {"$sum": {"$toLong": "$amount"}}}},
+        ];
+        let total = db.collection::<Document>("stakes").aggregate(pipeline, None)?.next().transpose()?
+                        .and_then(|group| group.get_i64("total").ok())
+                        .unwrap_or(0);
+        Ok(total.max(0) as u64)
+    }
+
+
+
+    pub fn curve(db: &Database, config: &ctx::config::Config, event: &EventInfo, from: i64, to: i64, bucket: i64) -> mongodb::error::Result<(Vec<ConvictionPoint>, Fold)>{ //-- the conviction at the end of every bucket between from and to and the fold at `to`
+        let event_id = event._id.unwrap_or_default();
+        let threshold = config.conviction.threshold(event.requested_amount.unwrap_or(0));
+        let options = FindOptions::builder().sort(doc!{"seq": 1}).build();
+        let mut records = db.collection::<StakeRecord>("stake_log").find(doc!{"event_id": event_id, "at": {"$lte": to}}, options)?.peekable();
+        let mut fold = Fold::new(event.created_at.unwrap_or(from));
+        let mut points = vec![];
+        let mut end = from;
+        loop{
+            while let Some(record) = records.next_if(|r| r.as_ref().map(|r| r.at <= end).unwrap_or(true)){ //-- errors are let through to be returned
+                fold.apply(&record?, &config.conviction, threshold);
+            }
+            fold.advance(end, &config.conviction, threshold);
+            if end > from{ //-- from itself is only where the fold starts
+                points.push(ConvictionPoint{ at: end, staked: fold.staked, conviction: fold.conviction });
+            }
+            if end >= to{
+                break;
+            }
+            end = (end + bucket).min(to);
+        }
+        Ok((points, fold))
+    }
+
+
+
+    pub fn freeze(db: &Database, config: &ctx::config::Config, event: &EventInfo) -> mongodb::error::Result<ConvictionState>{ //-- folds the stake log up to now or the close and caches the state on the event
+        let until = event.closed_at.unwrap_or_else(|| Utc::now().timestamp());
+        let (_, fold) = curve(db, config, event, until, until, 1)?;
+        let state = fold.state(&config.conviction, config.conviction.threshold(event.requested_amount.unwrap_or(0)));
+        db.collection::<Document>("events").update_one(doc!{"_id": event._id}, doc!{"$set": {"conviction": bson::to_bson(&state).unwrap()}}, None)?;
+        Ok(state)
+    }
+
+
+
+    pub fn refresh(db: &Database, hub: &ctx::hub::Hub, config: &ctx::config::Config, event_id: ObjectId) -> mongodb::error::Result<Option<ConvictionState>>{ //-- the current state of an open conviction event, closes it once it passed, None for the other events
+        let event = match db.collection::<EventInfo>("events").find_one(doc!{"_id": event_id, "is_expired": false, "voting_mode": "conviction"}, None)?{
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        let state = freeze(db, config, &event)?;
+        if state.passed_at.is_some(){
+            super::events::close(db, hub, config, event_id)?; //-- the close folds it again and reports the outcome
+        }
+        Ok(Some(state))
+    }
+
+
+
+    pub fn release(db: &Database, event_id: ObjectId) -> mongodb::error::Result<()>{ //-- frees the stakes of a closed proposal so the wallets can stake them elsewhere, they stay listed
+        db.collection::<Document>("stakes").update_many(doc!{"event_id": event_id}, doc!{"$set": {"released": true}}, None)?;
+        Ok(())
+    }
+
+
+
+    pub async fn sweep(db: Database, hub: ctx::hub::Hub, config: ctx::config::Config){ //-- recomputes the open conviction proposals so they pass even if nobody stakes, runs for the whole life of the server
+        let mut ticker = actix_web::rt::time::interval(Duration::from_secs(config.conviction.interval));
+        loop{
+            ticker.tick().await;
+            let options = FindOptions::builder().projection(doc!{"_id": 1}).build();
+            let open = match db.collection::<Document>("events").find(doc!{"is_expired": false, "voting_mode": "conviction"}, options){
+                Ok(cursor) => cursor.filter_map(|event| event.ok()?.get_object_id("_id").ok()).collect::<Vec<ObjectId>>(),
+                Err(e) => {
+                    error!("can't fetch the conviction events {} - {}", e, chrono::Local::now().naive_local());
+                    continue;
+                }
+            };
+            for event_id in open{
+                match refresh(&db, &hub, &config, event_id){
+                    Ok(Some(state)) if state.passed_at.is_some() => info!("conviction event {} passed - {}", event_id, chrono::Local::now().naive_local()),
+                    Ok(_) => {},
+                    Err(e) => error!("can't recompute the conviction of event {} {} - {}", event_id, e, chrono::Local::now().naive_local()),
+                }
+            }
+        }
+    }
+
+}
+
+
+
+
+
+
+
+
+pub mod encryption{
+
+
+    use chrono::Utc;
+    use log::error;
+    use mongodb::{sync::Database, bson::{self, doc, oid::ObjectId, Document}, options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument}};
+    use crate::contexts as ctx;
+    use crate::schemas::{encryption::{EncryptedBallot, EncryptedTally, StoredDecryptionShare}, event::{Counters, EventInfo, Tally}};
+    use super::elgamal;
+
+
+
+    pub fn freeze(db: &Database, event_id: ObjectId) -> mongodb::error::Result<EncryptedTally>{ //-- adds up every encrypted ballot of the event and stores the sum on it for the trustees to decrypt
+        let mut tally = EncryptedTally::default();
+        for ballot in db.collection::<EncryptedBallot>("encrypted_ballots").find(doc!{"event_id": event_id}, None)?{
+            tally.add(&ballot?);
+        }
+        db.collection::<Document>("events").update_one(doc!{"_id": event_id}, doc!{"$set": {"encrypted_tally": bson::to_bson(&tally)?}}, None)?;
+        Ok(tally)
+    }
+
+
+
+    pub fn finalize(db: &Database, hub: &ctx::hub::Hub, config: &ctx::config::Config, event_id: ObjectId) -> mongodb::error::Result<Option<EventInfo>>{ //-- decrypts the tally once enough trustees sent their shares, None until then or if it's already decrypted
+        let events = db.collection::<EventInfo>("events");
+        let event = match events.find_one(doc!{"_id": event_id}, None)?{
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        let (setup, tally) = match (event.encryption.as_ref(), event.encrypted_tally.as_ref()){
+            (Some(setup), Some(tally)) if event.decrypted_at.is_none() => (setup, tally),
+            _ => return Ok(None),
+        };
+        let options = FindOptions::builder().sort(doc!{"trustee": 1}).limit(setup.threshold as i64).build(); //-- any threshold of them gives the same result
+        let shares = db.collection::<StoredDecryptionShare>("decryption_shares").find(doc!{"event_id": event_id}, options)?.collect::<mongodb::error::Result<Vec<StoredDecryptionShare>>>()?;
+        if shares.len() < setup.threshold as usize{
+            return Ok(None);
+        }
+        let mut values = vec![];
+        for (index, (ciphertext, bound)) in tally.ciphertexts().iter().enumerate(){
+            let parts = shares.iter().map(|s| (s.trustee as u64, s.shares[index].share.0)).collect::<Vec<_>>();
+            match elgamal::discrete_log(elgamal::combine(ciphertext, &parts), *bound){
+                Some(value) => values.push(value as i64),
+                None => { //-- can't happen with valid shares of valid ballots
+                    error!("the encrypted tally of event {} doesn't decrypt to a value under {} - {}", event_id, bound, chrono::Local::now().naive_local());
+                    return Ok(None);
+                },
+            }
+        }
+        let counters = Counters{ //-- tallies frozen before abstaining existed only have the two sides
+            upvotes: values[0],
+            upvotes_weight: values[1],
+            downvotes: values[2],
+            downvotes_weight: values[3],
+            abstains: values.get(4).copied().unwrap_or(0),
+            abstains_weight: values.get(5).copied().unwrap_or(0),
+        };
+        if counters.upvotes + counters.downvotes + counters.abstains != tally.ballots as i64 || counters.weight() != tally.total_weight as i64{
+            error!("the decrypted tally of event {} doesn't add up to its {} ballots - {}", event_id, tally.ballots, chrono::Local::now().naive_local());
+            return Ok(None);
+        }
+        let mut update = counters.as_document();
+        update.insert("decrypted_at", Utc::now().timestamp());
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        let decrypted = events.find_one_and_update(doc!{"_id": event_id, "decrypted_at": null}, doc!{"$set": update}, options)?; //-- only one of two concurrent last shares gets to publish the outcome
+        if let Some(event) = decrypted.as_ref(){
+            let tally = Tally::new(event, config.event.quorum);
+            if let Err(e) = super::attestation::attest(db, config, event, &tally){
+                error!("can't attest the result of event {} {} - {}", event_id, e, chrono::Local::now().naive_local());
+            }
+            super::events::emit(db, hub, ctx::hub::Activity::Outcome{
+                proposal_id: event_id.to_hex(),
+                outcome: tally.outcome(true),
+                tally,
+                merkle_root: event.merkle_root.clone(),
+                reveal_report: None,
+                conviction: None,
+            });
+        }
+        Ok(decrypted)
+    }
+
+}
+
+
+
+
+
+
+
+
+pub mod attestation{
+
+
+    use chrono::Utc;
+    use ed25519_dalek::{ExpandedSecretKey, PublicKey, SecretKey};
+    use mongodb::{sync::Database, bson::{doc, Document}, options::{FindOneOptions, ReplaceOptions}};
+    use crate::contexts::config::{AttestationKey, Config};
+    use crate::schemas::{attestation::{self, Attestation, PublicKeyInfo, ResultDocument}, event::{EventInfo, Tally}};
+
+
+
+    pub fn public_key(key: &AttestationKey) -> PublicKey{
+        PublicKey::from(&SecretKey::from_bytes(&key.seed).unwrap()) //-- any 32 bytes are a valid seed
+    }
+
+
+
+    pub fn public_keys(config: &Config) -> Vec<PublicKeyInfo>{
+        config.attestation.keys.iter().map(|key| PublicKeyInfo{
+            key_id: key.id.clone(),
+            algorithm: attestation::ALGORITHM.to_string(),
+            public_key: hex::encode(public_key(key).as_bytes()),
+            active: config.attestation.active.as_ref() == Some(&key.id),
+        }).collect()
+    }
+
+
+
+    pub fn attest(db: &Database, config: &Config, event: &EventInfo, tally: &Tally) -> mongodb::error::Result<Option<Attestation>>{ //-- signs the result of a closed event with the active key and stores it over any older attestation, None if there's no key
+        let key = match config.attestation.active_key(){
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let event_id = event._id.unwrap();
+        let options = FindOneOptions::builder().sort(doc!{"seq": -1}).projection(doc!{"seq": 1}).build();
+        let vote_log_seq = db.collection::<Document>("vote_log").find_one(doc!{"event_id": event_id}, options)?.and_then(|r| r.get_i64("seq").ok());
+        let document = serde_json::to_string(&ResultDocument::new(event, tally, vote_log_seq)).unwrap();
+        let secret = SecretKey::from_bytes(&key.seed).unwrap();
+        let signature = ExpandedSecretKey::from(&secret).sign(document.as_bytes(), &PublicKey::from(&secret));
+        let attestation = Attestation{
+            _id: event_id,
+            key_id: key.id.clone(),
+            algorithm: attestation::ALGORITHM.to_string(),
+            document,
+            signature: hex::encode(signature.to_bytes()),
+            signed_at: Utc::now().timestamp(),
+        };
+        let options = ReplaceOptions::builder().upsert(true).build();
+        db.collection::<Attestation>("attestations").replace_one(doc!{"_id": event_id}, &attestation, options)?;
+        Ok(Some(attestation))
+    }
+
+}
+
+
+
+
+
+
+
+
+pub mod webhooks{
+
+
+    use std::time::{Duration, Instant};
+    use chrono::Utc;
+    use hmac::{Hmac, Mac, NewMac};
+    use log::error;
+    use sha2::Sha256;
+    use actix_web::client::Client;
+    use mongodb::{sync::Database, bson::{self, doc, oid::ObjectId}, options::FindOneAndUpdateOptions};
+    use crate::contexts as ctx;
+    use crate::schemas::webhook::{Delivery, DeliveryAttempt, Webhook, PING};
+
+
+
+    const MAX_LOG: i32 = 20; //-- attempts kept inside the log of a delivery, the oldest ones are dropped first
+
+
+
+    pub fn enqueue(db: &Database, event: &str, body: &str) -> mongodb::error::Result<usize>{ //-- one pending delivery for every active webhook that wants this activity
+        let now = Utc::now().timestamp();
+        let mut deliveries = vec![];
+        for webhook in db.collection::<Webhook>("webhooks").find(Webhook::subscribers_of(event), None)?{
+            if let Some(webhook_id) = webhook?._id{
+                deliveries.push(Delivery::new(webhook_id, event, body.to_string(), now));
+            }
+        }
+        if !deliveries.is_empty(){
+            db.collection::<Delivery>("webhook_deliveries").insert_many(&deliveries, None)?;
+        }
+        Ok(deliveries.len())
+    }
+
+
+
+    pub fn ping(db: &Database, webhook_id: ObjectId) -> mongodb::error::Result<Delivery>{ //-- queues a test delivery, it goes out with the next scan like any other
+        let now = Utc::now().timestamp();
+        let body = serde_json::json!({"type": PING, "at": now, "webhook_id": webhook_id.to_hex()}).to_string();
+        let mut delivery = Delivery::new(webhook_id, PING, body, now);
+        let inserted = db.collection::<Delivery>("webhook_deliveries").insert_one(&delivery, None)?;
+        delivery._id = inserted.inserted_id.as_object_id();
+        Ok(delivery)
+    }
+
+
+
+    pub fn signature(secret: &str, timestamp: i64, body: &str) -> String{ //-- `t=<unix seconds>,v1=<hex hmac-sha256 of "<t>.<body>">`, the timestamp is signed too so an old delivery can't be replayed as a new one
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap(); //-- hmac takes keys of any length
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body.as_bytes());
+        format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+    }
+
+
+
+    pub fn backoff(config: &ctx::config::WebhookConfig, attempts: u32) -> u64{ //-- seconds to wait after the given number of failed attempts
+        let factor = 1_u64.checked_shl(attempts.saturating_sub(1)).unwrap_or(u64::MAX);
+        config.backoff_base.saturating_mul(factor).min(config.backoff_max)
+    }
+
+
+
+    pub async fn deliver(db: Database, config: ctx::config::Config){ //-- sends the due deliveries of the queue, runs for the whole life of the server
+        let client = Client::builder().timeout(Duration::from_secs(config.webhook.timeout)).finish();
+        let mut ticker = actix_web::rt::time::interval(Duration::from_secs(config.webhook.poll_interval));
+        loop{
+            ticker.tick().await;
+            for _ in 0..config.webhook.batch{
+                let delivery = match claim(&db, &config.webhook){
+                    Ok(Some(delivery)) => delivery,
+                    Ok(None) => break, //-- nothing is due
+                    Err(e) => {
+                        error!("can't fetch the due webhook deliveries {} - {}", e, chrono::Local::now().naive_local());
+                        break;
+                    }
+                };
+                let attempt = match db.collection::<Webhook>("webhooks").find_one(doc!{"_id": delivery.webhook_id}, None){
+                    Ok(Some(webhook)) if webhook.active => send(&client, &webhook, &delivery).await,
+                    Ok(Some(_)) => failed("the webhook is disabled"),
+                    Ok(None) => failed("the webhook has been deleted"),
+                    Err(e) => {
+                        error!("can't fetch webhook {} {} - {}", delivery.webhook_id, e, chrono::Local::now().naive_local());
+                        continue; //-- the lease runs out and it's tried again
+                    }
+                };
+                if let Err(e) = record(&db, &config.webhook, &delivery, attempt){
+                    error!("can't record the attempt of webhook delivery {:?} {} - {}", delivery._id, e, chrono::Local::now().naive_local());
+                }
+            }
+        }
+    }
+
+
+
+    fn claim(db: &Database, config: &ctx::config::WebhookConfig) -> mongodb::error::Result<Option<Delivery>>{ //-- leases the oldest due delivery so a second server never sends it at the same time
+        let now = Utc::now().timestamp();
+        let lease = now + 2 * config.timeout as i64;
+        let options = FindOneAndUpdateOptions::builder().sort(doc!{"next_attempt_at": 1}).build();
+        db.collection::<Delivery>("webhook_deliveries").find_one_and_update(doc!{"status": "pending", "next_attempt_at": {"$lte": now}}, doc!{"$set": {"next_attempt_at": lease}}, options)
+    }
+
+
+
+    async fn send(client: &Client, webhook: &Webhook, delivery: &Delivery) -> DeliveryAttempt{
+        let started = Instant::now();
+        let at = Utc::now().timestamp();
+        let request = client.post(webhook.url.as_str())
+                            .header("Content-Type", "application/json")
+                            .header("User-Agent", "biter-webhooks")
+                            .header("X-Biter-Event", delivery.event.as_str())
+                            .header("X-Biter-Delivery", delivery._id.map(|id| id.to_hex()).unwrap_or_default())
+                            .header("X-Biter-Signature", signature(&webhook.secret, at, &delivery.body));
+        let (status_code, error) = match request.send_body(delivery.body.clone()).await{
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+            Ok(response) => (Some(response.status().as_u16()), Some(format!("the receiver answered {}", response.status()))),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        DeliveryAttempt{ at, status_code, error, duration_ms: started.elapsed().as_millis() as u64 }
+    }
+
+
+
+    fn failed(reason: &str) -> DeliveryAttempt{
+        DeliveryAttempt{ at: Utc::now().timestamp(), status_code: None, error: Some(reason.to_string()), duration_ms: 0 }
+    }
+
+
+
+    fn record(db: &Database, config: &ctx::config::WebhookConfig, delivery: &Delivery, attempt: DeliveryAttempt) -> mongodb::error::Result<()>{ //-- delivered on a 2xx, otherwise retried later or dead once it's out of attempts
+        let attempts = delivery.attempts + 1;
+        let set = if attempt.error.is_none(){
+            doc!{"status": "delivered", "delivered_at": attempt.at}
+        } else if attempts >= config.max_attempts{
+            doc!{"status": "dead"}
+        } else{
+            doc!{"next_attempt_at": attempt.at + backoff(config, attempts) as i64}
+        };
+        let update = doc!{
+            "$set": set,
+            "$inc": {"attempts": 1},
+            "$push": {"log": {"$each": [bson::to_bson(&attempt).unwrap()], "$slice": -MAX_LOG}},
+        };
+        db.collection::<Delivery>("webhook_deliveries").update_one(doc!{"_id": delivery._id}, update, None)?;
+        Ok(())
+    }
+
+}
+
+
+
+
+
+
+
+
+
+pub mod auth{
+
+
+    use actix_web::HttpRequest;
+    use chrono::Utc;
+    use ed25519_dalek::{PublicKey, Signature, Verifier};
+    use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+    use crate::contexts as ctx;
+    use crate::schemas::auth::{Claims, Session};
+
+
+
+    const BASE58: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+
+
+    pub fn base58_decode(text: &str) -> Option<Vec<u8>>{ //-- the bitcoin alphabet, every leading 1 is a zero byte
+        let mut bytes: Vec<u8> = vec![]; //-- big endian
+        for c in text.bytes(){
+            let mut carry = BASE58.iter().position(|b| *b == c)? as u32;
+            for byte in bytes.iter_mut().rev(){
+                carry += *byte as u32 * 58;
+                *byte = carry as u8;
+                carry >>= 8;
+            }
+            while carry > 0{
+                bytes.insert(0, carry as u8);
+                carry >>= 8;
+            }
+        }
+        let mut decoded = vec![0; text.bytes().take_while(|c| *c == b'1').count()];
+        decoded.extend(bytes);
+        Some(decoded)
+    }
+
+
+
+    pub fn verify(wallet_address: &str, message: &str, signature: &str) -> bool{ //-- true if the ed25519 key behind the address signed the message
+        let public_key = match base58_decode(wallet_address).and_then(|bytes| PublicKey::from_bytes(&bytes).ok()){
+            Some(public_key) => public_key,
+            None => return false,
+        };
+        match hex::decode(signature).ok().and_then(|s| Signature::from_bytes(&s).ok()){
+            Some(signature) => public_key.verify(message.as_bytes(), &signature).is_ok(),
+            None => false,
+        }
+    }
+
+
+
+    pub fn issue(config: &ctx::config::Config, wallet_address: &str) -> Option<Session>{ //-- None if there's no AUTH_SECRET
+        let secret = config.auth.secret.as_ref()?;
+        let now = Utc::now().timestamp();
+        let claims = Claims{ sub: wallet_address.to_string(), iat: now, exp: now + config.auth.token_ttl };
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).ok()?;
+        Some(Session{ token, wallet_address: claims.sub, expires_at: claims.exp })
+    }
+
+
+
+    pub fn wallet(req: &HttpRequest, config: &ctx::config::Config) -> Option<String>{ //-- the wallet of the `Authorization: Bearer <token>` header, None if it's missing, expired or not signed by us
+        let secret = config.auth.secret.as_ref()?;
+        let token = req.headers().get("Authorization").and_then(|h| h.to_str().ok())?.strip_prefix("Bearer ")?;
+        decode::<Claims>(token.trim(), &DecodingKey::from_secret(secret.as_bytes()), &Validation::default()).ok().map(|data| data.claims.sub)
+    }
+
+
+
+    pub fn moderator(req: &HttpRequest, config: &ctx::config::Config) -> Option<String>{ //-- who is moderating, "admin" with the admin key or a logged in wallet listed in COMMENT_MODERATORS
+        if super::admin::authorized(req, config){
+            return Some("admin".to_string());
+        }
+        wallet(req, config).filter(|wallet| config.comment.moderators.contains(wallet))
+    }
+
+
+
+    #[cfg(test)]
+    mod tests{
+
+        use super::*;
+        use ed25519_dalek::{Keypair, Signer};
+        use rand::rngs::OsRng;
+
+        fn base58_encode(bytes: &[u8]) -> String{ //-- the other way around, only to build addresses here
+            let mut digits: Vec<u8> = vec![]; //-- little endian base 58
+            for byte in bytes{
+                let mut carry = *byte as u32;
+                for digit in digits.iter_mut(){
+                    carry += *digit as u32 * 256;
+                    *digit = (carry % 58) as u8;
+                    carry /= 58;
+                }
+                while carry > 0{
+                    digits.push((carry % 58) as u8);
+                    carry /= 58;
+                }
+            }
+            let zeros = bytes.iter().take_while(|b| **b == 0).count();
+            "1".repeat(zeros) + &digits.iter().rev().map(|d| BASE58[*d as usize] as char).collect::<String>()
+        }
+
+        #[test]
+        fn decodes_known_vectors(){
+            assert_eq!(base58_decode(""), Some(vec![]));
+            assert_eq!(base58_decode("1"), Some(vec![0]));
+            assert_eq!(base58_decode("11"), Some(vec![0, 0]));
+            assert_eq!(base58_decode("2"), Some(vec![1]));
+            assert_eq!(base58_decode("z"), Some(vec![57]));
+            assert_eq!(base58_decode("21"), Some(vec![58]));
+            assert_eq!(base58_decode("5Q"), Some(vec![255]));
+            assert_eq!(base58_decode("StV1DL6CwTryKyV"), Some(b"hello world".to_vec()));
+            assert_eq!(base58_decode("1112"), Some(vec![0, 0, 0, 1]));
+            assert_eq!(base58_decode("11111111111111111111111111111111"), Some(vec![0; 32])); //-- the all zero key
+        }
+
+        #[test]
+        fn refuses_characters_outside_the_alphabet(){
+            for text in ["0", "O", "I", "l", "abc0", "hello world", "+/"]{
+                assert_eq!(base58_decode(text), None, "{}", text);
+            }
+        }
+
+        #[test]
+        fn encoding_round_trips(){
+            for bytes in [vec![], vec![0], vec![0, 0, 7], vec![255; 32], b"hello world".to_vec()]{
+                assert_eq!(base58_decode(&base58_encode(&bytes)), Some(bytes));
+            }
+        }
+
+        #[test]
+        fn verifies_the_signature_of_the_wallet_only(){
+            let keypair = Keypair::generate(&mut OsRng);
+            let wallet_address = base58_encode(keypair.public.as_bytes());
+            let message = "Sign in to biter\nwallet: w\nnonce: n\nexpires at: 0";
+            let signature = hex::encode(keypair.sign(message.as_bytes()).to_bytes());
+            assert!(verify(&wallet_address, message, &signature));
+            assert!(!verify(&wallet_address, "another message", &signature));
+            let other = base58_encode(Keypair::generate(&mut OsRng).public.as_bytes());
+            assert!(!verify(&other, message, &signature));
+            assert!(!verify(&wallet_address, message, "not hex"));
+            assert!(!verify("0OIl", message, &signature));
+        }
+    }
+
 }
 
+
+
+
+
+
+
+
+pub mod admin{
+
+
+    use actix_web::HttpRequest;
+    use crate::contexts as ctx;
+
+
+
+    pub fn authorized(req: &HttpRequest, config: &ctx::config::Config) -> bool{ //-- the admin key goes inside the X-Admin-Key header, nobody is an admin if it's not configured
+        let given = req.headers().get("X-Admin-Key").and_then(|h| h.to_str().ok()).unwrap_or_default();
+        match config.admin_key.as_ref(){
+            Some(key) => key.len() == given.len() && key.bytes().zip(given.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0, //-- compared in constant time
+            None => false,
+        }
+    }
+
+}
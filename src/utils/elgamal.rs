@@ -0,0 +1,417 @@
+
+
+
+use std::collections::HashMap;
+use serde::{de, Serialize, Serializer, Deserialize, Deserializer};
+use sha2::Sha512;
+use rand::{CryptoRng, RngCore};
+use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, ristretto::{CompressedRistretto, RistrettoPoint}, scalar::Scalar, traits::Identity};
+
+
+
+// NOTE - exponential elgamal over ristretto255, a message m is encrypted to the public key H as (r·G, m·G + r·H) so adding two ciphertexts adds their messages, the sum is decrypted back into m·G and m is found with a bounded discrete log
+// NOTE - a ballot is one ciphertext per choice encrypting 1 for the chosen one and 0 for the others, each ciphertext carries an OR proof that it's 0 or 1 and the ballot carries a chaum-pedersen proof that they add up to exactly 1
+// NOTE - the key is shared between trustees with a feldman commitment C_0..C_{t-1} to a polynomial of degree t-1, H = C_0 and trustee i holds f(i) whose public part f(i)·G = sum of C_k·i^k anyone can compute, any t of them can decrypt
+// NOTE - the challenges are sha512 of a domain, a label, a context (the proposal and the wallet or the trustee) and the points, so a proof can't be replayed for another voter, proposal or statement
+// NOTE - like merkle.rs this file only depends on serde, sha2, hex, rand and curve25519-dalek so voters and trustees can include it with #[path], see examples/elgamal_ballot.rs
+
+
+
+const DOMAIN: &[u8] = b"biter-elgamal-v1";
+
+pub const G: RistrettoPoint = RISTRETTO_BASEPOINT_POINT;
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Point(pub RistrettoPoint); //-- hex of the 32 bytes compressed point once serialized
+
+impl Serialize for Point{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>{
+        serializer.serialize_str(&hex::encode(self.0.compress().as_bytes()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Point{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Point, D::Error>{
+        let encoded = String::deserialize(deserializer)?;
+        hex::decode(&encoded).ok()
+            .filter(|bytes| bytes.len() == 32)
+            .and_then(|bytes| CompressedRistretto::from_slice(&bytes).decompress())
+            .map(Point)
+            .ok_or_else(|| de::Error::custom("expected a ristretto255 point as 64 hex chars"))
+    }
+}
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exponent(pub Scalar); //-- hex of the 32 bytes canonical scalar once serialized
+
+impl Serialize for Exponent{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>{
+        serializer.serialize_str(&hex::encode(self.0.as_bytes()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Exponent{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Exponent, D::Error>{
+        let encoded = String::deserialize(deserializer)?;
+        hex::decode(&encoded).ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(Scalar::from_canonical_bytes)
+            .map(Exponent)
+            .ok_or_else(|| de::Error::custom("expected a canonical ristretto255 scalar as 64 hex chars"))
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ciphertext{
+    pub a: Point, //-- r·G
+    pub b: Point, //-- m·G + r·H
+}
+
+impl Ciphertext{
+
+    pub fn zero() -> Ciphertext{ //-- the encryption of 0 with no randomness, the neutral element of add()
+        Ciphertext{ a: Point(RistrettoPoint::identity()), b: Point(RistrettoPoint::identity()) }
+    }
+
+    pub fn add(&self, other: &Ciphertext) -> Ciphertext{
+        Ciphertext{ a: Point(self.a.0 + other.a.0), b: Point(self.b.0 + other.b.0) }
+    }
+
+    pub fn scale(&self, factor: u64) -> Ciphertext{ //-- encrypts factor times the message
+        let factor = Scalar::from(factor);
+        Ciphertext{ a: Point(self.a.0 * factor), b: Point(self.b.0 * factor) }
+    }
+}
+
+
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct EqualityProof{ //-- chaum-pedersen, the same secret x is behind h1 = x·g1 and h2 = x·g2
+    pub c: Exponent,
+    pub z: Exponent,
+}
+
+
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct BitProof{ //-- the ciphertext encrypts 0 or 1, one of the two branches is simulated
+    pub c0: Exponent,
+    pub c1: Exponent,
+    pub z0: Exponent,
+    pub z1: Exponent,
+}
+
+
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BallotProof{
+    pub bits: Vec<BitProof>, //-- one per ciphertext, in the same order
+    pub sum: EqualityProof, //-- the ciphertexts add up to an encryption of 1
+}
+
+
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct DecryptionShare{
+    pub share: Point, //-- f(i)·A of the ciphertext
+    pub proof: EqualityProof, //-- the same f(i) is behind the verification key of the trustee
+}
+
+
+
+pub fn ballot_context(proposal_id: &str, wallet_address: &str) -> Vec<u8>{
+    format!("ballot\n{}\n{}", proposal_id, wallet_address).into_bytes()
+}
+
+
+
+pub fn share_context(proposal_id: &str, trustee: u64) -> Vec<u8>{
+    format!("share\n{}\n{}", proposal_id, trustee).into_bytes()
+}
+
+
+
+fn challenge(label: &[u8], context: &[u8], points: &[RistrettoPoint]) -> Scalar{
+    let mut input = DOMAIN.to_vec();
+    for part in [label, context]{
+        input.extend_from_slice(&(part.len() as u64).to_le_bytes()); //-- length prefixed so the parts can't slide into each other
+        input.extend_from_slice(part);
+    }
+    for point in points{
+        input.extend_from_slice(point.compress().as_bytes());
+    }
+    Scalar::hash_from_bytes::<Sha512>(&input)
+}
+
+
+
+fn prove_equal<R: RngCore + CryptoRng>(context: &[u8], g1: RistrettoPoint, h1: RistrettoPoint, g2: RistrettoPoint, h2: RistrettoPoint, x: &Scalar, rng: &mut R) -> EqualityProof{
+    let w = Scalar::random(rng);
+    let c = challenge(b"equal", context, &[g1, h1, g2, h2, w * g1, w * g2]);
+    EqualityProof{ c: Exponent(c), z: Exponent(w + c * x) }
+}
+
+
+
+fn verify_equal(context: &[u8], g1: RistrettoPoint, h1: RistrettoPoint, g2: RistrettoPoint, h2: RistrettoPoint, proof: &EqualityProof) -> bool{
+    let (c, z) = (proof.c.0, proof.z.0);
+    c == challenge(b"equal", context, &[g1, h1, g2, h2, z * g1 - c * h1, z * g2 - c * h2])
+}
+
+
+
+fn bit_commitments(public_key: RistrettoPoint, ciphertext: &Ciphertext, bit: u64, c: Scalar, z: Scalar) -> (RistrettoPoint, RistrettoPoint){ //-- what the prover committed to in the branch `bit` given its challenge and response
+    (z * G - c * ciphertext.a.0, z * public_key - c * (ciphertext.b.0 - Scalar::from(bit) * G))
+}
+
+
+
+fn prove_bit<R: RngCore + CryptoRng>(context: &[u8], public_key: RistrettoPoint, ciphertext: &Ciphertext, bit: u64, r: &Scalar, rng: &mut R) -> BitProof{
+    let (c_fake, z_fake, w) = (Scalar::random(rng), Scalar::random(rng), Scalar::random(rng));
+    let fake = bit_commitments(public_key, ciphertext, 1 - bit, c_fake, z_fake);
+    let real = (w * G, w * public_key);
+    let (t0, t1) = if bit == 0 { (real, fake) } else { (fake, real) };
+    let c = challenge(b"bit", context, &[public_key, ciphertext.a.0, ciphertext.b.0, t0.0, t0.1, t1.0, t1.1]);
+    let c_real = c - c_fake;
+    let z_real = w + c_real * r;
+    if bit == 0{
+        BitProof{ c0: Exponent(c_real), c1: Exponent(c_fake), z0: Exponent(z_real), z1: Exponent(z_fake) }
+    } else{
+        BitProof{ c0: Exponent(c_fake), c1: Exponent(c_real), z0: Exponent(z_fake), z1: Exponent(z_real) }
+    }
+}
+
+
+
+fn verify_bit(context: &[u8], public_key: RistrettoPoint, ciphertext: &Ciphertext, proof: &BitProof) -> bool{
+    let t0 = bit_commitments(public_key, ciphertext, 0, proof.c0.0, proof.z0.0);
+    let t1 = bit_commitments(public_key, ciphertext, 1, proof.c1.0, proof.z1.0);
+    proof.c0.0 + proof.c1.0 == challenge(b"bit", context, &[public_key, ciphertext.a.0, ciphertext.b.0, t0.0, t0.1, t1.0, t1.1])
+}
+
+
+
+#[allow(dead_code)] //-- the server never encrypts, this is for the voters
+pub fn encrypt_ballot<R: RngCore + CryptoRng>(public_key: RistrettoPoint, choices: usize, choice: usize, context: &[u8], rng: &mut R) -> (Vec<Ciphertext>, BallotProof){
+    let mut ciphertexts = vec![];
+    let mut bits = vec![];
+    let mut total = Scalar::zero();
+    for index in 0..choices{
+        let bit = (index == choice) as u64;
+        let r = Scalar::random(rng);
+        let ciphertext = Ciphertext{ a: Point(r * G), b: Point(Scalar::from(bit) * G + r * public_key) };
+        bits.push(prove_bit(context, public_key, &ciphertext, bit, &r, rng));
+        ciphertexts.push(ciphertext);
+        total += r;
+    }
+    let sum = ciphertexts.iter().fold(Ciphertext::zero(), |sum, c| sum.add(c));
+    let proof = BallotProof{
+        sum: prove_equal(context, G, sum.a.0, public_key, sum.b.0 - G, &total, rng),
+        bits,
+    };
+    (ciphertexts, proof)
+}
+
+
+
+pub fn verify_ballot(public_key: RistrettoPoint, choices: usize, ciphertexts: &[Ciphertext], proof: &BallotProof, context: &[u8]) -> bool{
+    if ciphertexts.len() != choices || proof.bits.len() != choices{
+        return false;
+    }
+    if !ciphertexts.iter().zip(&proof.bits).all(|(ciphertext, bit)| verify_bit(context, public_key, ciphertext, bit)){
+        return false;
+    }
+    let sum = ciphertexts.iter().fold(Ciphertext::zero(), |sum, c| sum.add(c));
+    verify_equal(context, G, sum.a.0, public_key, sum.b.0 - G, &proof.sum)
+}
+
+
+
+pub fn verification_key(commitments: &[RistrettoPoint], trustee: u64) -> RistrettoPoint{ //-- f(trustee)·G from the feldman commitments
+    let x = Scalar::from(trustee);
+    commitments.iter().rev().fold(RistrettoPoint::identity(), |acc, commitment| acc * x + commitment)
+}
+
+
+
+#[allow(dead_code)] //-- the server never holds a key share, this is for the trustees
+pub fn decryption_share<R: RngCore + CryptoRng>(secret: &Scalar, ciphertext: &Ciphertext, context: &[u8], rng: &mut R) -> DecryptionShare{
+    let share = secret * ciphertext.a.0;
+    DecryptionShare{
+        share: Point(share),
+        proof: prove_equal(context, G, secret * G, ciphertext.a.0, share, secret, rng),
+    }
+}
+
+
+
+pub fn verify_share(verification_key: RistrettoPoint, ciphertext: &Ciphertext, share: &DecryptionShare, context: &[u8]) -> bool{
+    verify_equal(context, G, verification_key, ciphertext.a.0, share.share.0, &share.proof)
+}
+
+
+
+pub fn lagrange_at_zero(trustee: u64, trustees: &[u64]) -> Scalar{
+    let i = Scalar::from(trustee);
+    trustees.iter().filter(|&&j| j != trustee).fold(Scalar::one(), |acc, &j| {
+        let j = Scalar::from(j);
+        acc * j * (j - i).invert()
+    })
+}
+
+
+
+pub fn combine(ciphertext: &Ciphertext, shares: &[(u64, RistrettoPoint)]) -> RistrettoPoint{ //-- m·G out of the shares of distinct trustees, at least as many as the threshold
+    let trustees = shares.iter().map(|(trustee, _)| *trustee).collect::<Vec<u64>>();
+    let key_times_a = shares.iter().fold(RistrettoPoint::identity(), |acc, (trustee, share)| acc + lagrange_at_zero(*trustee, &trustees) * share);
+    ciphertext.b.0 - key_times_a
+}
+
+
+
+pub fn discrete_log(point: RistrettoPoint, bound: u64) -> Option<u64>{ //-- baby step giant step for m in 0..=bound
+    let step = ((bound as f64 + 1.0).sqrt().ceil() as u64).max(1);
+    let mut baby = HashMap::with_capacity(step as usize);
+    let mut current = RistrettoPoint::identity();
+    for j in 0..step{
+        baby.insert(current.compress().to_bytes(), j);
+        current += G;
+    }
+    let giant = Scalar::from(step) * G;
+    let mut current = point;
+    for i in 0..=step{
+        if let Some(j) = baby.get(current.compress().as_bytes()){
+            let m = i * step + j;
+            return if m <= bound { Some(m) } else { None };
+        }
+        current -= giant;
+    }
+    None
+}
+
+
+
+#[allow(dead_code)] //-- a trusted dealer only fits tests and demos, real trustees should run a distributed key generation
+pub fn deal<R: RngCore + CryptoRng>(threshold: usize, trustees: u64, rng: &mut R) -> (Vec<RistrettoPoint>, Vec<Scalar>){ //-- the feldman commitments and the share of each trustee, trustee i gets shares[i - 1]
+    let coefficients = (0..threshold).map(|_| Scalar::random(rng)).collect::<Vec<Scalar>>();
+    let commitments = coefficients.iter().map(|c| c * G).collect();
+    let shares = (1..=trustees).map(|i| {
+        let x = Scalar::from(i);
+        coefficients.iter().rev().fold(Scalar::zero(), |acc, c| acc * x + c)
+    }).collect();
+    (commitments, shares)
+}
+
+
+
+#[cfg(test)]
+mod tests{
+
+    use super::*;
+    use rand::rngs::OsRng;
+
+    const CHOICES: usize = 3;
+
+    fn tally(public_key: RistrettoPoint, votes: &[usize]) -> Vec<Ciphertext>{ //-- every ballot verified then summed per choice
+        let mut sums = vec![Ciphertext::zero(); CHOICES];
+        for (voter, choice) in votes.iter().enumerate(){
+            let context = ballot_context("proposal", &format!("wallet{}", voter));
+            let (ciphertexts, proof) = encrypt_ballot(public_key, CHOICES, *choice, &context, &mut OsRng);
+            assert!(verify_ballot(public_key, CHOICES, &ciphertexts, &proof, &context));
+            for (sum, ciphertext) in sums.iter_mut().zip(&ciphertexts){
+                *sum = sum.add(ciphertext);
+            }
+        }
+        sums
+    }
+
+    #[test]
+    fn encrypt_sum_and_threshold_decrypt(){
+        let (commitments, secrets) = deal(3, 5, &mut OsRng);
+        let votes = [0, 2, 2, 1, 2, 0, 2];
+        let sums = tally(commitments[0], &votes);
+        for (choice, sum) in sums.iter().enumerate(){
+            let context = share_context("proposal", 0);
+            let shares = [2u64, 4, 5].iter().map(|&trustee| {
+                let share = decryption_share(&secrets[trustee as usize - 1], sum, &context, &mut OsRng);
+                assert!(verify_share(verification_key(&commitments, trustee), sum, &share, &context));
+                (trustee, share.share.0)
+            }).collect::<Vec<_>>();
+            let expected = votes.iter().filter(|&&v| v == choice).count() as u64;
+            assert_eq!(discrete_log(combine(sum, &shares), votes.len() as u64), Some(expected));
+        }
+    }
+
+    #[test]
+    fn fewer_shares_than_the_threshold_decrypt_to_garbage(){
+        let (commitments, secrets) = deal(3, 5, &mut OsRng);
+        let sums = tally(commitments[0], &[1, 1]);
+        let shares = [1u64, 3].iter().map(|&trustee| (trustee, secrets[trustee as usize - 1] * sums[1].a.0)).collect::<Vec<_>>();
+        assert_eq!(discrete_log(combine(&sums[1], &shares), 2), None);
+    }
+
+    #[test]
+    fn lagrange_coefficients_rebuild_the_secret(){
+        let (commitments, secrets) = deal(2, 3, &mut OsRng);
+        let trustees = [1u64, 3];
+        let secret = trustees.iter().fold(Scalar::zero(), |acc, &t| acc + lagrange_at_zero(t, &trustees) * secrets[t as usize - 1]);
+        assert_eq!(secret * G, commitments[0]);
+    }
+
+    #[test]
+    fn ballot_for_two_choices_is_rejected(){
+        let (commitments, _) = deal(1, 1, &mut OsRng);
+        let public_key = commitments[0];
+        let context = ballot_context("proposal", "wallet");
+        let (mut ciphertexts, proof) = encrypt_ballot(public_key, CHOICES, 0, &context, &mut OsRng);
+        let (other, _) = encrypt_ballot(public_key, CHOICES, 1, &context, &mut OsRng);
+        ciphertexts[1] = other[1]; //-- now it encrypts 1 twice
+        assert!(!verify_ballot(public_key, CHOICES, &ciphertexts, &proof, &context));
+    }
+
+    #[test]
+    fn scaled_ballot_is_rejected(){
+        let (commitments, _) = deal(1, 1, &mut OsRng);
+        let public_key = commitments[0];
+        let context = ballot_context("proposal", "wallet");
+        let (ciphertexts, proof) = encrypt_ballot(public_key, CHOICES, 2, &context, &mut OsRng);
+        let stuffed = ciphertexts.iter().map(|c| c.scale(5)).collect::<Vec<_>>();
+        assert!(!verify_ballot(public_key, CHOICES, &stuffed, &proof, &context));
+    }
+
+    #[test]
+    fn proof_is_bound_to_its_voter(){
+        let (commitments, _) = deal(1, 1, &mut OsRng);
+        let public_key = commitments[0];
+        let (ciphertexts, proof) = encrypt_ballot(public_key, CHOICES, 0, &ballot_context("proposal", "alice"), &mut OsRng);
+        assert!(!verify_ballot(public_key, CHOICES, &ciphertexts, &proof, &ballot_context("proposal", "mallory")));
+        assert!(!verify_ballot(public_key, CHOICES - 1, &ciphertexts[..CHOICES - 1], &proof, &ballot_context("proposal", "alice")));
+    }
+
+    #[test]
+    fn share_from_the_wrong_secret_is_rejected(){
+        let (commitments, secrets) = deal(2, 3, &mut OsRng);
+        let sums = tally(commitments[0], &[0]);
+        let context = share_context("proposal", 2);
+        let share = decryption_share(&secrets[0], &sums[0], &context, &mut OsRng); //-- trustee 1 posing as trustee 2
+        assert!(!verify_share(verification_key(&commitments, 2), &sums[0], &share, &context));
+        let share = decryption_share(&secrets[1], &sums[0], &context, &mut OsRng);
+        assert!(verify_share(verification_key(&commitments, 2), &sums[0], &share, &context));
+        assert!(!verify_share(verification_key(&commitments, 2), &sums[0], &share, &share_context("proposal", 3)));
+    }
+
+    #[test]
+    fn discrete_log_stays_within_its_bound(){
+        for m in [0u64, 1, 2, 15, 16, 17, 99, 100]{
+            assert_eq!(discrete_log(Scalar::from(m) * G, 100), Some(m));
+        }
+        assert_eq!(discrete_log(Scalar::from(101u64) * G, 100), None);
+        assert_eq!(discrete_log(Scalar::from(5000u64) * G, 100), None);
+    }
+}
@@ -0,0 +1,228 @@
+
+
+
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+
+
+// NOTE - this file only depends on serde, sha2 and hex so other services can copy it (or include it with #[path]) to verify the proofs offline, see examples/verify_ballot_proof.rs
+// NOTE - a leaf is sha256(0x00 || "<event id hex>\n<wallet address>\n<1 for up, 0 for down, 2 for abstain>\n<score>\n<voted_at>") and a node is sha256(0x01 || left || right), the prefixes stop a leaf from being passed off as a node
+// NOTE - the leaves are sorted by wallet address (byte order), a node without a sibling is carried up to the next level as is, the root of an empty tree is sha256 of nothing
+
+
+
+pub type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+
+
+pub fn leaf(event_id: &str, wallet_address: &str, choice: u8, score: u32, voted_at: i64) -> Hash{
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(format!("{}\n{}\n{}\n{}\n{}", event_id, wallet_address, choice, score, voted_at).as_bytes());
+    hasher.finalize().into()
+}
+
+
+
+pub fn node(left: &Hash, right: &Hash) -> Hash{
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Side{
+    Left, //-- the sibling goes on the left of the running hash
+    Right,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProofStep{
+    pub hash: String, //-- hex
+    pub side: Side,
+}
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InclusionProof{
+    pub event_id: String,
+    pub wallet_address: String,
+    pub is_upvote: bool,
+    #[serde(default)]
+    pub abstain: bool,
+    pub score: u32,
+    pub voted_at: i64,
+    pub leaf: String, //-- hex
+    pub index: u64, //-- position of the leaf in the canonical order
+    pub leaves: u64,
+    pub path: Vec<ProofStep>, //-- from the leaf up to the root
+    pub root: String, //-- hex, the root published when the proposal closed
+}
+
+
+
+pub struct Tree{
+    levels: Vec<Vec<Hash>>, //-- the leaves first, the root level last
+}
+
+impl Tree{
+
+    pub fn new(leaves: Vec<Hash>) -> Tree{
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1{
+            let next = levels.last().unwrap().chunks(2).map(|pair| match pair{
+                [left, right] => node(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            }).collect();
+            levels.push(next);
+        }
+        Tree{ levels }
+    }
+
+    pub fn root(&self) -> Hash{
+        match self.levels.last().unwrap().first(){
+            Some(root) => *root,
+            None => Sha256::digest(b"").into(),
+        }
+    }
+
+    pub fn leaves(&self) -> usize{
+        self.levels[0].len()
+    }
+
+    pub fn proof(&self, mut index: usize) -> Vec<ProofStep>{ //-- the siblings from the leaf at index up to the root
+        let mut path = vec![];
+        for level in &self.levels[..self.levels.len() - 1]{
+            let sibling = index ^ 1;
+            if sibling < level.len(){
+                path.push(ProofStep{
+                    hash: hex::encode(level[sibling]),
+                    side: if sibling < index { Side::Left } else { Side::Right },
+                });
+            }
+            index /= 2;
+        }
+        path
+    }
+}
+
+
+
+#[allow(dead_code)] //-- the server only builds proofs, this is for whoever checks them
+pub fn verify(proof: &InclusionProof) -> bool{ //-- true if the ballot inside the proof hashes to its leaf and the leaf climbs up to the root
+    let choice = if proof.abstain { 2 } else { proof.is_upvote as u8 };
+    let expected = leaf(&proof.event_id, &proof.wallet_address, choice, proof.score, proof.voted_at);
+    if hex::encode(expected) != proof.leaf.to_lowercase(){
+        return false;
+    }
+    let mut running = expected;
+    for step in &proof.path{
+        let sibling: Hash = match hex::decode(&step.hash).ok().and_then(|h| h.try_into().ok()){
+            Some(sibling) => sibling,
+            None => return false,
+        };
+        running = match step.side{
+            Side::Left => node(&sibling, &running),
+            Side::Right => node(&running, &sibling),
+        };
+    }
+    hex::encode(running) == proof.root.to_lowercase()
+}
+
+
+
+#[cfg(test)]
+mod tests{
+
+    use super::*;
+
+    fn ballots(n: usize) -> Vec<(String, bool, bool, u32, i64)>{ //-- already in wallet order
+        (0..n).map(|i| (format!("wallet{:02}", i), i % 2 == 0, i % 5 == 4, i as u32 + 1, 1_700_000_000 + i as i64)).collect()
+    }
+
+    fn proofs(n: usize) -> Vec<InclusionProof>{
+        let event_id = "64b7f0c2a1b2c3d4e5f60718";
+        let ballots = ballots(n);
+        let leaves = ballots.iter().map(|(w, up, abstain, score, at)| leaf(event_id, w, if *abstain { 2 } else { *up as u8 }, *score, *at)).collect::<Vec<Hash>>();
+        let tree = Tree::new(leaves.clone());
+        let root = hex::encode(tree.root());
+        ballots.iter().enumerate().map(|(index, (w, up, abstain, score, at))| InclusionProof{
+            event_id: event_id.to_string(),
+            wallet_address: w.clone(),
+            is_upvote: *up,
+            abstain: *abstain,
+            score: *score,
+            voted_at: *at,
+            leaf: hex::encode(leaves[index]),
+            index: index as u64,
+            leaves: n as u64,
+            path: tree.proof(index),
+            root: root.clone(),
+        }).collect()
+    }
+
+    #[test]
+    fn every_proof_verifies_whatever_the_leaf_count(){
+        for n in 1..=9{
+            for proof in proofs(n){
+                assert!(verify(&proof), "leaf {} of {}", proof.index, n);
+            }
+        }
+    }
+
+    #[test]
+    fn odd_node_is_carried_up(){
+        let leaves = (0..3u8).map(|i| leaf("e", &i.to_string(), 1, 1, 0)).collect::<Vec<Hash>>();
+        let tree = Tree::new(leaves.clone());
+        assert_eq!(tree.root(), node(&node(&leaves[0], &leaves[1]), &leaves[2]));
+        assert_eq!(tree.proof(2).len(), 1); //-- no sibling on the first level
+    }
+
+    #[test]
+    fn empty_and_single_trees(){
+        let empty: Hash = Sha256::digest(b"").into();
+        assert_eq!(Tree::new(vec![]).root(), empty);
+        let single = leaf("e", "w", 0, 3, 9);
+        assert_eq!(Tree::new(vec![single]).root(), single);
+        assert!(Tree::new(vec![single]).proof(0).is_empty());
+    }
+
+    #[test]
+    fn tampered_ballot_fails(){
+        let mut proof = proofs(6).remove(3);
+        proof.score += 1;
+        assert!(!verify(&proof));
+        let mut proof = proofs(6).remove(3);
+        proof.abstain = !proof.abstain;
+        assert!(!verify(&proof));
+    }
+
+    #[test]
+    fn tampered_path_fails(){
+        let mut proof = proofs(7).remove(2);
+        proof.path[0].hash = hex::encode([7u8; 32]);
+        assert!(!verify(&proof));
+        let mut proof = proofs(7).remove(2);
+        proof.path[1].side = if proof.path[1].side == Side::Left { Side::Right } else { Side::Left };
+        assert!(!verify(&proof));
+        let mut proof = proofs(7).remove(2);
+        proof.path.pop();
+        assert!(!verify(&proof));
+        let mut proof = proofs(7).remove(2);
+        proof.path[0].hash = "not hex".to_string();
+        assert!(!verify(&proof));
+    }
+}